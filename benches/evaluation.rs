@@ -0,0 +1,56 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use num_bigint::BigInt;
+use simple_calculator::{
+    eval_assignment, parse_assignment, tokenize, CalcError, Value, VariableStore,
+};
+
+/// Clones the values on every lookup, the way the evaluator used to
+struct CloningStore(HashMap<String, Value>);
+
+impl VariableStore for CloningStore {
+    fn get(&self, var: &str) -> Option<Cow<'_, Value>> {
+        self.0.get(var).cloned().map(Cow::Owned)
+    }
+
+    fn set(&mut self, var: &str, val: Value) -> Result<(), CalcError> {
+        self.0.set(var, val)
+    }
+}
+
+/// Variables `a`, `b` and `c` with the given number of decimal digits
+fn variables(digits: usize) -> HashMap<String, Value> {
+    ["a", "b", "c"]
+        .iter()
+        .enumerate()
+        .map(|(i, var)| {
+            let val: BigInt = "9".repeat(digits).parse::<BigInt>().unwrap() - i;
+            (var.to_string(), Value::Int(val))
+        })
+        .collect()
+}
+
+fn bench_large_operands(c: &mut Criterion) {
+    // Additions and bitwise operations are linear, so copying the operands is a large part of the cost.
+    // The expression is parsed once and evaluated again in every iteration.
+    let line = "((a + b) - c) | (b + c)";
+    let ass = parse_assignment(&tokenize(line).unwrap()).unwrap();
+
+    let mut group = c.benchmark_group("large_operands");
+    for digits in [1_000, 10_000, 100_000] {
+        let mut borrowing = variables(digits);
+        group.bench_with_input(BenchmarkId::new("borrowed", digits), &ass, |bench, ass| {
+            bench.iter(|| eval_assignment(ass, &mut borrowing).unwrap())
+        });
+
+        let mut cloning = CloningStore(variables(digits));
+        group.bench_with_input(BenchmarkId::new("cloned", digits), &ass, |bench, ass| {
+            bench.iter(|| eval_assignment(ass, &mut cloning).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_large_operands);
+criterion_main!(benches);
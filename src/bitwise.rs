@@ -0,0 +1,278 @@
+use num_bigint::{BigInt, Sign};
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::{error::CalcError, evaluator::Evaluation};
+
+fn invalid_argument(function: &str, reason: &str) -> CalcError {
+    CalcError::InvalidArgument(function.to_string(), reason.to_string())
+}
+
+/// 2^width - 1
+pub fn mask(width: u64) -> BigInt {
+    (BigInt::one() << width) - 1
+}
+
+/// A bit index or width argument
+fn index(n: &BigInt, function: &str) -> Result<u64, CalcError> {
+    if n.is_negative() {
+        return Err(invalid_argument(
+            function,
+            "bit positions cannot be negative",
+        ));
+    }
+    n.to_u64()
+        .ok_or_else(|| invalid_argument(function, "bit position is too large"))
+}
+
+/// The value as an unsigned number of the given width. Negative values are taken in two's
+/// complement, so anything from -2^(width - 1) to 2^width - 1 fits.
+pub fn to_width(
+    x: &BigInt,
+    width: u64,
+    function: &str,
+    ctx: &Evaluation,
+) -> Result<BigInt, CalcError> {
+    let fits = match x.sign() {
+        Sign::Minus => (-x - 1u32).bits() < width,
+        _ => x.bits() <= width,
+    };
+    if !fits {
+        return Err(invalid_argument(
+            function,
+            &format!("{} does not fit in {} bits", x, width),
+        ));
+    }
+    if x.is_negative() {
+        ctx.check_bits(width)?;
+        Ok(x + (BigInt::one() << width))
+    } else {
+        Ok(x.clone())
+    }
+}
+
+/// Negative values are taken in two's complement within the session width
+fn unsigned(x: &BigInt, function: &str, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    if x.is_negative() {
+        to_width(x, u64::from(ctx.width()?), function, ctx)
+    } else {
+        Ok(x.clone())
+    }
+}
+
+/// Number of the set bits
+pub fn popcount(x: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let x = unsigned(x, "popcount", ctx)?;
+    Ok(x.magnitude().count_ones().into())
+}
+
+/// Number of the zero bits above the highest set bit in a value of the given width
+pub fn leading_zeros(x: &BigInt, width: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let width = index(width, "clz")?;
+    let x = to_width(x, width, "clz", ctx)?;
+    Ok((width - x.bits()).into())
+}
+
+/// Number of the zero bits below the lowest set bit, the width for zero
+pub fn trailing_zeros(x: &BigInt, width: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let width = index(width, "ctz")?;
+    let x = to_width(x, width, "ctz", ctx)?;
+    Ok(x.trailing_zeros().unwrap_or(width).into())
+}
+
+/// Minimum number of bits to write |x|
+pub fn bit_length(x: &BigInt) -> BigInt {
+    x.bits().into()
+}
+
+/// 1 if the bit is set, using two's complement for negative values
+pub fn bit(x: &BigInt, n: &BigInt) -> Result<BigInt, CalcError> {
+    let n = index(n, "bit")?;
+    Ok(u8::from(x.bit(n)).into())
+}
+
+/// The value with the bit changed by `f`, which gets the current bit
+pub fn change_bit<F>(
+    x: &BigInt,
+    n: &BigInt,
+    function: &str,
+    f: F,
+    ctx: &Evaluation,
+) -> Result<BigInt, CalcError>
+where
+    F: Fn(bool) -> bool,
+{
+    let n = index(n, function)?;
+    ctx.check_bits(n.saturating_add(1))?;
+    let mut x = x.clone();
+    x.set_bit(n, f(x.bit(n)));
+    Ok(x)
+}
+
+/// The range of bits from hi down to lo, both inclusive
+fn field_range(hi: &BigInt, lo: &BigInt, function: &str) -> Result<(u64, u64), CalcError> {
+    let (hi, lo) = (index(hi, function)?, index(lo, function)?);
+    if hi < lo {
+        return Err(invalid_argument(
+            function,
+            "the high bit must not be below the low bit",
+        ));
+    }
+    let width = (hi - lo)
+        .checked_add(1)
+        .ok_or_else(|| invalid_argument(function, "bit position is too large"))?;
+    Ok((lo, width))
+}
+
+/// Bits hi..=lo of the value, shifted down to bit 0
+pub fn extract(
+    x: &BigInt,
+    hi: &BigInt,
+    lo: &BigInt,
+    ctx: &Evaluation,
+) -> Result<BigInt, CalcError> {
+    let (lo, width) = field_range(hi, lo, "bits")?;
+    let shifted = x >> lo;
+    if !shifted.is_negative() && shifted.bits() <= width {
+        return Ok(shifted);
+    }
+    // Negative values have ones in every bit of the field
+    ctx.check_bits(width)?;
+    Ok(shifted & mask(width))
+}
+
+/// The value with bits hi..=lo replaced by v
+pub fn insert(
+    x: &BigInt,
+    v: &BigInt,
+    hi: &BigInt,
+    lo: &BigInt,
+    ctx: &Evaluation,
+) -> Result<BigInt, CalcError> {
+    let (lo, width) = field_range(hi, lo, "insert")?;
+    ctx.check_bits(lo.saturating_add(width))?;
+    let v = to_width(v, width, "insert", ctx)?;
+    Ok((x & !(mask(width) << lo)) | (v << lo))
+}
+
+/// Reverses the order of the bytes of a value of the given width
+pub fn swap_bytes(
+    x: &BigInt,
+    width: u32,
+    function: &str,
+    ctx: &Evaluation,
+) -> Result<BigInt, CalcError> {
+    let x = to_width(x, u64::from(width), function, ctx)?
+        .to_u64()
+        .unwrap_or(0);
+    Ok(match width {
+        16 => (x as u16).swap_bytes().into(),
+        32 => (x as u32).swap_bytes().into(),
+        _ => x.swap_bytes().into(),
+    })
+}
+
+/// Reverses the order of the lowest `width` bits
+pub fn reverse_bits(x: &BigInt, width: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let width = index(width, "bitrev")?;
+    ctx.check_bits(width)?;
+    let x = to_width(x, width, "bitrev", ctx)?;
+
+    let mut reversed = BigInt::zero();
+    for n in 0..x.bits() {
+        if x.bit(n) {
+            reversed.set_bit(width - 1 - n, true);
+        }
+    }
+    Ok(reversed)
+}
+
+/// Smallest power of two which is not smaller than the value
+pub fn next_power_of_two(x: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    if x.is_negative() {
+        return Err(invalid_argument(
+            "nextpow2",
+            "expects a non-negative number",
+        ));
+    }
+    if x <= &BigInt::one() {
+        return Ok(BigInt::one());
+    }
+    let bits = (x - 1u32).bits();
+    ctx.check_bits(bits + 1)?;
+    Ok(BigInt::one() << bits)
+}
+
+/// 1 if the number of the set bits is odd
+pub fn parity(x: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let x = unsigned(x, "parity", ctx)?;
+    Ok((x.magnitude().count_ones() % 2).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::evaluator::{tests::empty_evaluation, EvalOptions};
+
+    fn n(n: i64) -> BigInt {
+        BigInt::from(n)
+    }
+
+    #[test]
+    fn test_to_width() {
+        let ctx = empty_evaluation();
+        assert_eq!(to_width(&n(255), 8, "f", &ctx), Ok(n(255)));
+        assert_eq!(to_width(&n(-128), 8, "f", &ctx), Ok(n(128)));
+        assert_eq!(to_width(&n(-1), 8, "f", &ctx), Ok(n(255)));
+        assert!(to_width(&n(256), 8, "f", &ctx).is_err());
+        assert!(to_width(&n(-129), 8, "f", &ctx).is_err());
+        assert_eq!(
+            to_width(&n(-1), u64::MAX, "f", &ctx),
+            Err(CalcError::ResultTooLarge(1 << 24))
+        );
+    }
+
+    #[test]
+    fn test_negative_needs_width() {
+        let ctx = empty_evaluation();
+        assert_eq!(popcount(&n(-1), &ctx), Err(CalcError::MissingWidth));
+
+        let variables = HashMap::new();
+        let ctx = Evaluation::new(
+            &variables,
+            EvalOptions {
+                width: Some(16),
+                ..Default::default()
+            },
+        );
+        assert_eq!(popcount(&n(-1), &ctx), Ok(n(16)));
+        assert_eq!(parity(&n(-2), &ctx), Ok(n(1)));
+    }
+
+    #[test]
+    fn test_fields() {
+        let ctx = empty_evaluation();
+        assert_eq!(extract(&n(0xABCD), &n(11), &n(4), &ctx), Ok(n(0xBC)));
+        assert_eq!(extract(&n(-1), &n(3), &n(0), &ctx), Ok(n(0xF)));
+        assert_eq!(
+            extract(&n(0xABCD), &n(u32::MAX as i64), &n(8), &ctx),
+            Ok(n(0xAB))
+        );
+        assert!(extract(&n(0xABCD), &n(4), &n(11), &ctx).is_err());
+
+        assert_eq!(
+            insert(&n(0xABCD), &n(0x12), &n(11), &n(4), &ctx),
+            Ok(n(0xA12D))
+        );
+        assert!(insert(&n(0xABCD), &n(0x100), &n(11), &n(4), &ctx).is_err());
+    }
+
+    #[test]
+    fn test_reverse_bits() {
+        let ctx = empty_evaluation();
+        assert_eq!(reverse_bits(&n(0b1101), &n(8), &ctx), Ok(n(0b1011_0000)));
+        assert_eq!(reverse_bits(&n(-1), &n(4), &ctx), Ok(n(0xF)));
+        assert_eq!(reverse_bits(&n(0), &n(0), &ctx), Ok(n(0)));
+    }
+}
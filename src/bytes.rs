@@ -0,0 +1,143 @@
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::ToPrimitive;
+
+use crate::{bitwise, error::CalcError, evaluator::Evaluation};
+
+fn invalid_argument(function: &str, reason: &str) -> CalcError {
+    CalcError::InvalidArgument(function.to_string(), reason.to_string())
+}
+
+/// How the `bytes` display mode writes the bytes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ByteSyntax {
+    /// Separated by spaces, `DE AD BE EF`
+    #[default]
+    Plain,
+    /// An array initializer, `{ 0xDE, 0xAD, 0xBE, 0xEF }`
+    C,
+    /// An array expression, `[0xDE, 0xAD, 0xBE, 0xEF]`
+    Rust,
+}
+
+/// The number of bytes of a length argument
+pub fn byte_count(function: &str, len: &BigInt) -> Result<usize, CalcError> {
+    len.to_usize()
+        .ok_or_else(|| invalid_argument(function, "the byte count must not be negative"))
+}
+
+/// The bytes of `x` from the most significant one, or from the least significant one if
+/// `little_endian`. Without a length they are as few as needed, zero has no bytes.
+/// With a length negative values are taken in two's complement.
+pub fn to_bytes(
+    x: &BigInt,
+    len: Option<usize>,
+    little_endian: bool,
+    function: &str,
+    ctx: &Evaluation,
+) -> Result<Vec<u8>, CalcError> {
+    let mut bytes = match len {
+        Some(len) => {
+            let bits = (len as u64).saturating_mul(8);
+            ctx.check_bits(bits)?;
+            let (_, mut bytes) = bitwise::to_width(x, bits, function, ctx)?.to_bytes_le();
+            // Only zero bytes are cut off, the value fits
+            bytes.resize(len, 0);
+            bytes
+        }
+        None if x.sign() == Sign::Minus => {
+            return Err(invalid_argument(
+                function,
+                "negative numbers need a byte count",
+            ))
+        }
+        None if x.sign() == Sign::NoSign => vec![],
+        None => x.to_bytes_le().1,
+    };
+    if !little_endian {
+        bytes.reverse();
+    }
+    Ok(bytes)
+}
+
+/// The number of the bytes, the first one is the most significant unless `little_endian`
+pub fn from_bytes(bytes: &[u8], little_endian: bool) -> BigInt {
+    let n = match little_endian {
+        true => BigUint::from_bytes_le(bytes),
+        false => BigUint::from_bytes_be(bytes),
+    };
+    n.into()
+}
+
+/// Reverses the order of the `len` bytes of `x`, which converts between big- and little-endian
+pub fn swap(x: &BigInt, len: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let len = byte_count("bswap", len)?;
+    let bytes = to_bytes(x, Some(len), true, "bswap", ctx)?;
+    Ok(from_bytes(&bytes, false))
+}
+
+/// The bytes as two hex digits each, e.g. `DE AD BE EF`
+pub fn format_bytes(bytes: &[u8], syntax: ByteSyntax) -> String {
+    let hex = |prefix: &str| -> Vec<String> {
+        bytes
+            .iter()
+            .map(|byte| format!("{}{:02X}", prefix, byte))
+            .collect()
+    };
+    match syntax {
+        ByteSyntax::Plain => hex("").join(" "),
+        ByteSyntax::C => format!("{{ {} }}", hex("0x").join(", ")),
+        ByteSyntax::Rust => format!("[{}]", hex("0x").join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::tests::empty_evaluation;
+
+    #[test]
+    fn test_to_bytes() {
+        let ctx = empty_evaluation();
+        let bytes = |x: i64, len: Option<usize>, le: bool| {
+            to_bytes(&BigInt::from(x), len, le, "test", &ctx)
+        };
+
+        assert_eq!(bytes(0x123456, None, false), Ok(vec![0x12, 0x34, 0x56]));
+        assert_eq!(bytes(0x123456, None, true), Ok(vec![0x56, 0x34, 0x12]));
+        assert_eq!(
+            bytes(0x123456, Some(4), false),
+            Ok(vec![0x00, 0x12, 0x34, 0x56])
+        );
+        assert_eq!(bytes(-2, Some(2), true), Ok(vec![0xFE, 0xFF]));
+        assert_eq!(bytes(0, None, false), Ok(vec![]));
+        assert_eq!(bytes(0, Some(2), false), Ok(vec![0, 0]));
+        assert!(bytes(0x123456, Some(2), false).is_err());
+        assert!(bytes(-1, None, false).is_err());
+    }
+
+    #[test]
+    fn test_swap() {
+        let ctx = empty_evaluation();
+        let swap = |x: i64, len: i64| super::swap(&BigInt::from(x), &BigInt::from(len), &ctx);
+
+        assert_eq!(swap(0x123456, 3), Ok(BigInt::from(0x563412)));
+        assert_eq!(swap(0x1234, 4), Ok(BigInt::from(0x34120000)));
+        assert_eq!(swap(-2, 2), Ok(BigInt::from(0xFEFF)));
+        assert_eq!(
+            swap(0x1234, 0),
+            Err(CalcError::InvalidArgument(
+                String::from("bswap"),
+                String::from("4660 does not fit in 0 bits")
+            ))
+        );
+        assert_eq!(from_bytes(&[0x12, 0x34], true), BigInt::from(0x3412));
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        let bytes = [0xDE, 0xAD, 0x0F];
+        assert_eq!(format_bytes(&bytes, ByteSyntax::Plain), "DE AD 0F");
+        assert_eq!(format_bytes(&bytes, ByteSyntax::C), "{ 0xDE, 0xAD, 0x0F }");
+        assert_eq!(format_bytes(&bytes, ByteSyntax::Rust), "[0xDE, 0xAD, 0x0F]");
+    }
+}
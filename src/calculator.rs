@@ -0,0 +1,1012 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::{
+    error::CalcError,
+    evaluator::{eval_assignment_with, EvalOptions},
+    format::format_variable,
+    function::FUNCTIONS,
+    parser::{parse_assignment, parse_function_definition},
+    special_function::{RuntimeVariables, SPECIAL_FUNCTIONS},
+    tokenizer::tokenize,
+};
+
+/// The result of evaluating a single line. Nothing is printed by the library,
+/// it is up to the caller how the output is presented.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Output {
+    /// Names of variables with their values formatted in the current display mode
+    Variables(Vec<(String, String)>),
+    /// Text produced by a command
+    Text(String),
+    /// The screen should be cleared
+    ClearScreen,
+    /// The session was ended by the user
+    Exit,
+    /// Nothing to show
+    None,
+}
+
+/// A calculator session. Keeps the variables and settings between the evaluated lines.
+#[derive(Debug, Default)]
+pub struct Calculator {
+    runtime_vars: RuntimeVariables,
+}
+
+impl Calculator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs a command, or evaluates an expression and stores its result.
+    pub fn evaluate(&mut self, line: &str) -> Result<Output, CalcError> {
+        let line = line.trim();
+
+        // An interruption before the evaluation started is not meant for this line
+        self.runtime_vars.interrupt.store(false, Ordering::Relaxed);
+
+        let (command, args) = match line.split_once(char::is_whitespace) {
+            Some((command, args)) => (command, args.trim()),
+            None => (line, ""),
+        };
+        if let Some(f) = SPECIAL_FUNCTIONS.get(command) {
+            // `limit = 5` assigns to the name of a command
+            if args.starts_with('=') {
+                return Err(CalcError::SpecialVariableInvalidUse(command.to_string()));
+            }
+            return f(&mut self.runtime_vars, args);
+        }
+
+        let tokens = tokenize(line)?;
+        if let Some((name, function)) = parse_function_definition(&tokens)? {
+            if FUNCTIONS.contains_key(name.as_str()) {
+                return Err(CalcError::BuiltinFunctionRedefined(name));
+            }
+            self.runtime_vars.functions.insert(name, function);
+            return Ok(Output::None);
+        }
+
+        let ass = parse_assignment(&tokens)?;
+        let options = EvalOptions {
+            limits: self.runtime_vars.limits,
+            width: self.runtime_vars.width,
+            modulus: self.runtime_vars.modulus.as_ref(),
+            interrupt: Some(&self.runtime_vars.interrupt),
+            rng: Some(&self.runtime_vars.rng),
+            functions: Some(&self.runtime_vars.functions),
+        };
+        let var = eval_assignment_with(&ass, &mut self.runtime_vars.vars, options)?;
+
+        Ok(Output::Variables(vec![format_variable(
+            &self.runtime_vars,
+            &var,
+        )]))
+    }
+
+    /// Setting this flag stops the running evaluation with [`CalcError::EvaluationInterrupted`].
+    /// The session and its variables are kept.
+    pub fn interrupt_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.runtime_vars.interrupt)
+    }
+
+    pub fn runtime_vars(&self) -> &RuntimeVariables {
+        &self.runtime_vars
+    }
+
+    pub fn runtime_vars_mut(&mut self) -> &mut RuntimeVariables {
+        &mut self.runtime_vars
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigInt;
+
+    use super::*;
+    use crate::{function::Arity, value::Value};
+
+    fn variable(name: &str, val: &str) -> Output {
+        Output::Variables(vec![(name.to_string(), val.to_string())])
+    }
+
+    #[test]
+    fn test_calculator_session() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(calculator.evaluate("10+(5/2)"), Ok(variable("$", "12")));
+        assert_eq!(calculator.evaluate("num = $"), Ok(variable("num", "12")));
+        assert_eq!(calculator.evaluate("hex"), Ok(Output::None));
+        assert_eq!(calculator.evaluate("num * 12"), Ok(variable("$", "0x90")));
+        assert_eq!(
+            calculator.runtime_vars().vars["num"],
+            Value::Int(BigInt::from(12))
+        );
+    }
+
+    #[test]
+    fn test_calculator_error_keeps_session() {
+        let mut calculator = Calculator::new();
+
+        calculator.evaluate("a = 5").unwrap();
+        assert_eq!(
+            calculator.evaluate("a / 0"),
+            Err(CalcError::DivisionByZero(BigInt::from(5).into()))
+        );
+        assert_eq!(calculator.evaluate("a"), Ok(variable("$", "5")));
+    }
+
+    #[test]
+    fn test_calculator_commands() {
+        let mut calculator = Calculator::new();
+
+        calculator.evaluate("a = 5").unwrap();
+        assert_eq!(calculator.evaluate(" exit "), Ok(Output::Exit));
+        assert_eq!(calculator.evaluate("clear"), Ok(Output::ClearScreen));
+        assert!(calculator.runtime_vars().vars.is_empty());
+        // Command names are not variables, with or without spaces
+        for (line, command) in [
+            ("limit = 5", "limit"),
+            ("limit=5", "limit"),
+            ("mod = 7", "mod"),
+        ] {
+            assert_eq!(
+                calculator.evaluate(line),
+                Err(CalcError::SpecialVariableInvalidUse(command.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_calculator_never_panics() {
+        // Every operator must return either a result or an error for any combination of edge values
+        let values = [
+            "0",
+            "1",
+            "-1",
+            "2",
+            "-2",
+            "65535",
+            "65536",
+            "0xFFFFFFFFFFFFFFFF",
+            "-0xFFFFFFFFFFFFFFFF",
+            "0x10000000000000000000000000000000000000000",
+            "1i",
+            "-2-3i",
+            "[1, 2; 3, 4]",
+            "[-1, 0, 0x10000000000000000]",
+            "1/16MHz",
+            "0 KiB",
+            "1h 30min",
+            "2024-03-01T12:00:00Z",
+            "10.0.0.0/8",
+            "0.5",
+            "'\\x00'",
+        ];
+        let operators = [
+            "+", "-", "*", "/", "%", "&", "|", "^", "<<", ">>", "<<<", ">>>", "**",
+        ];
+
+        let mut calculator = Calculator::new();
+        calculator.evaluate("width 8").unwrap();
+        // Keeps the huge powers and shifts fast, they only have to fail
+        calculator.evaluate("limit bits 4096").unwrap();
+        for lhs in values {
+            calculator.evaluate(&format!("a = {}", lhs)).unwrap();
+            let _ = calculator.evaluate("a!");
+            let _ = calculator.evaluate("a!!");
+            for (name, function) in FUNCTIONS.entries() {
+                if function.arity.accepts(1) {
+                    let _ = calculator.evaluate(&format!("{}(a)", name));
+                }
+            }
+
+            for rhs in values {
+                calculator.evaluate(&format!("b = {}", rhs)).unwrap();
+                for operator in operators {
+                    let _ = calculator.evaluate(&format!("a {} b", operator));
+                }
+                for (name, function) in FUNCTIONS.entries() {
+                    if function.arity.accepts(2) {
+                        let _ = calculator.evaluate(&format!("{}(a, b)", name));
+                    }
+                    if function.arity.accepts(3) {
+                        let _ = calculator.evaluate(&format!("{}(a, b, b)", name));
+                        let _ = calculator.evaluate(&format!("{}(a, a, b)", name));
+                    }
+                    if function.arity.accepts(4) {
+                        let _ = calculator.evaluate(&format!("{}(a, a, b, b)", name));
+                        let _ = calculator.evaluate(&format!("{}(a, b, a, b)", name));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_calculator_modulo_by_zero() {
+        let mut calculator = Calculator::new();
+
+        calculator.evaluate("a = 5").unwrap();
+        for line in ["a % 0", "rem(a, 0)", "mod(a, 0)", "emod(a, 0)"] {
+            assert_eq!(
+                calculator.evaluate(line),
+                Err(CalcError::DivisionByZero(BigInt::from(5).into()))
+            );
+        }
+        assert_eq!(calculator.evaluate("a"), Ok(variable("$", "5")));
+    }
+
+    #[test]
+    fn test_calculator_result_too_large() {
+        let mut calculator = Calculator::new();
+
+        calculator.evaluate("limit bits 64").unwrap();
+        calculator.evaluate("a = 1 << 63").unwrap();
+        assert_eq!(
+            calculator.evaluate("a << 1"),
+            Err(CalcError::ResultTooLarge(64))
+        );
+        assert_eq!(
+            calculator.evaluate("a * 2"),
+            Err(CalcError::ResultTooLarge(64))
+        );
+        assert_eq!(
+            calculator.evaluate("a + a"),
+            Err(CalcError::ResultTooLarge(64))
+        );
+        assert!(calculator.evaluate("a + 1").is_ok());
+    }
+
+    #[test]
+    fn test_calculator_shift_limit() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(
+            calculator.evaluate("1 << 70000 >> 69999"),
+            Ok(variable("$", "2"))
+        );
+        assert_eq!(
+            calculator.evaluate("1 << 0x100000000"),
+            Err(CalcError::InvalidBitShiftTooLarge(BigInt::from(1u64 << 32)))
+        );
+        // Right shifts never grow the value, any amount is allowed
+        assert_eq!(
+            calculator.evaluate("1 >> 0x100000000"),
+            Ok(variable("$", "0"))
+        );
+        assert_eq!(calculator.evaluate("-1 >> 2**70"), Ok(variable("$", "-1")));
+        assert_eq!(calculator.evaluate("-5 >> 1"), Ok(variable("$", "-3")));
+        calculator.evaluate("limit shift 8").unwrap();
+        assert_eq!(
+            calculator.evaluate("1 << 9"),
+            Err(CalcError::InvalidBitShiftTooLarge(BigInt::from(9)))
+        );
+        // Zero can be shifted by any amount
+        assert_eq!(calculator.evaluate("0 << 9"), Ok(variable("$", "0")));
+        assert_eq!(calculator.evaluate("0 >> 9"), Ok(variable("$", "0")));
+        assert_eq!(calculator.evaluate("256 >> 4"), Ok(variable("$", "16")));
+        calculator.evaluate("limit shift off").unwrap();
+        assert_eq!(calculator.evaluate("0 << 2**32"), Ok(variable("$", "0")));
+        assert_eq!(
+            calculator.evaluate("0 << -1"),
+            Err(CalcError::InvalidBitShiftNegative)
+        );
+    }
+
+    #[test]
+    fn test_calculator_rotate() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(calculator.evaluate("1 <<< 1"), Err(CalcError::MissingWidth));
+        calculator.evaluate("width 8").unwrap();
+        assert_eq!(calculator.evaluate("0x81 <<< 1"), Ok(variable("$", "3")));
+        assert_eq!(calculator.evaluate("0x81 >>> 1"), Ok(variable("$", "192")));
+        assert_eq!(calculator.evaluate("0x81 >>> 9"), Ok(variable("$", "192")));
+        assert_eq!(calculator.evaluate("0x81 <<< -1"), Ok(variable("$", "192")));
+        // Negative values are rotated in two's complement
+        assert_eq!(calculator.evaluate("-2 <<< 1"), Ok(variable("$", "253")));
+        assert_eq!(calculator.evaluate("0x1FF <<< 0"), Ok(variable("$", "255")));
+    }
+
+    #[test]
+    fn test_calculator_modular() {
+        let mut calculator = Calculator::new();
+
+        calculator.evaluate("a = 10").unwrap();
+        calculator.evaluate("mod 2**255 - 19").unwrap();
+        assert_eq!(
+            calculator.evaluate("mod"),
+            Ok(Output::Text(String::from(
+                "mod = 57896044618658097711785492504343953926634992332820282019728792003956564819949"
+            )))
+        );
+        // Fermat's little theorem
+        assert_eq!(
+            calculator.evaluate("a ** (2**255 - 20)"),
+            Ok(variable("$", "1"))
+        );
+        assert_eq!(calculator.evaluate("a / a"), Ok(variable("$", "1")));
+        assert_eq!(calculator.evaluate("(1 / a) * a"), Ok(variable("$", "1")));
+        assert!(calculator.evaluate("a / 0").is_err());
+        // The new modulus is not reduced by the current one
+        calculator.evaluate("mod 12").unwrap();
+        assert_eq!(calculator.evaluate("a"), Ok(variable("$", "10")));
+        assert_eq!(calculator.evaluate("a + 5"), Ok(variable("$", "3")));
+        assert_eq!(
+            calculator.evaluate("a / 2"),
+            Err(CalcError::NoModularInverse(
+                BigInt::from(2).into(),
+                BigInt::from(12)
+            ))
+        );
+        assert_eq!(calculator.evaluate("mod(a, 3)"), Ok(variable("$", "1")));
+        assert!(calculator.evaluate("mod 1").is_err());
+        calculator.evaluate("mod 2^8 - 5").unwrap();
+        assert_eq!(
+            calculator.evaluate("mod"),
+            Ok(Output::Text(String::from("mod = 251")))
+        );
+
+        // Exponents are not reduced by the modulus
+        calculator.evaluate("mod off").unwrap();
+        calculator.evaluate("e = 10").unwrap();
+        calculator.evaluate("mod 7").unwrap();
+        assert_eq!(calculator.evaluate("2**(3+7)"), Ok(variable("$", "2")));
+        assert_eq!(calculator.evaluate("2**e"), Ok(variable("$", "2")));
+        assert_eq!(calculator.evaluate("3**(0-1)"), Ok(variable("$", "5")));
+        assert_eq!(calculator.evaluate("3**-1"), Ok(variable("$", "5")));
+        assert_eq!(calculator.evaluate("2**-2**2"), Ok(variable("$", "4")));
+
+        calculator.evaluate("mod off").unwrap();
+        assert_eq!(calculator.evaluate("a + 5"), Ok(variable("$", "15")));
+    }
+
+    #[test]
+    fn test_calculator_complex() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(
+            calculator.evaluate("(3+4i) * (1-2i)"),
+            Ok(variable("$", "11-2i"))
+        );
+        assert_eq!(calculator.evaluate("1i ** 2"), Ok(variable("$", "-1")));
+        assert_eq!(calculator.evaluate("abs(3-4i)"), Ok(variable("$", "5")));
+        assert_eq!(calculator.evaluate("(7+5i) / 2"), Ok(variable("$", "3+2i")));
+        assert_eq!(
+            calculator.evaluate("1i & 1"),
+            Err(CalcError::ComplexOperand(String::from("&")))
+        );
+        calculator.evaluate("polar on").unwrap();
+        assert_eq!(
+            calculator.evaluate("3+4i"),
+            Ok(variable("$", "5 ∠ 53.1301°"))
+        );
+        calculator.evaluate("polar off").unwrap();
+
+        calculator.evaluate("mod 7").unwrap();
+        assert_eq!(
+            calculator.evaluate("(1+2i) * 5i"),
+            Ok(variable("$", "4+5i"))
+        );
+        assert_eq!(
+            calculator.evaluate("(1+2i) * (1 / (1+2i))"),
+            Ok(variable("$", "1"))
+        );
+    }
+
+    #[test]
+    fn test_calculator_matrix() {
+        let mut calculator = Calculator::new();
+
+        calculator.evaluate("a = [1, 2; 3, 4]").unwrap();
+        assert_eq!(
+            calculator.evaluate("a * a - a"),
+            Ok(variable("$", "[  6   8 ]\n[ 12  18 ]"))
+        );
+        assert_eq!(
+            calculator.evaluate("2 * transpose(a)"),
+            Ok(variable("$", "[ 2  6 ]\n[ 4  8 ]"))
+        );
+        assert_eq!(calculator.evaluate("det(a)"), Ok(variable("$", "-2")));
+        assert_eq!(
+            calculator.evaluate("[1, 1; 1, 0] ** 10"),
+            Ok(variable("$", "[ 89  55 ]\n[ 55  34 ]"))
+        );
+        assert_eq!(
+            calculator.evaluate("a + [1, 2]"),
+            Err(CalcError::IncompatibleMatrices(
+                String::from("+"),
+                String::from("2x2"),
+                String::from("1x2")
+            ))
+        );
+        assert_eq!(
+            calculator.evaluate("a + 1"),
+            Err(CalcError::MatrixOperand(String::from("+")))
+        );
+        assert_eq!(
+            calculator.evaluate("[1, 2; 3]"),
+            Err(CalcError::MatrixRowLength(2, 1, 2))
+        );
+        assert!(calculator.evaluate("inverse(a)").is_err());
+
+        calculator.evaluate("mod 7").unwrap();
+        assert_eq!(
+            calculator.evaluate("inverse(a)"),
+            Ok(variable("$", "[ 5  1 ]\n[ 5  3 ]"))
+        );
+        assert_eq!(
+            calculator.evaluate("a * inverse(a)"),
+            Ok(variable("$", "[ 1  0 ]\n[ 0  1 ]"))
+        );
+    }
+
+    #[test]
+    fn test_calculator_factor_functions() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(
+            calculator.evaluate("factor(-360)"),
+            Ok(variable("$", "[ -1  1 ]\n[  2  3 ]\n[  3  2 ]\n[  5  1 ]"))
+        );
+        assert_eq!(
+            calculator.evaluate("factor(1)"),
+            Ok(variable("$", "[ 1  1 ]"))
+        );
+        assert_eq!(
+            calculator.evaluate("egcd(240, 46)"),
+            Ok(variable("$", "[ 2  -9  47 ]"))
+        );
+        // Without parentheses it is still the command
+        assert_eq!(
+            calculator.evaluate("factor 12"),
+            Ok(Output::Text(String::from("12 = 2**2 * 3")))
+        );
+        assert!(calculator.evaluate("factor(0)").is_err());
+    }
+
+    #[test]
+    fn test_calculator_units() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(
+            calculator.evaluate("1 GiB in bytes"),
+            Ok(variable("$", "1073741824 bytes"))
+        );
+        assert_eq!(
+            calculator.evaluate("1/16MHz in ns"),
+            Ok(variable("$", "62.5 ns"))
+        );
+        assert_eq!(
+            calculator.evaluate("4 KiB + 512 B"),
+            Ok(variable("$", "4.5 KiB"))
+        );
+        assert_eq!(
+            calculator.evaluate("115200 baud * 1 s / 10"),
+            Ok(variable("$", "11520"))
+        );
+        assert_eq!(
+            calculator.evaluate("100 Mbit / 1 s * 3 min to GiB"),
+            Ok(variable("$", "2.0954757928 GiB"))
+        );
+        assert_eq!(
+            calculator.evaluate("$ * 2"),
+            Ok(variable("$", "4.1909515857 GiB"))
+        );
+        assert_eq!(
+            calculator.evaluate("1 MiB / 1 KiB"),
+            Ok(variable("$", "1024"))
+        );
+        assert_eq!(
+            calculator.evaluate("1 KiB / 3 KiB"),
+            Ok(variable("$", "0.3333333333"))
+        );
+        assert_eq!(
+            calculator.evaluate("(2 m) ** 2 - 1 m ** 2"),
+            Ok(variable("$", "3 m**2"))
+        );
+
+        assert_eq!(
+            calculator.evaluate("1 s + 1 m"),
+            Err(CalcError::IncompatibleUnits(
+                String::from("s"),
+                String::from("m")
+            ))
+        );
+        assert_eq!(
+            calculator.evaluate("1 KiB + 1"),
+            Err(CalcError::IncompatibleUnits(
+                String::from("bit"),
+                String::from("a plain number")
+            ))
+        );
+        assert_eq!(
+            calculator.evaluate("1 h in Hz"),
+            Err(CalcError::IncompatibleUnits(
+                String::from("s"),
+                String::from("Hz")
+            ))
+        );
+        assert_eq!(
+            calculator.evaluate("1 KiB & 1"),
+            Err(CalcError::QuantityOperand(String::from("&")))
+        );
+
+        // Only numbers get units, a variable can have the name of a unit
+        calculator.evaluate("s = 2").unwrap();
+        assert_eq!(calculator.evaluate("s * 3 s"), Ok(variable("$", "6 s")));
+    }
+
+    #[test]
+    fn test_calculator_datetime() {
+        let mut calculator = Calculator::new();
+
+        calculator.evaluate("t = 2024-02-28T22:30:00Z").unwrap();
+        assert_eq!(
+            calculator.evaluate("t + 1d 2h"),
+            Ok(variable("$", "2024-03-01T00:30:00Z"))
+        );
+        assert_eq!(
+            calculator.evaluate("2024-03-01 - t"),
+            Ok(variable("$", "1d 1h 30min"))
+        );
+        assert_eq!(
+            calculator.evaluate("$ in min"),
+            Ok(variable("$", "1530 min"))
+        );
+        assert_eq!(
+            calculator.evaluate("t - 1500 ms"),
+            Ok(variable("$", "2024-02-28T22:29:58.5Z"))
+        );
+        assert_eq!(
+            calculator.evaluate("fromunix(1700000000)"),
+            Ok(variable("$", "2023-11-14T22:13:20Z"))
+        );
+        assert_eq!(
+            calculator.evaluate("unixms(2024-03-01T00:00:00.123+01:00)"),
+            Ok(variable("$", "1709247600123"))
+        );
+        assert_eq!(
+            calculator.evaluate("unix(fromunixns(-1))"),
+            Ok(variable("$", "-1"))
+        );
+        assert_eq!(
+            calculator.evaluate("1h 30min * 3"),
+            Ok(variable("$", "4h 30min"))
+        );
+
+        assert_eq!(
+            calculator.evaluate("t + 1"),
+            Err(CalcError::IncompatibleUnits(
+                String::from("a date"),
+                String::from("a plain number")
+            ))
+        );
+        assert_eq!(
+            calculator.evaluate("t + t"),
+            Err(CalcError::IncompatibleUnits(
+                String::from("a date"),
+                String::from("a date")
+            ))
+        );
+        assert_eq!(
+            calculator.evaluate("t * 2"),
+            Err(CalcError::DateTimeOperand(String::from("*")))
+        );
+        assert_eq!(
+            calculator.evaluate("9999-12-31 + 1d"),
+            Err(CalcError::DateOutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_calculator_ip() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(
+            calculator.evaluate("10.0.0.1 + 300"),
+            Ok(variable("$", "167772461"))
+        );
+        calculator.evaluate("ip").unwrap();
+        assert_eq!(calculator.evaluate("$"), Ok(variable("$", "10.0.1.45")));
+        calculator.evaluate("net = 192.168.1.77/24").unwrap();
+        assert_eq!(
+            calculator.evaluate("net"),
+            Ok(variable("$", "192.168.1.77/24"))
+        );
+        assert_eq!(
+            calculator.evaluate("network(net)"),
+            Ok(variable("$", "192.168.1.0"))
+        );
+        assert_eq!(
+            calculator.evaluate("broadcast(net) - 1"),
+            Ok(variable("$", "192.168.1.254"))
+        );
+        assert_eq!(
+            calculator.evaluate("netmask(net)"),
+            Ok(variable("$", "255.255.255.0"))
+        );
+        assert_eq!(
+            calculator.evaluate("[network(2001:db8::1/32), ::1]"),
+            Ok(variable("$", "[ 2001:db8::  0.0.0.1 ]"))
+        );
+        assert_eq!(calculator.evaluate("-1"), Ok(variable("$", "-1")));
+
+        calculator.evaluate("dec").unwrap();
+        assert_eq!(calculator.evaluate("hosts(net)"), Ok(variable("$", "254")));
+        assert_eq!(
+            calculator.evaluate("contains(net, 192.168.1.200)"),
+            Ok(variable("$", "1"))
+        );
+        assert_eq!(
+            calculator.evaluate("contains(net, 192.168.0.0/16)"),
+            Ok(variable("$", "0"))
+        );
+        assert_eq!(
+            calculator.evaluate("cidr(network(net) + 256, 24)"),
+            Ok(variable("$", "192.168.2.0/24"))
+        );
+        assert_eq!(
+            calculator.evaluate("net + 1"),
+            Err(CalcError::NetworkOperand(String::from("+")))
+        );
+        assert_eq!(
+            calculator.evaluate("1.2.3.256"),
+            Err(CalcError::InvalidAddress(String::from("1.2.3.256")))
+        );
+    }
+
+    #[test]
+    fn test_calculator_float() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(calculator.evaluate("0.1 + 0.2"), Ok(variable("$", "0.3")));
+        assert_eq!(calculator.evaluate("1.5 * 2"), Ok(variable("$", "3")));
+        assert_eq!(
+            calculator.evaluate("2.5h in min"),
+            Ok(variable("$", "150 min"))
+        );
+        assert_eq!(
+            calculator.evaluate("fromf32bits(0x40490FDB)"),
+            Ok(variable("$", "3.141592741"))
+        );
+        calculator.evaluate("hex").unwrap();
+        assert_eq!(
+            calculator.evaluate("f32bits($)"),
+            Ok(variable("$", "0x40490FDB"))
+        );
+        assert_eq!(
+            calculator.evaluate("f32bits(3.1415927)"),
+            Ok(variable("$", "0x40490FDB"))
+        );
+        assert_eq!(
+            calculator.evaluate("f64bits(-2)"),
+            Ok(variable("$", "0xC000000000000000"))
+        );
+        assert_eq!(
+            calculator.evaluate("f16bits(100000)"),
+            Ok(variable("$", "0x7C00"))
+        );
+        assert_eq!(
+            calculator.evaluate("bf16bits(fromf32bits(0x40490FDB))"),
+            Ok(variable("$", "0x4049"))
+        );
+        assert_eq!(
+            calculator.evaluate("fromf16bits(0x7C00)"),
+            Err(CalcError::InvalidArgument(
+                String::from("fromf16bits"),
+                String::from("the bits are infinity or NaN")
+            ))
+        );
+        assert_eq!(
+            calculator.evaluate("fromf16bits(0x10000)"),
+            Err(CalcError::InvalidArgument(
+                String::from("fromf16bits"),
+                String::from("expected a 16 bit pattern")
+            ))
+        );
+        assert!(calculator.evaluate("f32bits(1 KiB)").is_err());
+    }
+
+    #[test]
+    fn test_calculator_text() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(
+            calculator.evaluate("'RIFF'"),
+            Ok(variable("$", "1380533830"))
+        );
+        calculator.evaluate("hex").unwrap();
+        assert_eq!(
+            calculator.evaluate("'RIFF'le"),
+            Ok(variable("$", "0x46464952"))
+        );
+        assert_eq!(calculator.evaluate("'A' + 2"), Ok(variable("$", "0x43")));
+        calculator.evaluate("ascii").unwrap();
+        assert_eq!(
+            calculator.evaluate("\"WAV\" << 8 | ' '"),
+            Ok(variable("$", "\"WAV \""))
+        );
+        assert_eq!(
+            calculator.evaluate("\"é\n\""),
+            Ok(variable("$", r#""\xC3\xA9\n""#))
+        );
+        calculator.evaluate("utf8").unwrap();
+        assert_eq!(calculator.evaluate("$"), Ok(variable("$", "\"é\\n\"")));
+        assert_eq!(calculator.evaluate("-1"), Ok(variable("$", "-1")));
+        assert_eq!(
+            calculator.evaluate("'ab"),
+            Err(CalcError::InvalidText(String::from("'ab")))
+        );
+    }
+
+    #[test]
+    fn test_calculator_checksums() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("hex").unwrap();
+
+        assert_eq!(
+            calculator.evaluate("crc32(\"123456789\")"),
+            Ok(variable("$", "0xCBF43926"))
+        );
+        assert_eq!(
+            calculator.evaluate("crc32(\"1234\") - crc32(tobytes_le(0x34333231, 4))"),
+            Ok(variable("$", "0x0"))
+        );
+        assert_eq!(
+            calculator.evaluate("crc16ccitt(0x0102, 4)"),
+            calculator.evaluate("crc16ccitt([0, 0, 1, 2])")
+        );
+        assert_eq!(
+            calculator.evaluate("crc(\"123456789\", 16, 0x1021, 0, 0, 0)"),
+            Ok(variable("$", "0x31C3"))
+        );
+        assert_eq!(
+            calculator.evaluate("adler32('Wikipedia')"),
+            Ok(variable("$", "0x11E60398"))
+        );
+        assert_eq!(
+            calculator.evaluate("md5(\"\")"),
+            Ok(variable("$", "0xD41D8CD98F00B204E9800998ECF8427E"))
+        );
+        assert_eq!(
+            calculator.evaluate("sha256(-1, 1) >> 240"),
+            Ok(variable("$", "0xA810"))
+        );
+        assert_eq!(
+            calculator.evaluate("crc32(0x12345678, 4, 0x6C65)"),
+            Err(CalcError::InvalidArgumentCount(
+                String::from("crc32"),
+                Arity::Between(1, 2),
+                3
+            ))
+        );
+        assert_eq!(
+            calculator.evaluate("crc8([1, 256])"),
+            Err(CalcError::InvalidArgument(
+                String::from("crc8"),
+                String::from("the list must contain bytes")
+            ))
+        );
+        assert!(calculator.evaluate("crc(1, 65, 1, 0, 0, 0)").is_err());
+        assert!(calculator.evaluate("crc(1, 8, 256, 0, 0, 0)").is_err());
+        assert!(calculator.evaluate("crc32(-1)").is_err());
+    }
+
+    #[test]
+    fn test_calculator_bytes() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("hex").unwrap();
+
+        assert_eq!(
+            calculator.evaluate("bswap(0x12345678, 4)"),
+            Ok(variable("$", "0x78563412"))
+        );
+        assert_eq!(
+            calculator.evaluate("tobytes_le(0x1234, 4)"),
+            Ok(variable("$", "[ 0x34  0x12  0x0  0x0 ]"))
+        );
+        assert_eq!(
+            calculator.evaluate("frombytes_le($)"),
+            Ok(variable("$", "0x1234"))
+        );
+        assert_eq!(
+            calculator.evaluate("frombytes(tobytes(-2, 2))"),
+            Ok(variable("$", "0xFFFE"))
+        );
+
+        calculator.evaluate("bytes").unwrap();
+        assert_eq!(
+            calculator.evaluate("0xDEADBEEF"),
+            Ok(variable("$", "DE AD BE EF"))
+        );
+        calculator.evaluate("bytes c").unwrap();
+        assert_eq!(
+            calculator.evaluate("tobytes_le('RIFF', 4)"),
+            Ok(variable("$", "{ 0x46, 0x46, 0x49, 0x52 }"))
+        );
+        calculator.evaluate("bytes rust").unwrap();
+        assert_eq!(
+            calculator.evaluate("crc16(\"123456789\")"),
+            Ok(variable("$", "[0xBB, 0x3D]"))
+        );
+        assert_eq!(
+            calculator.evaluate("[1, 300]"),
+            Ok(variable("$", "[ 0x1  0x12C ]"))
+        );
+        assert_eq!(
+            calculator.evaluate("bytes java"),
+            Err(CalcError::InvalidCommandArgument(String::from("java")))
+        );
+        assert_eq!(
+            calculator.evaluate("frombytes(tobytes(0x1234), 1)"),
+            Err(CalcError::InvalidArgumentCount(
+                String::from("frombytes"),
+                Arity::Exact(1),
+                2
+            ))
+        );
+        assert_eq!(
+            calculator.evaluate("frombytes(1)"),
+            Err(CalcError::InvalidArgument(
+                String::from("frombytes"),
+                String::from("expected a list of bytes")
+            ))
+        );
+        assert_eq!(
+            calculator.evaluate("1 KiB in bytes"),
+            Ok(variable("$", "1024 bytes"))
+        );
+    }
+
+    #[test]
+    fn test_calculator_lists() {
+        let mut calculator = Calculator::new();
+
+        calculator.evaluate("a = [4, -1, 7, 4, 2]").unwrap();
+        assert_eq!(calculator.evaluate("sum(a)"), Ok(variable("$", "16")));
+        assert_eq!(
+            calculator.evaluate("max(a) - min(a)"),
+            Ok(variable("$", "8"))
+        );
+        assert_eq!(calculator.evaluate("median(a)"), Ok(variable("$", "4")));
+        assert_eq!(calculator.evaluate("mean(1, 2, 4)"), Ok(variable("$", "2")));
+        assert_eq!(calculator.evaluate("len([])"), Ok(variable("$", "0")));
+        assert_eq!(
+            calculator.evaluate("sort(a)"),
+            Ok(variable("$", "[ -1  2  4  4  7 ]"))
+        );
+        assert_eq!(
+            calculator.evaluate("sort([3; 1; 2])"),
+            Ok(variable("$", "[ 1 ]\n[ 2 ]\n[ 3 ]"))
+        );
+        assert_eq!(
+            calculator.evaluate("range(10, 0, -3)"),
+            Ok(variable("$", "[ 10  7  4  1 ]"))
+        );
+        assert!(calculator.evaluate("min([])").is_err());
+    }
+
+    #[test]
+    fn test_calculator_user_functions() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(calculator.evaluate("sq(x) = x ** 2"), Ok(Output::None));
+        calculator.evaluate("x = 10").unwrap();
+        assert_eq!(calculator.evaluate("sq(3) + x"), Ok(variable("$", "19")));
+        assert_eq!(
+            calculator.evaluate("map(sq, range(1, 5))"),
+            Ok(variable("$", "[ 1  4  9  16 ]"))
+        );
+        assert_eq!(
+            calculator.evaluate("sum(filter(isprime, range(20)))"),
+            Ok(variable("$", "77"))
+        );
+        // The body is evaluated when the function is called
+        calculator.evaluate("shifted(x) = x + offset").unwrap();
+        assert_eq!(
+            calculator.evaluate("shifted(1)"),
+            Err(CalcError::UnknownVariable(String::from("offset")))
+        );
+        calculator.evaluate("offset = 5").unwrap();
+        assert_eq!(calculator.evaluate("shifted(1)"), Ok(variable("$", "6")));
+
+        assert_eq!(
+            calculator.evaluate("sq(1, 2)"),
+            Err(CalcError::InvalidArgumentCount(
+                String::from("sq"),
+                Arity::Exact(1),
+                2
+            ))
+        );
+        assert_eq!(
+            calculator.evaluate("gcd(a) = a"),
+            Err(CalcError::BuiltinFunctionRedefined(String::from("gcd")))
+        );
+        assert_eq!(
+            calculator.evaluate("f(x, x) = x"),
+            Err(CalcError::InvalidExpression)
+        );
+        calculator.evaluate("loop(x) = loop(x + 1)").unwrap();
+        assert_eq!(
+            calculator.evaluate("loop(0)"),
+            Err(CalcError::CallDepthExceeded(String::from("loop")))
+        );
+    }
+
+    #[test]
+    fn test_calculator_factorial() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(calculator.evaluate("-3! - 1"), Ok(variable("$", "-7")));
+        assert_eq!(calculator.evaluate("2**3!"), Ok(variable("$", "64")));
+        assert_eq!(calculator.evaluate("(3!)!"), Ok(variable("$", "720")));
+        assert_eq!(calculator.evaluate("7!!"), Ok(variable("$", "105")));
+        assert!(calculator.evaluate("1000!").is_ok());
+        assert_eq!(
+            calculator.evaluate("10000000!"),
+            Err(CalcError::ResultTooLarge(1 << 24))
+        );
+        assert_eq!(
+            calculator.evaluate("fib(100000000)"),
+            Err(CalcError::ResultTooLarge(1 << 24))
+        );
+    }
+
+    #[test]
+    fn test_calculator_step_limit() {
+        let mut calculator = Calculator::new();
+
+        calculator.evaluate("limit steps 10").unwrap();
+        assert!(calculator.evaluate("1+1").is_ok());
+        assert_eq!(
+            calculator.evaluate("1+1+1+1+1+1+1+1+1+1"),
+            Err(CalcError::EvaluationTimeout)
+        );
+    }
+
+    #[test]
+    fn test_calculator_limit_zero() {
+        let mut calculator = Calculator::new();
+
+        for limit in ["limit bits 0", "limit steps 0", "limit time 0"] {
+            assert_eq!(
+                calculator.evaluate(limit),
+                Err(CalcError::InvalidCommandArgument(limit[6..].to_string()))
+            );
+        }
+        assert!(calculator.evaluate("1+1").is_ok());
+
+        calculator.evaluate("limit shift 0").unwrap();
+        assert_eq!(calculator.evaluate("3 << 0"), Ok(variable("$", "3")));
+    }
+
+    #[test]
+    fn test_calculator_seed() {
+        let mut calculator = Calculator::new();
+
+        calculator.evaluate("seed 1234").unwrap();
+        let first = calculator.evaluate("rand(1, 1000000) + randbits(64)");
+        assert_ne!(
+            calculator.evaluate("rand(1, 1000000) + randbits(64)"),
+            first
+        );
+        calculator.evaluate("seed 1234").unwrap();
+        assert_eq!(
+            calculator.evaluate("rand(1, 1000000) + randbits(64)"),
+            first
+        );
+    }
+
+    #[test]
+    fn test_calculator_interrupt() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("a = 1").unwrap();
+
+        // A flag set before the evaluation is ignored
+        calculator.interrupt_flag().store(true, Ordering::Relaxed);
+        assert!(calculator.evaluate("a + 1").is_ok());
+    }
+}
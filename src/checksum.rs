@@ -0,0 +1,211 @@
+use md5::Md5;
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+/// Parameters of a CRC in the Rocksoft model. The input bytes and the result are reflected
+/// together, as in all the common CRCs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc {
+    /// 1 to 64 bits
+    pub width: u32,
+    /// Without the highest term, e.g. `0x07` for x^8 + x^2 + x + 1
+    pub poly: u64,
+    pub init: u64,
+    pub reflect: bool,
+    pub xorout: u64,
+}
+
+/// CRC-8/SMBUS
+pub const CRC8: Crc = Crc {
+    width: 8,
+    poly: 0x07,
+    init: 0,
+    reflect: false,
+    xorout: 0,
+};
+/// CRC-8/MAXIM-DOW of the 1-Wire bus
+pub const CRC8_MAXIM: Crc = Crc {
+    width: 8,
+    poly: 0x31,
+    init: 0,
+    reflect: true,
+    xorout: 0,
+};
+/// CRC-16/ARC
+pub const CRC16: Crc = Crc {
+    width: 16,
+    poly: 0x8005,
+    init: 0,
+    reflect: true,
+    xorout: 0,
+};
+/// CRC-16/IBM-3740, often called CRC-16/CCITT-FALSE
+pub const CRC16_CCITT: Crc = Crc {
+    width: 16,
+    poly: 0x1021,
+    init: 0xFFFF,
+    reflect: false,
+    xorout: 0,
+};
+pub const CRC16_MODBUS: Crc = Crc {
+    width: 16,
+    poly: 0x8005,
+    init: 0xFFFF,
+    reflect: true,
+    xorout: 0,
+};
+pub const CRC16_XMODEM: Crc = Crc {
+    width: 16,
+    poly: 0x1021,
+    init: 0,
+    reflect: false,
+    xorout: 0,
+};
+/// CRC-32/ISO-HDLC of Ethernet, zlib and PNG
+pub const CRC32: Crc = Crc {
+    width: 32,
+    poly: 0x04C11DB7,
+    init: 0xFFFFFFFF,
+    reflect: true,
+    xorout: 0xFFFFFFFF,
+};
+/// CRC-32/ISCSI with the Castagnoli polynomial
+pub const CRC32C: Crc = Crc {
+    width: 32,
+    poly: 0x1EDC6F41,
+    init: 0xFFFFFFFF,
+    reflect: true,
+    xorout: 0xFFFFFFFF,
+};
+/// CRC-32/MPEG-2 of STM32 CRC units
+pub const CRC32_MPEG2: Crc = Crc {
+    width: 32,
+    poly: 0x04C11DB7,
+    init: 0xFFFFFFFF,
+    reflect: false,
+    xorout: 0,
+};
+
+impl Crc {
+    /// Computed bit by bit, the data is small enough
+    pub fn checksum(&self, data: &[u8]) -> u64 {
+        let mask = u64::MAX >> (64 - self.width);
+        let mut crc = self.init & mask;
+        for &byte in data {
+            let byte = if self.reflect {
+                byte.reverse_bits()
+            } else {
+                byte
+            };
+            for i in (0..8).rev() {
+                let feedback = (crc >> (self.width - 1)) & 1 != u64::from(byte >> i) & 1;
+                crc = (crc << 1) & mask;
+                if feedback {
+                    crc ^= self.poly;
+                }
+            }
+        }
+        if self.reflect {
+            crc = crc.reverse_bits() >> (64 - self.width);
+        }
+        (crc ^ self.xorout) & mask
+    }
+}
+
+/// Adler-32 of zlib
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+/// Fletcher-16 over the bytes
+pub fn fletcher16(data: &[u8]) -> u16 {
+    let (mut a, mut b) = (0u16, 0u16);
+    for &byte in data {
+        a = (a + u16::from(byte)) % 255;
+        b = (b + a) % 255;
+    }
+    b << 8 | a
+}
+
+/// Fletcher-32 over little-endian 16-bit words, an odd byte is padded with a zero
+pub fn fletcher32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (0u32, 0u32);
+    for word in data.chunks(2) {
+        let word = u32::from(word[0]) | u32::from(*word.get(1).unwrap_or(&0)) << 8;
+        a = (a + word) % 65535;
+        b = (b + a) % 65535;
+    }
+    b << 16 | a
+}
+
+/// The digest as one big-endian number
+pub fn sha256(data: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(&Sha256::digest(data))
+}
+
+/// The digest as one big-endian number
+pub fn md5(data: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(&Md5::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use num_traits::Num;
+
+    use super::*;
+
+    const CHECK: &[u8] = b"123456789";
+
+    #[test]
+    fn test_crc() {
+        assert_eq!(CRC8.checksum(CHECK), 0xF4);
+        assert_eq!(CRC8_MAXIM.checksum(CHECK), 0xA1);
+        assert_eq!(CRC16.checksum(CHECK), 0xBB3D);
+        assert_eq!(CRC16_CCITT.checksum(CHECK), 0x29B1);
+        assert_eq!(CRC16_MODBUS.checksum(CHECK), 0x4B37);
+        assert_eq!(CRC16_XMODEM.checksum(CHECK), 0x31C3);
+        assert_eq!(CRC32.checksum(CHECK), 0xCBF43926);
+        assert_eq!(CRC32C.checksum(CHECK), 0xE3069283);
+        assert_eq!(CRC32_MPEG2.checksum(CHECK), 0x0376E6E7);
+        assert_eq!(CRC32.checksum(b""), 0);
+
+        // CRC-64/XZ and CRC-5/USB
+        let crc64 = Crc {
+            width: 64,
+            poly: 0x42F0E1EBA9EA3693,
+            init: u64::MAX,
+            reflect: true,
+            xorout: u64::MAX,
+        };
+        assert_eq!(crc64.checksum(CHECK), 0x995DC9BBDF1939FA);
+        let crc5 = Crc {
+            width: 5,
+            poly: 0x05,
+            init: 0x1F,
+            reflect: true,
+            xorout: 0x1F,
+        };
+        assert_eq!(crc5.checksum(CHECK), 0x19);
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(adler32(CHECK), 0x091E01DE);
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(fletcher16(b"abcde"), 0xC8F0);
+        assert_eq!(fletcher32(b"abcde"), 0xF04FC729);
+        assert_eq!(fletcher32(b"abcdef"), 0x56502D2A);
+
+        let hex = |s: &str| BigUint::from_str_radix(s, 16).unwrap();
+        assert_eq!(
+            sha256(b"abc"),
+            hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert_eq!(md5(b""), hex("d41d8cd98f00b204e9800998ecf8427e"));
+    }
+}
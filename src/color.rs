@@ -0,0 +1,62 @@
+use std::{
+    io::{self, IsTerminal},
+    sync::OnceLock,
+};
+
+/// Colors used for highlighting the input and the output of the REPL
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    Dimmed,
+}
+
+impl Color {
+    fn ansi_code(self) -> &'static str {
+        match self {
+            Color::Red => "31",
+            Color::Green => "32",
+            Color::Yellow => "33",
+            Color::Blue => "34",
+            Color::Magenta => "35",
+            Color::Cyan => "36",
+            Color::Dimmed => "2",
+        }
+    }
+}
+
+/// Wraps the text in the escape codes of the given color
+pub fn paint(text: &str, color: Color) -> String {
+    format!("\x1B[{}m{}\x1B[0m", color.ansi_code(), text)
+}
+
+/// Whether colors should be written to stdout
+pub fn stdout_colors() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| colors_allowed() && io::stdout().is_terminal())
+}
+
+/// Whether colors should be written to stderr
+pub fn stderr_colors() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| colors_allowed() && io::stderr().is_terminal())
+}
+
+/// Colors are disabled by setting a non-empty `NO_COLOR`. See https://no-color.org
+fn colors_allowed() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|val| val.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint("12", Color::Cyan), "\x1B[36m12\x1B[0m");
+    }
+}
@@ -0,0 +1,447 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::{error::CalcError, evaluator::Evaluation};
+
+/// Below this the factorials are calculated by simple multiplication
+const SMALL_FACTORIAL: u64 = 32;
+
+/// Largest number the primes are sieved up to. The factorials of the larger ones would have
+/// billions of bits.
+const SIEVE_LIMIT: u64 = 1 << 32;
+
+/// The size of the results is estimated before calculating them, so the too large ones are
+/// rejected quickly. The estimates are lowered a bit to stay below the exact sizes.
+fn check_estimate(ctx: &Evaluation, log2: f64) -> Result<(), CalcError> {
+    let bits = log2 * (1.0 - 1e-9) - 2.0;
+    if bits > 0.0 {
+        // Saturates for the estimates beyond u64
+        ctx.check_bits(bits as u64)?;
+    }
+    Ok(())
+}
+
+/// Stirling's approximation of log2(n!)
+fn log2_factorial(n: f64) -> f64 {
+    if n < 2.0 {
+        return 0.0;
+    }
+    (n * n.ln() - n + 0.5 * (2.0 * std::f64::consts::PI * n).ln() + 1.0 / (12.0 * n))
+        / std::f64::consts::LN_2
+}
+
+/// The argument as a non-negative number. Numbers too large for u64 could only have results
+/// which are too large as well.
+fn to_count(n: &BigInt, function: &str, ctx: &Evaluation) -> Result<u64, CalcError> {
+    if n.is_negative() {
+        return Err(CalcError::InvalidArgument(
+            function.to_string(),
+            String::from("expects a non-negative number"),
+        ));
+    }
+    match n.to_u64() {
+        Some(n) => Ok(n),
+        None => {
+            ctx.check_bits(u64::MAX)?;
+            Err(CalcError::ResultTooLarge(u64::MAX))
+        }
+    }
+}
+
+/// Primes up to n with the sieve of Eratosthenes
+fn primes_up_to(n: u64, ctx: &Evaluation) -> Result<Vec<u64>, CalcError> {
+    if n > SIEVE_LIMIT {
+        return Err(CalcError::ResultTooLarge(u64::MAX));
+    }
+    let n = n as usize;
+    let mut composite = vec![false; n + 1];
+    let mut primes = vec![];
+    for i in 2..=n {
+        if composite[i] {
+            continue;
+        }
+        primes.push(i as u64);
+        if i.saturating_mul(i) <= n {
+            ctx.step()?;
+            for multiple in (i * i..=n).step_by(i) {
+                composite[multiple] = true;
+            }
+        }
+    }
+    Ok(primes)
+}
+
+/// Product of the numbers, multiplying numbers of similar sizes
+fn product(mut factors: Vec<BigInt>, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    while factors.len() > 1 {
+        ctx.step()?;
+        factors = factors
+            .chunks(2)
+            .map(|pair| pair.iter().product())
+            .collect();
+    }
+    Ok(factors.pop().unwrap_or_else(BigInt::one))
+}
+
+/// p^e for every prime with a non-zero exponent
+fn prime_powers<F>(primes: &[u64], exponent: F) -> Vec<BigInt>
+where
+    F: Fn(u64) -> u64,
+{
+    primes
+        .iter()
+        .filter_map(|&p| match exponent(p) {
+            0 => None,
+            // At most log2(n)
+            e => Some(BigInt::from(p).pow(e as u32)),
+        })
+        .collect()
+}
+
+/// Sum of f(n / p^i) for i >= 1
+fn sum_over_prime_powers<F>(n: u64, p: u64, f: F) -> u64
+where
+    F: Fn(u64) -> u64,
+{
+    let mut sum = 0;
+    let mut power = p;
+    while power <= n {
+        sum += f(n / power);
+        power = match power.checked_mul(p) {
+            Some(power) => power,
+            None => break,
+        };
+    }
+    sum
+}
+
+/// n! with the prime swing algorithm, n! = (n/2)!^2 * swing(n)
+pub fn factorial(n: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let n = to_count(n, "!", ctx)?;
+    check_estimate(ctx, log2_factorial(n as f64))?;
+    let primes = primes_up_to(n, ctx)?;
+    swing_factorial(n, &primes, ctx)
+}
+
+fn swing_factorial(n: u64, primes: &[u64], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    if n < SMALL_FACTORIAL {
+        return Ok(factorial_small(n));
+    }
+
+    let half = swing_factorial(n / 2, primes, ctx)?;
+    // The exponent of p in n! / (n/2)!^2 is the number of odd n / p^i
+    let primes = &primes[..primes.partition_point(|&p| p <= n)];
+    let swing = product(
+        prime_powers(primes, |p| sum_over_prime_powers(n, p, |q| q % 2)),
+        ctx,
+    )?;
+    Ok(&half * &half * swing)
+}
+
+/// Double factorial n!! = n * (n - 2) * ..., 1 for -1 and 0
+pub fn double_factorial(n: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    if n == &-BigInt::one() {
+        return Ok(BigInt::one());
+    }
+    let n = to_count(n, "!!", ctx)?;
+    // n!! * (n - 1)!! = n! and n!! is the larger one
+    check_estimate(ctx, log2_factorial(n as f64) / 2.0)?;
+
+    if n % 2 == 0 {
+        // (2m)!! = 2^m * m!
+        Ok(factorial(&BigInt::from(n / 2), ctx)? << (n / 2))
+    } else {
+        product((1..=n).step_by(2).map(BigInt::from).collect(), ctx)
+    }
+}
+
+/// Number of the ways to choose k of n. Negative n is extended with binom(n, k) =
+/// (-1)^k binom(k - n - 1, k).
+pub fn binomial(n: &BigInt, k: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    if k.is_negative() {
+        return Ok(BigInt::zero());
+    }
+    if n.is_negative() {
+        let res = binomial(&(k - n - 1), k, ctx)?;
+        return Ok(if k.is_odd() { -res } else { res });
+    }
+    if k > n {
+        return Ok(BigInt::zero());
+    }
+
+    // binom(n, k) = binom(n, n - k), the smaller one has less factors
+    let k = std::cmp::min(k.clone(), n - k);
+    let k = to_count(&k, "binom", ctx)?;
+    if let Some(n) = n.to_u64() {
+        let log2 =
+            log2_factorial(n as f64) - log2_factorial(k as f64) - log2_factorial((n - k) as f64);
+        check_estimate(ctx, log2)?;
+
+        // Sieving pays off when there are not many more primes, about n / ln(n), than bits of
+        // the result. Otherwise k is small compared to n and the falling factorial is cheaper.
+        let prime_count = n as f64 / (n as f64).ln();
+        if k >= SMALL_FACTORIAL && n <= SIEVE_LIMIT && prime_count <= log2 {
+            // The exponent of p is the number of carries when adding k and n - k in base p
+            let primes = primes_up_to(n, ctx)?;
+            return product(
+                prime_powers(&primes, |p| {
+                    sum_over_prime_powers(n, p, |q| q)
+                        - sum_over_prime_powers(k, p, |q| q)
+                        - sum_over_prime_powers(n - k, p, |q| q)
+                }),
+                ctx,
+            );
+        }
+    }
+
+    // Too large to sieve, but then k must be small compared to n
+    Ok(falling_factorial_checked(n, k, ctx)? / factorial(&BigInt::from(k), ctx)?)
+}
+
+/// n! for the n which are small enough to multiply in a loop
+fn factorial_small(n: u64) -> BigInt {
+    (2..=n).map(BigInt::from).product()
+}
+
+/// n * (n - 1) * ... * (n - k + 1)
+fn falling_factorial(n: &BigInt, k: u64, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let mut factors = vec![];
+    let mut factor = n.clone();
+    for _ in 0..k {
+        factors.push(factor.clone());
+        factor -= 1;
+    }
+    product(factors, ctx)
+}
+
+/// [`falling_factorial`] which rejects too large results before calculating them
+fn falling_factorial_checked(n: &BigInt, k: u64, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    // Every factor has at least the bits of the smallest one minus one
+    let smallest: BigInt = n - k + 1;
+    ctx.check_bits((smallest.bits().saturating_sub(1)).saturating_mul(k))?;
+    falling_factorial(n, k, ctx)
+}
+
+/// Number of the ordered ways to choose k of n, n! / (n - k)!
+pub fn permutations(n: &BigInt, k: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    if n.is_negative() {
+        return Err(CalcError::InvalidArgument(
+            String::from("perm"),
+            String::from("expects a non-negative number"),
+        ));
+    }
+    let k = to_count(k, "perm", ctx)?;
+    if &BigInt::from(k) > n {
+        return Ok(BigInt::zero());
+    }
+    if let Some(n) = n.to_u64() {
+        check_estimate(
+            ctx,
+            log2_factorial(n as f64) - log2_factorial((n - k) as f64),
+        )?;
+    }
+    falling_factorial_checked(n, k, ctx)
+}
+
+/// (F(n), F(n + 1)) with the fast doubling method
+fn fibonacci_pair(n: u64, ctx: &Evaluation) -> Result<(BigInt, BigInt), CalcError> {
+    let (mut a, mut b) = (BigInt::zero(), BigInt::one());
+    for bit in (0..u64::BITS - n.leading_zeros()).rev() {
+        ctx.step()?;
+        // F(2m) = F(m) * (2F(m + 1) - F(m)), F(2m + 1) = F(m)^2 + F(m + 1)^2
+        let double = &a * ((&b << 1) - &a);
+        let double_next = &a * &a + &b * &b;
+        if n >> bit & 1 == 1 {
+            a = double_next;
+            b = double + &a;
+        } else {
+            a = double;
+            b = double_next;
+        }
+    }
+    Ok((a, b))
+}
+
+/// log2 of the golden ratio, the Fibonacci and Lucas numbers grow by this many bits
+const LOG2_PHI: f64 = 0.694_241_913_630_617_3;
+
+/// Fibonacci numbers, extended to negative n with F(-n) = (-1)^(n + 1) F(n)
+pub fn fibonacci(n: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let m = to_count(&n.abs(), "fib", ctx)?;
+    check_estimate(ctx, m as f64 * LOG2_PHI)?;
+    let (fib, _) = fibonacci_pair(m, ctx)?;
+    Ok(if n.is_negative() && m % 2 == 0 {
+        -fib
+    } else {
+        fib
+    })
+}
+
+/// Lucas numbers, extended to negative n with L(-n) = (-1)^n L(n)
+pub fn lucas(n: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let m = to_count(&n.abs(), "lucas", ctx)?;
+    check_estimate(ctx, m as f64 * LOG2_PHI)?;
+    // L(n) = 2F(n + 1) - F(n)
+    let (fib, fib_next) = fibonacci_pair(m, ctx)?;
+    let lucas: BigInt = (fib_next << 1) - fib;
+    Ok(if n.is_negative() && m % 2 == 1 {
+        -lucas
+    } else {
+        lucas
+    })
+}
+
+/// Catalan numbers, binom(2n, n) / (n + 1)
+pub fn catalan(n: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    to_count(n, "catalan", ctx)?;
+    Ok(binomial(&(n * 2), n, ctx)? / (n + 1))
+}
+
+/// The leading terms of de Bruijn's expansion of ln(B(n)) / n, ln(n) - ln(ln(n)) - 1, the
+/// remaining ones are positive
+fn log2_bell(n: f64) -> f64 {
+    if n < 2.0 {
+        return 0.0;
+    }
+    n * (n.ln() - n.ln().ln() - 1.0) / std::f64::consts::LN_2
+}
+
+/// Bell numbers with the Bell triangle
+pub fn bell(n: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let n = to_count(n, "bell", ctx)?;
+    check_estimate(ctx, log2_bell(n as f64))?;
+    let mut row = vec![BigInt::one()];
+    for _ in 0..n {
+        ctx.step()?;
+        // Every row starts with the last number of the previous one
+        let mut next = Vec::with_capacity(row.len() + 1);
+        next.push(row[row.len() - 1].clone());
+        for val in &row {
+            let sum = &next[next.len() - 1] + val;
+            next.push(sum);
+        }
+        ctx.check_bits(next[next.len() - 1].bits())?;
+        row = next;
+    }
+    Ok(row.swap_remove(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::tests::empty_evaluation;
+
+    type Function = fn(&BigInt, &Evaluation) -> Result<BigInt, CalcError>;
+
+    fn sequence(f: Function, range: std::ops::Range<i64>) -> Vec<BigInt> {
+        let ctx = empty_evaluation();
+        range.map(|n| f(&BigInt::from(n), &ctx).unwrap()).collect()
+    }
+
+    fn numbers(numbers: &[i64]) -> Vec<BigInt> {
+        numbers.iter().map(|&n| BigInt::from(n)).collect()
+    }
+
+    #[test]
+    fn test_factorial() {
+        let ctx = empty_evaluation();
+        for n in [0u64, 1, 5, 31, 32, 33, 100, 1000] {
+            assert_eq!(factorial(&n.into(), &ctx), Ok(factorial_small(n)));
+        }
+        assert!(factorial(&BigInt::from(-1), &ctx).is_err());
+        assert_eq!(
+            factorial(&BigInt::from(10_000_000), &ctx),
+            Err(CalcError::ResultTooLarge(1 << 24))
+        );
+    }
+
+    #[test]
+    fn test_double_factorial() {
+        assert_eq!(
+            sequence(double_factorial, -1..10),
+            numbers(&[1, 1, 1, 2, 3, 8, 15, 48, 105, 384, 945])
+        );
+    }
+
+    #[test]
+    fn test_binomial() {
+        let ctx = empty_evaluation();
+        let binom = |n: i64, k: i64| binomial(&n.into(), &k.into(), &ctx).unwrap();
+
+        assert_eq!(binom(5, 2), BigInt::from(10));
+        assert_eq!(binom(5, 6), BigInt::zero());
+        assert_eq!(binom(5, -1), BigInt::zero());
+        assert_eq!(binom(-5, 3), BigInt::from(-35));
+        assert_eq!(
+            binom(100, 50),
+            "100891344545564193334812497256".parse().unwrap()
+        );
+        // Large n with a small k is not sieved
+        let n: BigInt = BigInt::one() << 100;
+        assert_eq!(binomial(&n, &BigInt::from(2), &ctx), Ok(&n * (&n - 1) / 2));
+        // Neither is a large n below the sieve limit with a small k
+        let falling = binomial(&BigInt::from(1u64 << 31), &BigInt::from(40), &ctx).unwrap();
+        assert_eq!(
+            falling,
+            falling_factorial(&BigInt::from(1u64 << 31), 40, &ctx).unwrap() / factorial_small(40)
+        );
+        // The sieve and the falling factorial agree
+        let n = BigInt::from(1000);
+        assert_eq!(
+            binomial(&n, &BigInt::from(400), &ctx).unwrap(),
+            falling_factorial(&n, 400, &ctx).unwrap()
+                / factorial(&BigInt::from(400), &ctx).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_permutations() {
+        let ctx = empty_evaluation();
+        let perm = |n: i64, k: i64| permutations(&n.into(), &k.into(), &ctx);
+
+        assert_eq!(perm(5, 2), Ok(BigInt::from(20)));
+        assert_eq!(perm(5, 0), Ok(BigInt::one()));
+        assert_eq!(perm(5, 6), Ok(BigInt::zero()));
+        assert!(perm(-5, 2).is_err());
+    }
+
+    #[test]
+    fn test_sequences() {
+        assert_eq!(
+            sequence(fibonacci, -5..10),
+            numbers(&[5, -3, 2, -1, 1, 0, 1, 1, 2, 3, 5, 8, 13, 21, 34])
+        );
+        assert_eq!(
+            sequence(lucas, -3..7),
+            numbers(&[-4, 3, -1, 2, 1, 3, 4, 7, 11, 18])
+        );
+        assert_eq!(
+            sequence(catalan, 0..8),
+            numbers(&[1, 1, 2, 5, 14, 42, 132, 429])
+        );
+        assert_eq!(
+            sequence(bell, 0..8),
+            numbers(&[1, 1, 2, 5, 15, 52, 203, 877])
+        );
+
+        let ctx = empty_evaluation();
+        assert_eq!(
+            bell(&BigInt::from(10_000_000), &ctx),
+            Err(CalcError::ResultTooLarge(1 << 24))
+        );
+        // The estimate stays below the size
+        for n in [2u64, 10, 100, 500] {
+            let size = bell(&BigInt::from(n), &ctx).unwrap().bits() as f64;
+            assert!(log2_bell(n as f64) < size);
+        }
+    }
+
+    #[test]
+    fn test_fibonacci_large() {
+        let ctx = empty_evaluation();
+        let fib = fibonacci(&BigInt::from(100_000), &ctx).unwrap();
+        assert_eq!(fib.bits(), 69_424);
+        assert_eq!(fib % 1_000_000u32, BigInt::from(746_875));
+    }
+}
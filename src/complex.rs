@@ -0,0 +1,90 @@
+use num_bigint::BigInt;
+use num_complex::Complex;
+use num_integer::Integer;
+use num_traits::ToPrimitive;
+
+use crate::{error::CalcError, number_theory, value::Value};
+
+/// Floor of the absolute value
+pub fn abs(z: &Complex<BigInt>) -> BigInt {
+    z.norm_sqr().sqrt()
+}
+
+/// The components scaled down by `2^scale` so they fit in a float
+fn to_f64(z: &Complex<BigInt>) -> (f64, f64, u64) {
+    let scale = z.re.bits().max(z.im.bits()).saturating_sub(64);
+    let re = (&z.re >> scale).to_f64().unwrap_or(0.0);
+    let im = (&z.im >> scale).to_f64().unwrap_or(0.0);
+    (re, im, scale)
+}
+
+/// Angle in degrees from -180 (exclusive) to 180
+pub fn arg(z: &Complex<BigInt>) -> f64 {
+    let (re, im, _) = to_f64(z);
+    im.atan2(re).to_degrees()
+}
+
+/// Absolute value and angle in degrees. The absolute value is `mantissa * 2^exponent`,
+/// as it does not always fit in a float.
+pub fn polar(z: &Complex<BigInt>) -> (f64, u64, f64) {
+    let (re, im, scale) = to_f64(z);
+    (re.hypot(im), scale, im.atan2(re).to_degrees())
+}
+
+/// `z * w = 1 (mod n)`, exists if the norm of `z` is invertible
+pub fn inverse_mod(z: &Complex<BigInt>, modulus: &BigInt) -> Result<Complex<BigInt>, CalcError> {
+    let norm_inverse = number_theory::mod_inverse(&z.norm_sqr(), modulus)
+        .map_err(|_| CalcError::NoModularInverse(Value::from(z.clone()), modulus.clone()))?;
+    let w = z.conj() * norm_inverse;
+    Ok(Complex::new(
+        w.re.mod_floor(modulus),
+        w.im.mod_floor(modulus),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use num_traits::Zero;
+
+    use super::*;
+
+    fn z(re: i64, im: i64) -> Complex<BigInt> {
+        Complex::new(BigInt::from(re), BigInt::from(im))
+    }
+
+    #[test]
+    fn test_abs() {
+        assert_eq!(abs(&z(3, -4)), BigInt::from(5));
+        assert_eq!(abs(&z(1, 1)), BigInt::from(1));
+        assert_eq!(abs(&z(-7, 0)), BigInt::from(7));
+    }
+
+    #[test]
+    fn test_angles() {
+        assert_eq!(arg(&z(1, 1)), 45.0);
+        assert_eq!(arg(&z(-1, 0)), 180.0);
+        assert_eq!(arg(&z(0, -2)), -90.0);
+
+        let (magnitude, scale, angle) = polar(&z(3, 4));
+        assert_eq!((magnitude, scale), (5.0, 0));
+        assert!((angle - 53.1301).abs() < 1e-4);
+        let (magnitude, scale, _) = polar(&Complex::new(BigInt::from(1) << 100, BigInt::zero()));
+        assert_eq!(magnitude * 2f64.powi(scale as i32), 2f64.powi(100));
+    }
+
+    #[test]
+    fn test_inverse_mod() {
+        let modulus = BigInt::from(7);
+        let w = inverse_mod(&z(1, 2), &modulus).unwrap();
+        let product = z(1, 2) * w;
+        assert_eq!(
+            Value::complex(
+                product.re.mod_floor(&modulus),
+                product.im.mod_floor(&modulus)
+            ),
+            Value::Int(BigInt::from(1))
+        );
+        // 2+i has the norm 5
+        assert!(inverse_mod(&z(2, 1), &BigInt::from(5)).is_err());
+    }
+}
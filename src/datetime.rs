@@ -0,0 +1,320 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{ToPrimitive, Zero};
+
+use crate::{
+    error::CalcError,
+    units::{self, Quantity, Unit},
+    value::Value,
+};
+
+/// Nanoseconds per second, dates are stored as nanoseconds since the Unix epoch
+pub const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const SECONDS_PER_DAY: i64 = 86400;
+
+/// Dates must be in the years 0 to 9999, which ISO-8601 can write without a sign
+const MIN_DAYS: i64 = -719528;
+const MAX_DAYS: i64 = 2932896;
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Years start in March, so the leap day is the last day of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Year, month and day of the days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    days_from_civil(year + month / 12, month % 12 + 1, 1) - days_from_civil(year, month, 1)
+}
+
+/// Fails for dates outside of the years 0 to 9999
+pub fn check_range(nanos: &BigInt) -> Result<(), CalcError> {
+    let day_nanos = BigInt::from(SECONDS_PER_DAY * NANOS_PER_SECOND);
+    let days = nanos.div_floor(&day_nanos);
+    if days < BigInt::from(MIN_DAYS) || days > BigInt::from(MAX_DAYS) {
+        return Err(CalcError::DateOutOfRange);
+    }
+    Ok(())
+}
+
+/// Parses an ISO-8601 date at the start of the string, `YYYY-MM-DD` optionally followed by a time
+/// `THH:MM`, `THH:MM:SS` or `THH:MM:SS.fraction` and a `Z` or `+HH:MM` offset.
+/// Returns the nanoseconds since the Unix epoch and the length of the literal,
+/// or `None` if the string doesn't start with a date.
+pub fn parse_literal(s: &str) -> Result<Option<(BigInt, usize)>, CalcError> {
+    let bytes = s.as_bytes();
+    let number = |start: usize, len: usize| -> Option<i64> {
+        let digits = s.get(start..start + len)?;
+        match digits.bytes().all(|b| b.is_ascii_digit()) {
+            true => digits.parse().ok(),
+            false => None,
+        }
+    };
+    let (Some(year), Some(b'-'), Some(month), Some(b'-'), Some(day)) = (
+        number(0, 4),
+        bytes.get(4),
+        number(5, 2),
+        bytes.get(7),
+        number(8, 2),
+    ) else {
+        return Ok(None);
+    };
+    // The whole word is shown in the error
+    let end = s
+        .find(|c: char| !(c.is_ascii_alphanumeric() || ":.+-".contains(c)))
+        .unwrap_or(s.len());
+    let invalid = || CalcError::InvalidDateTime(s[..end].to_string());
+
+    let mut len = 10;
+    let mut seconds = 0;
+    let mut fraction = 0;
+    let mut offset = 0;
+    if bytes.get(len) == Some(&b'T') {
+        let (Some(hour), Some(b':'), Some(minute)) = (number(11, 2), bytes.get(13), number(14, 2))
+        else {
+            return Err(invalid());
+        };
+        if hour > 23 || minute > 59 {
+            return Err(invalid());
+        }
+        seconds = hour * 3600 + minute * 60;
+        len = 16;
+
+        if bytes.get(len) == Some(&b':') {
+            match number(17, 2) {
+                Some(second) if second <= 59 => seconds += second,
+                _ => return Err(invalid()),
+            }
+            len = 19;
+            if bytes.get(len) == Some(&b'.') {
+                let digits = bytes[len + 1..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_digit())
+                    .count();
+                if !(1..=9).contains(&digits) {
+                    return Err(invalid());
+                }
+                fraction =
+                    number(len + 1, digits).ok_or_else(invalid)? * 10i64.pow(9 - digits as u32);
+                len += 1 + digits;
+            }
+        }
+
+        match bytes.get(len) {
+            Some(b'Z') => len += 1,
+            Some(sign @ (b'+' | b'-')) => {
+                // Only a `+HH:MM` offset, otherwise the sign is an operator, e.g. `...T12:00+1h`
+                if let (Some(hours), Some(b':'), Some(minutes)) =
+                    (number(len + 1, 2), bytes.get(len + 3), number(len + 4, 2))
+                {
+                    if hours > 23 || minutes > 59 {
+                        return Err(invalid());
+                    }
+                    offset = (hours * 3600 + minutes * 60) * if *sign == b'+' { 1 } else { -1 };
+                    len += 6;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || bytes.get(len).is_some_and(|b| b.is_ascii_alphanumeric())
+    {
+        return Err(invalid());
+    }
+
+    let seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY + seconds - offset;
+    let nanos = BigInt::from(seconds) * NANOS_PER_SECOND + fraction;
+    Ok(Some((nanos, len)))
+}
+
+/// ISO-8601 in UTC, e.g. `2024-03-01T12:00:00Z`. The fraction of the second is only shown
+/// if it is not zero.
+pub fn format_datetime(nanos: &BigInt) -> String {
+    let (seconds, fraction) = nanos.div_mod_floor(&BigInt::from(NANOS_PER_SECOND));
+    let (days, seconds) = seconds.div_mod_floor(&BigInt::from(SECONDS_PER_DAY));
+    // In range, as every date is checked
+    let days = days.to_i64().unwrap_or(0);
+    let seconds = seconds.to_i64().unwrap_or(0);
+    let (year, month, day) = civil_from_days(days);
+
+    let fraction = match fraction.is_zero() {
+        true => String::new(),
+        false => format!(".{:09}", fraction)
+            .trim_end_matches('0')
+            .to_string(),
+    };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        fraction
+    )
+}
+
+/// The date `n` seconds, milliseconds or nanoseconds after the Unix epoch,
+/// `nanos` is the number of nanoseconds in the unit of `n`
+pub fn from_unix(n: &BigInt, nanos: i64) -> Result<BigInt, CalcError> {
+    let res = n * nanos;
+    check_range(&res)?;
+    Ok(res)
+}
+
+/// Seconds, milliseconds or nanoseconds since the Unix epoch, rounded down
+pub fn to_unix(date: &BigInt, nanos: i64) -> BigInt {
+    date.div_floor(&BigInt::from(nanos))
+}
+
+/// The difference of two dates as a duration
+fn duration(nanos: BigInt) -> Quantity {
+    Quantity {
+        value: BigRational::new(nanos, BigInt::from(NANOS_PER_SECOND)),
+        unit: Unit::duration(),
+    }
+}
+
+/// Nanoseconds of a time quantity, rounded down
+fn nanos(q: &Quantity) -> BigInt {
+    (&q.value * BigInt::from(NANOS_PER_SECOND))
+        .floor()
+        .to_integer()
+}
+
+/// `+` and `-` with a date: a date plus or minus a duration is a date,
+/// and the difference of two dates is a duration
+pub fn add(lhs: &Value, rhs: &Value, subtract: bool) -> Result<Value, CalcError> {
+    let operation = if subtract { "-" } else { "+" };
+    let res = match (lhs, rhs) {
+        (Value::DateTime(lhs), Value::DateTime(rhs)) if subtract => {
+            return Ok(Value::Quantity(Box::new(duration(lhs - rhs))))
+        }
+        (Value::DateTime(date), Value::Quantity(q)) if q.dimension() == units::TIME => {
+            match subtract {
+                true => date - nanos(q),
+                false => date + nanos(q),
+            }
+        }
+        (Value::Quantity(q), Value::DateTime(date))
+            if q.dimension() == units::TIME && !subtract =>
+        {
+            date + nanos(q)
+        }
+        _ => {
+            let describe = |val: &Value| match val {
+                Value::DateTime(_) => Ok(String::from("a date")),
+                val => Ok(units::dimension_name(
+                    val.to_quantity(operation)?.dimension(),
+                )),
+            };
+            return Err(CalcError::IncompatibleUnits(describe(lhs)?, describe(rhs)?));
+        }
+    };
+    check_range(&res)?;
+    Ok(Value::DateTime(res))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Option<(BigInt, usize)>, CalcError> {
+        parse_literal(s)
+    }
+
+    #[test]
+    fn test_civil_days() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(days_from_civil(0, 1, 1), MIN_DAYS);
+        assert_eq!(days_from_civil(9999, 12, 31), MAX_DAYS);
+        for days in [MIN_DAYS, -1, 0, 59, 11016, 19782, MAX_DAYS] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(1900, 2), 28);
+        assert_eq!(days_in_month(2023, 12), 31);
+    }
+
+    #[test]
+    fn test_parse_literal() {
+        let seconds = |s: i64| BigInt::from(s) * NANOS_PER_SECOND;
+
+        assert_eq!(parse("1970-01-01"), Ok(Some((seconds(0), 10))));
+        assert_eq!(
+            parse("2024-03-01T12:30:15Z + 1"),
+            Ok(Some((seconds(1709296215), 20)))
+        );
+        assert_eq!(
+            parse("2024-03-01T13:30:15+01:00"),
+            Ok(Some((seconds(1709296215), 25)))
+        );
+        assert_eq!(
+            parse("1969-12-31T23:59:59.25"),
+            Ok(Some((BigInt::from(-750_000_000), 22)))
+        );
+        assert_eq!(
+            parse("2024-03-01T12:00+1h"),
+            Ok(Some((seconds(1709294400), 16)))
+        );
+        assert_eq!(parse("2024-3-1"), Ok(None));
+        assert_eq!(parse("2024"), Ok(None));
+        for invalid in [
+            "2023-02-29",
+            "2024-13-01",
+            "2024-03-01T24:00",
+            "2024-03-01T12",
+            "2024-03-01T12:00:00.",
+            "2024-03-01x",
+        ] {
+            assert_eq!(
+                parse(invalid),
+                Err(CalcError::InvalidDateTime(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        let seconds = |s: i64| BigInt::from(s) * NANOS_PER_SECOND;
+
+        assert_eq!(format_datetime(&seconds(0)), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            format_datetime(&BigInt::from(-750_000_000)),
+            "1969-12-31T23:59:59.25Z"
+        );
+        assert_eq!(
+            format_datetime(&(seconds(days_from_civil(9999, 12, 31) * SECONDS_PER_DAY) + 1)),
+            "9999-12-31T00:00:00.000000001Z"
+        );
+
+        assert!(check_range(&seconds(MAX_DAYS * SECONDS_PER_DAY + 86399)).is_ok());
+        assert!(check_range(&seconds((MAX_DAYS + 1) * SECONDS_PER_DAY)).is_err());
+    }
+}
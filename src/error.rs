@@ -1,21 +1,87 @@
 use num_bigint::BigInt;
 
+use crate::{function::Arity, value::Value};
+
 #[derive(thiserror::Error, Debug, PartialEq, Eq)]
 pub enum CalcError {
     #[error("Invalid token at index {0}")]
     InvalidToken(usize),
     #[error("Attempted dividing {0} by zero")]
-    DivisionByZero(BigInt),
+    DivisionByZero(Value),
     #[error("Expected `)`")]
     UnclosedParanthesis,
+    #[error("Expected `]`")]
+    UnclosedBracket,
     #[error("Invalid Expresssion")]
     InvalidExpression,
     #[error("Unknown Variable {0}")]
     UnknownVariable(String),
+    #[error("Unknown Function {0}")]
+    UnknownFunction(String),
+    #[error("Unknown layout {0}")]
+    UnknownLayout(String),
+    #[error("Function {0} expects {1} arguments but got {2}")]
+    InvalidArgumentCount(String, Arity, usize),
+    #[error("Invalid argument for {0}: {1}")]
+    InvalidArgument(String, String),
+    #[error("{0} has no inverse modulo {1}")]
+    NoModularInverse(Value, BigInt),
+    #[error("Cannot assign to read-only variable {0}")]
+    ReadOnlyVariable(String),
     #[error("Cannot use special function name as variable: {0}")]
     SpecialVariableInvalidUse(String),
     #[error("Right hand side of bit shift operation too large: {0}")]
     InvalidBitShiftTooLarge(BigInt),
     #[error("Attempted to bit shift by negative value")]
     InvalidBitShiftNegative,
+    #[error("A word width is needed, set one with `width <bits>`")]
+    MissingWidth,
+    #[error("Negative exponent {0} is only allowed in modular mode")]
+    NegativeExponent(BigInt),
+    #[error("Even root of negative number {0}")]
+    NegativeRoot(BigInt),
+    #[error("Logarithm of non-positive number {0}")]
+    NonPositiveLogarithm(BigInt),
+    #[error("Result is larger than the limit of {0} bits")]
+    ResultTooLarge(u64),
+    #[error("Evaluation took too long")]
+    EvaluationTimeout,
+    #[error("Evaluation was interrupted")]
+    EvaluationInterrupted,
+    #[error("{0} is not defined for complex numbers")]
+    ComplexOperand(String),
+    #[error("{0} is not defined for matrices")]
+    MatrixOperand(String),
+    #[error("{0} is not defined for a {1} and a {2} matrix")]
+    IncompatibleMatrices(String, String, String),
+    #[error("Row {0} of the matrix has {1} elements instead of {2}")]
+    MatrixRowLength(usize, usize, usize),
+    #[error("Matrix is not invertible")]
+    SingularMatrix,
+    #[error("{0} is not defined for numbers with units")]
+    QuantityOperand(String),
+    #[error("Incompatible units {0} and {1}")]
+    IncompatibleUnits(String, String),
+    #[error("Unknown unit {0}")]
+    UnknownUnit(String),
+    #[error("Invalid date {0}")]
+    InvalidDateTime(String),
+    #[error("Date is outside of the years 0 to 9999")]
+    DateOutOfRange,
+    #[error("{0} is not defined for dates")]
+    DateTimeOperand(String),
+    #[error("Invalid IP address {0}")]
+    InvalidAddress(String),
+    #[error("{0} is not defined for networks")]
+    NetworkOperand(String),
+    #[error("Invalid text literal {0}")]
+    InvalidText(String),
+    #[error("{0} is not defined for functions")]
+    FunctionOperand(String),
+    #[error("Cannot redefine built-in function {0}")]
+    BuiltinFunctionRedefined(String),
+    #[error("Too many nested calls of function {0}")]
+    CallDepthExceeded(String),
+    #[error("Invalid command argument: {0}")]
+    InvalidCommandArgument(String),
 }
@@ -1,125 +1,815 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    cell::{Cell, OnceCell, RefCell, RefMut},
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
 
-use num_bigint::{BigInt, Sign};
-use num_traits::{Signed, ToPrimitive};
+use num_bigint::BigInt;
+use num_complex::Complex;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
 
 use crate::{
+    combinatorics, complex, datetime,
     error::CalcError,
+    function::{Function, UserFunction, FUNCTIONS},
+    matrix::Matrix,
+    number_theory,
     parser::{Assign, Expr, ExprBitwise, Factor, Term, RES_VAR},
+    random::Random,
+    units::{self, Quantity},
+    value::Value,
+    variable_store::{LayeredStore, VariableStore},
 };
 
-/// Saves the result to the given hashtable, only returns the variable
+/// Calls of user-defined functions can only be nested this deep. They have no conditions,
+/// so a function calling itself would never stop.
+const MAX_CALL_DEPTH: u32 = 32;
+
+/// Limits on the resources a single evaluation can use. `None` means unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum bit length of any intermediate or final result
+    pub max_bits: Option<u64>,
+    /// Maximum right hand side of the shift operators
+    pub max_shift: Option<u64>,
+    /// Maximum number of evaluated nodes of the expression
+    pub max_steps: Option<u64>,
+    /// Maximum wall-clock time of the evaluation
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_bits: Some(1 << 24),
+            max_shift: Some(0xFFFFFFFF),
+            max_steps: Some(1_000_000),
+            timeout: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+/// Session settings affecting the evaluation
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EvalOptions<'a> {
+    pub limits: Limits,
+    /// Bit width of the values for the rotate operators
+    pub width: Option<u32>,
+    /// Every result is reduced modulo this positive number, and division multiplies by the
+    /// modular inverse
+    pub modulus: Option<&'a BigInt>,
+    /// The evaluation is stopped with [`CalcError::EvaluationInterrupted`] as soon as this
+    /// flag is set, e.g. from another thread
+    pub interrupt: Option<&'a AtomicBool>,
+    /// Generator of the random functions, kept by the session so a seed repeats the numbers.
+    /// Without one every evaluation gets a new generator seeded from the time.
+    pub rng: Option<&'a RefCell<Random>>,
+    /// Functions defined in the session, e.g. with `f(x) = x**2`
+    pub functions: Option<&'a HashMap<String, UserFunction>>,
+}
+
+/// Saves the result to the given variable store, only returns the variable
 /// string of the left hand side. If there was no lhs variable, will still
-/// return RES_VAL string
+/// return RES_VAL string.
+/// The expression is only borrowed, so it can be evaluated again, e.g. against other variables.
+/// The evaluation is restricted by the default [`Limits`].
 pub fn eval_assignment(
-    ass: Assign,
-    variables: &mut HashMap<String, BigInt>,
+    ass: &Assign,
+    variables: &mut dyn VariableStore,
 ) -> Result<String, CalcError> {
-    match ass {
-        Assign::Assign(var, expr) => {
-            let res = eval_expr_bitwise(expr, variables)?;
-            variables.insert(var.clone(), res);
-            Ok(var)
+    eval_assignment_with(ass, variables, EvalOptions::default())
+}
+
+/// Same as [`eval_assignment`] with the given session settings
+pub fn eval_assignment_with(
+    ass: &Assign,
+    variables: &mut dyn VariableStore,
+    options: EvalOptions,
+) -> Result<String, CalcError> {
+    let (var, expr) = match ass {
+        Assign::Assign(var, expr) => (var.clone(), expr),
+        // Save the result in the special result variable
+        Assign::ExprBitwise(expr) => (RES_VAR.to_string(), expr),
+    };
+
+    let ctx = Evaluation::new(&*variables, options);
+    let res = ctx.reduce(eval_expr_bitwise(expr, &ctx)?.into_owned());
+    variables.set(&var, res)?;
+    Ok(var)
+}
+
+/// State of a single evaluation, keeps track of the used resources.
+/// Built-in functions get it to check the limits while running long computations.
+pub struct Evaluation<'a> {
+    variables: &'a dyn VariableStore,
+    options: EvalOptions<'a>,
+    steps: Cell<u64>,
+    deadline: Option<Instant>,
+    /// Used when the options have no generator
+    own_rng: OnceCell<RefCell<Random>>,
+    /// Number of the user-defined functions being called
+    depth: u32,
+}
+
+/// A built-in or a user-defined function
+enum Callable<'a> {
+    BuiltIn(&'static Function),
+    User(&'a UserFunction),
+}
+
+impl<'a> Evaluation<'a> {
+    pub(crate) fn new(variables: &'a dyn VariableStore, options: EvalOptions<'a>) -> Self {
+        Evaluation {
+            variables,
+            options,
+            steps: Cell::new(0),
+            deadline: options
+                .limits
+                .timeout
+                .map(|timeout| Instant::now() + timeout),
+            own_rng: OnceCell::new(),
+            depth: 0,
+        }
+    }
+
+    /// Evaluates an expression sharing the resources of this evaluation
+    pub(crate) fn eval(&self, expr: &'a ExprBitwise) -> Result<Cow<'a, Value>, CalcError> {
+        eval_expr_bitwise(expr, self)
+    }
+
+    fn is_function(&self, name: &str) -> bool {
+        FUNCTIONS.contains_key(name)
+            || self
+                .options
+                .functions
+                .is_some_and(|functions| functions.contains_key(name))
+    }
+
+    /// Finds the function and checks that it accepts the number of arguments
+    fn resolve(&self, name: &str, count: usize) -> Result<Callable<'a>, CalcError> {
+        let (callable, arity) = match FUNCTIONS.get(name) {
+            Some(function) => (Callable::BuiltIn(function), function.arity),
+            None => match self
+                .options
+                .functions
+                .and_then(|functions| functions.get(name))
+            {
+                Some(function) => (Callable::User(function), function.arity()),
+                None => return Err(CalcError::UnknownFunction(name.to_string())),
+            },
+        };
+        if !arity.accepts(count) {
+            return Err(CalcError::InvalidArgumentCount(
+                name.to_string(),
+                arity,
+                count,
+            ));
+        }
+        Ok(callable)
+    }
+
+    /// Calls a built-in or a user-defined function with evaluated arguments
+    pub(crate) fn call(&self, name: &str, args: &[&Value]) -> Result<Value, CalcError> {
+        match self.resolve(name, args.len())? {
+            Callable::BuiltIn(function) => function.apply(name, args, self),
+            Callable::User(function) => self.call_user_function(name, function, args),
+        }
+    }
+
+    /// Evaluates outside of the modular mode, for the exponents of powers. Reducing an
+    /// exponent modulo `N` would change the result, `2**10` is not `2**3` modulo 7.
+    fn without_modulus<T>(
+        &self,
+        eval: impl FnOnce(&Evaluation) -> Result<T, CalcError>,
+    ) -> Result<T, CalcError> {
+        if self.options.modulus.is_none() {
+            return eval(self);
+        }
+        let inner = Evaluation {
+            variables: self.variables,
+            options: EvalOptions {
+                modulus: None,
+                ..self.options
+            },
+            steps: Cell::new(self.steps.get()),
+            deadline: self.deadline,
+            own_rng: OnceCell::new(),
+            depth: self.depth,
+        };
+        let res = eval(&inner);
+        self.steps.set(inner.steps.get());
+        res
+    }
+
+    /// Evaluates the body with the parameters in a new scope on top of the variables.
+    /// The limits are shared with this evaluation.
+    fn call_user_function(
+        &self,
+        name: &str,
+        function: &UserFunction,
+        args: &[&Value],
+    ) -> Result<Value, CalcError> {
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(CalcError::CallDepthExceeded(name.to_string()));
+        }
+
+        let mut scope = LayeredStore::new(self.variables);
+        for (param, arg) in function.params.iter().zip(args) {
+            scope.scope.insert(param.clone(), (*arg).clone());
+        }
+        let inner = Evaluation {
+            variables: &scope,
+            options: self.options,
+            steps: Cell::new(self.steps.get()),
+            deadline: self.deadline,
+            own_rng: OnceCell::new(),
+            depth: self.depth + 1,
+        };
+        let res = eval_expr_bitwise(&function.body, &inner).map(Cow::into_owned);
+        self.steps.set(inner.steps.get());
+        res
+    }
+
+    /// Must be called before evaluating every node of the expression,
+    /// and in every iteration of long running loops
+    pub(crate) fn step(&self) -> Result<(), CalcError> {
+        if self
+            .options
+            .interrupt
+            .is_some_and(|i| i.load(Ordering::Relaxed))
+        {
+            return Err(CalcError::EvaluationInterrupted);
+        }
+
+        self.steps.set(self.steps.get() + 1);
+        if self
+            .options
+            .limits
+            .max_steps
+            .is_some_and(|max| self.steps.get() > max)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() > deadline)
+        {
+            return Err(CalcError::EvaluationTimeout);
+        }
+
+        Ok(())
+    }
+
+    /// Fails right away if that many more steps would go over the step limit.
+    /// Used to reject loops which could never finish in time.
+    pub(crate) fn check_steps(&self, count: &BigInt) -> Result<(), CalcError> {
+        match self.options.limits.max_steps {
+            Some(max) if count > &BigInt::from(max.saturating_sub(self.steps.get())) => {
+                Err(CalcError::EvaluationTimeout)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if a result with the given bit length is not allowed.
+    /// Used to reject operations before calculating huge results.
+    pub(crate) fn check_bits(&self, bits: u64) -> Result<(), CalcError> {
+        match self.options.limits.max_bits {
+            Some(max) if bits > max => Err(CalcError::ResultTooLarge(max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Right hand side of a left shift, checked against the shift limit
+    fn shift_amount(&self, rhs: &BigInt) -> Result<u64, CalcError> {
+        if rhs.is_negative() {
+            return Err(CalcError::InvalidBitShiftNegative);
+        }
+        match rhs.to_u64() {
+            Some(shift) if self.options.limits.max_shift.is_none_or(|max| shift <= max) => {
+                Ok(shift)
+            }
+            _ => Err(CalcError::InvalidBitShiftTooLarge(rhs.clone())),
+        }
+    }
+
+    /// Generator of the random functions
+    pub(crate) fn random(&self) -> RefMut<'_, Random> {
+        match self.options.rng {
+            Some(rng) => rng.borrow_mut(),
+            None => self.own_rng.get_or_init(Default::default).borrow_mut(),
         }
-        Assign::ExprBitwise(expr) => {
-            // Save the result in the special result variable
-            let res = eval_expr_bitwise(expr, variables)?;
-            variables.insert(RES_VAR.to_string(), res);
-            Ok(RES_VAR.to_string())
+    }
+
+    /// Word width of the session
+    pub(crate) fn width(&self) -> Result<u32, CalcError> {
+        self.options
+            .width
+            .filter(|width| *width > 0)
+            .ok_or(CalcError::MissingWidth)
+    }
+
+    /// Modulus of the modular mode
+    pub(crate) fn modulus(&self) -> Option<&BigInt> {
+        self.options.modulus
+    }
+
+    /// Rotates the lowest `width` bits of the value, negative values are taken in two's complement
+    fn rotate(&self, val: &BigInt, rhs: &BigInt, left: bool) -> Result<BigInt, CalcError> {
+        let width = u64::from(self.width()?);
+        self.check_bits(width)?;
+
+        let mask = (BigInt::from(1) << width) - 1;
+        let val = val & &mask;
+        let shift = rhs.mod_floor(&BigInt::from(width)).to_u64().unwrap_or(0);
+        let shift = if left { shift } else { (width - shift) % width };
+
+        Ok(((&val << shift) | (val >> (width - shift))) & mask)
+    }
+
+    /// `lhs / rhs`, truncated. In modular mode multiplies by the inverse of the divisor.
+    fn div(&self, lhs: &Value, rhs: &Value) -> Result<Value, CalcError> {
+        match (lhs, rhs, self.options.modulus) {
+            (Value::Int(lhs), Value::Int(rhs), Some(modulus)) => {
+                Ok(Value::Int(lhs * number_theory::mod_inverse(rhs, modulus)?))
+            }
+            (_, _, Some(modulus)) => {
+                let inverse = complex::inverse_mod(&rhs.to_complex("/")?, modulus)?;
+                Ok((lhs.to_complex("/")? * inverse).into())
+            }
+            _ if rhs.is_zero() => Err(CalcError::DivisionByZero(lhs.clone())),
+            (Value::Int(lhs), Value::Int(rhs), None) => Ok(Value::Int(lhs / rhs)),
+            // Both components are truncated
+            _ => Ok((lhs.to_complex("/")? / rhs.to_complex("/")?).into()),
         }
     }
+
+    /// `base ** exponent`. In modular mode a negative exponent raises the inverse of the base.
+    fn pow(&self, base: &Value, exponent: &BigInt) -> Result<Value, CalcError> {
+        let base = match base {
+            Value::Int(base) => return self.int_pow(base, exponent).map(Value::Int),
+            Value::Complex(base) => base,
+            Value::Matrix(base) => return self.matrix_pow(base, exponent).map(Value::Matrix),
+            Value::Quantity(base) => return base.pow(exponent, self).map(Value::from),
+            Value::DateTime(_) => return Err(CalcError::DateTimeOperand(String::from("**"))),
+            Value::Network(_) => return Err(CalcError::NetworkOperand(String::from("**"))),
+            Value::Function(_) => return Err(CalcError::FunctionOperand(String::from("**"))),
+        };
+
+        let (base, exponent) = match self.options.modulus {
+            Some(modulus) if exponent.is_negative() => {
+                (complex::inverse_mod(base, modulus)?, -exponent)
+            }
+            _ if exponent.is_negative() => {
+                return Err(CalcError::NegativeExponent(exponent.clone()))
+            }
+            _ => (base.clone(), exponent.clone()),
+        };
+        // |base| is at least the larger component
+        let bits = base.re.bits().max(base.im.bits());
+        let min_bits = (bits - 1).saturating_mul(exponent.to_u64().unwrap_or(u64::MAX));
+        self.check_bits(min_bits.saturating_add(1))?;
+
+        // Square and multiply, a Gaussian integer of magnitude 1 stays small for any exponent
+        let mut res = Complex::new(BigInt::one(), BigInt::zero());
+        for i in (0..exponent.bits()).rev() {
+            self.step()?;
+            res = self.reduce_complex(&res * &res);
+            if exponent.bit(i) {
+                res = self.reduce_complex(res * &base);
+            }
+            self.check_bits(res.re.bits().max(res.im.bits()))?;
+        }
+        Ok(res.into())
+    }
+
+    /// Powers of square matrices. A negative exponent raises the inverse, which only has integer
+    /// elements in modular mode or if the determinant is 1 or -1.
+    fn matrix_pow(&self, base: &Matrix, exponent: &BigInt) -> Result<Matrix, CalcError> {
+        if base.rows() != base.cols() {
+            return Err(CalcError::IncompatibleMatrices(
+                String::from("**"),
+                base.shape(),
+                base.shape(),
+            ));
+        }
+        let base = if exponent.is_negative() {
+            base.inverse(self.options.modulus, self)?
+        } else {
+            base.clone()
+        };
+
+        let exponent = exponent.magnitude();
+        let mut res = Matrix::identity(base.rows());
+        for i in (0..exponent.bits()).rev() {
+            self.step()?;
+            res = self.reduce_matrix(res.mul(&res, self)?);
+            if exponent.bit(i) {
+                res = self.reduce_matrix(res.mul(&base, self)?);
+            }
+            self.check_bits(res.bits())?;
+        }
+        Ok(res)
+    }
+
+    fn int_pow(&self, base: &BigInt, exponent: &BigInt) -> Result<BigInt, CalcError> {
+        if let Some(modulus) = self.options.modulus {
+            return Ok(if exponent.is_negative() {
+                number_theory::mod_inverse(base, modulus)?.modpow(&-exponent, modulus)
+            } else {
+                base.modpow(exponent, modulus)
+            });
+        }
+
+        if exponent.is_negative() {
+            return Err(CalcError::NegativeExponent(exponent.clone()));
+        }
+        // These stay small for any exponent
+        if exponent.is_zero() {
+            return Ok(BigInt::one());
+        }
+        if base.is_zero() || base.is_one() {
+            return Ok(base.clone());
+        }
+        if base == &-BigInt::one() {
+            return Ok(if exponent.is_even() {
+                BigInt::one()
+            } else {
+                -BigInt::one()
+            });
+        }
+
+        // |base| has at least `bits - 1` bits after the leading one
+        let min_bits = (base.bits() - 1).saturating_mul(exponent.to_u64().unwrap_or(u64::MAX));
+        self.check_bits(min_bits.saturating_add(1))?;
+        match exponent.to_u32() {
+            Some(exponent) => Ok(base.pow(exponent)),
+            None => Err(CalcError::ResultTooLarge(u64::from(u32::MAX))),
+        }
+    }
+
+    /// The value in the range `0..modulus` in modular mode, otherwise unchanged.
+    /// Both components of complex values are reduced, quantities, dates and networks are
+    /// never reduced.
+    fn reduce(&self, val: Value) -> Value {
+        match val {
+            Value::Int(n) => Value::Int(self.reduce_int(n)),
+            Value::Complex(z) => self.reduce_complex(z).into(),
+            Value::Matrix(m) => Value::Matrix(self.reduce_matrix(m)),
+            Value::Quantity(q) => Value::Quantity(q),
+            Value::DateTime(nanos) => Value::DateTime(nanos),
+            Value::Network(network) => Value::Network(network),
+            Value::Function(name) => Value::Function(name),
+        }
+    }
+
+    fn reduce_int(&self, n: BigInt) -> BigInt {
+        match self.options.modulus {
+            Some(modulus) if modulus.is_positive() => n.mod_floor(modulus),
+            _ => n,
+        }
+    }
+
+    fn reduce_complex(&self, z: Complex<BigInt>) -> Complex<BigInt> {
+        Complex::new(self.reduce_int(z.re), self.reduce_int(z.im))
+    }
+
+    fn reduce_matrix(&self, m: Matrix) -> Matrix {
+        match self.options.modulus {
+            Some(modulus) if modulus.is_positive() => m.map(|n| n.mod_floor(modulus)),
+            _ => m,
+        }
+    }
+
+    fn checked_result<'r>(&self, res: Value) -> Result<Cow<'r, Value>, CalcError> {
+        let res = self.reduce(res);
+        self.check_bits(res.bits())?;
+        Ok(Cow::Owned(res))
+    }
+}
+
+/// Applies the operation to two integers, or the same operation on complex numbers when
+/// either of them is complex. Matrices are only combined with matrices, elementwise.
+fn arithmetic(
+    operation: &str,
+    lhs: &Value,
+    rhs: &Value,
+    int: fn(&BigInt, &BigInt) -> BigInt,
+    complex: fn(&Complex<BigInt>, &Complex<BigInt>) -> Complex<BigInt>,
+) -> Result<Value, CalcError> {
+    Ok(match (lhs, rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => Value::Int(int(lhs, rhs)),
+        (Value::Matrix(lhs), Value::Matrix(rhs)) => Value::Matrix(lhs.zip(rhs, operation, int)?),
+        _ => complex(&lhs.to_complex(operation)?, &rhs.to_complex(operation)?).into(),
+    })
+}
+
+/// Whether either operand has a unit, then the operation is done on quantities
+fn has_unit(lhs: &Value, rhs: &Value) -> bool {
+    matches!(lhs, Value::Quantity(_)) || matches!(rhs, Value::Quantity(_))
+}
+
+fn has_date(lhs: &Value, rhs: &Value) -> bool {
+    matches!(lhs, Value::DateTime(_)) || matches!(rhs, Value::DateTime(_))
 }
 
-fn eval_expr_bitwise(
-    expr_bitwise: ExprBitwise,
-    variables: &mut HashMap<String, BigInt>,
-) -> Result<BigInt, CalcError> {
-    match expr_bitwise {
+/// `+`, `-`, `*` or `/` on quantities, integers are numbers without a unit
+fn quantity_arithmetic(
+    operation: &str,
+    lhs: &Value,
+    rhs: &Value,
+    ctx: &Evaluation,
+) -> Result<Value, CalcError> {
+    let (lhs, rhs) = (lhs.to_quantity(operation)?, rhs.to_quantity(operation)?);
+    let res = match operation {
+        "+" => lhs.add(&rhs, false)?,
+        "-" => lhs.add(&rhs, true)?,
+        _ => {
+            // The fractions are not reduced yet
+            ctx.check_bits(lhs.bits().saturating_add(rhs.bits()))?;
+            lhs.mul(&rhs, operation == "/")?
+        }
+    };
+    Ok(res.into())
+}
+
+/// The sum of the numbers with units of a literal. A duration like `3d 4h` is shown the same way.
+fn quantity(parts: &[(BigRational, String)]) -> Result<Quantity, CalcError> {
+    let mut res: Option<Quantity> = None;
+    for (n, unit) in parts {
+        let unit = units::unit(unit).ok_or_else(|| CalcError::UnknownUnit(unit.clone()))?;
+        let part = Quantity::new(n.clone(), unit);
+        res = Some(match res {
+            Some(res) => res.add(&part, false)?,
+            None => part,
+        });
+    }
+    let mut res = res.ok_or(CalcError::InvalidExpression)?;
+    if parts.len() > 1 && res.dimension() == units::TIME {
+        res.unit = units::Unit::duration();
+    }
+    Ok(res)
+}
+
+/// Matrix product, or the product of a matrix and an integer
+fn mul(lhs: &Value, rhs: &Value, ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(match (lhs, rhs) {
+        (Value::Matrix(lhs), Value::Matrix(rhs)) => Value::Matrix(lhs.mul(rhs, ctx)?),
+        (Value::Matrix(m), n) | (n, Value::Matrix(m)) => {
+            let n = n.as_int("*")?;
+            Value::Matrix(m.map(|element| element * n))
+        }
+        _ => arithmetic("*", lhs, rhs, |lhs, rhs| lhs * rhs, |lhs, rhs| lhs * rhs)?,
+    })
+}
+
+// The values of the variables are only borrowed while evaluating,
+// they are never cloned unless they are the result of the whole expression.
+// Every evaluated node counts as a step and the size of all the calculated values is checked.
+
+fn eval_expr_bitwise<'a>(
+    expr_bitwise: &'a ExprBitwise,
+    ctx: &Evaluation<'a>,
+) -> Result<Cow<'a, Value>, CalcError> {
+    ctx.step()?;
+    let res = match expr_bitwise {
         ExprBitwise::BitwiseOr(eb, e) => {
-            Ok(eval_expr_bitwise(*eb, variables)? | eval_expr(e, variables)?)
+            let (lhs, rhs) = (eval_expr_bitwise(eb, ctx)?, eval_expr(e, ctx)?);
+            lhs.as_int("|")? | rhs.as_int("|")?
         }
         ExprBitwise::BitwiseAnd(eb, e) => {
-            Ok(eval_expr_bitwise(*eb, variables)? & eval_expr(e, variables)?)
+            let (lhs, rhs) = (eval_expr_bitwise(eb, ctx)?, eval_expr(e, ctx)?);
+            lhs.as_int("&")? & rhs.as_int("&")?
         }
         ExprBitwise::BitwiseXor(eb, e) => {
-            Ok(eval_expr_bitwise(*eb, variables)? ^ eval_expr(e, variables)?)
+            let (lhs, rhs) = (eval_expr_bitwise(eb, ctx)?, eval_expr(e, ctx)?);
+            lhs.as_int("^")? ^ rhs.as_int("^")?
         }
         ExprBitwise::BitshiftLeft(eb, e) => {
-            let rhs = eval_expr(e, variables)?;
-            if rhs.is_negative() {
-                return Err(CalcError::InvalidBitShiftNegative);
+            let rhs = eval_expr(e, ctx)?;
+            let lhs = eval_expr_bitwise(eb, ctx)?;
+            let (lhs, rhs) = (lhs.as_int("<<")?, rhs.as_int("<<")?);
+            // Zero stays zero however far it is shifted
+            if lhs.is_zero() && !rhs.is_negative() {
+                BigInt::zero()
+            } else {
+                let rhs = ctx.shift_amount(rhs)?;
+                ctx.check_bits(lhs.bits().saturating_add(rhs))?;
+                lhs << rhs
             }
-            let Some(rhs) = rhs.to_u16() else {
-                return Err(CalcError::InvalidBitShiftTooLarge(rhs));
-            };
-            Ok(eval_expr_bitwise(*eb, variables)? << rhs)
         }
         ExprBitwise::BitshiftRight(eb, e) => {
-            let rhs = eval_expr(e, variables)?;
+            let rhs = eval_expr(e, ctx)?;
+            let lhs = eval_expr_bitwise(eb, ctx)?;
+            let (lhs, rhs) = (lhs.as_int(">>")?, rhs.as_int(">>")?);
             if rhs.is_negative() {
                 return Err(CalcError::InvalidBitShiftNegative);
             }
-            let Some(rhs) = rhs.to_u16() else {
-                return Err(CalcError::InvalidBitShiftTooLarge(rhs));
-            };
-            Ok(eval_expr_bitwise(*eb, variables)? >> rhs)
+            // Shifting out every bit leaves only the sign, however large the amount
+            match rhs.to_u64() {
+                Some(shift) if shift < lhs.bits() => lhs >> shift,
+                _ if lhs.is_negative() => BigInt::from(-1),
+                _ => BigInt::zero(),
+            }
         }
-        ExprBitwise::Expr(e) => eval_expr(e, variables),
-    }
+        ExprBitwise::RotateLeft(eb, e) => {
+            let rhs = eval_expr(e, ctx)?;
+            let lhs = eval_expr_bitwise(eb, ctx)?;
+            ctx.rotate(lhs.as_int("<<<")?, rhs.as_int("<<<")?, true)?
+        }
+        ExprBitwise::RotateRight(eb, e) => {
+            let rhs = eval_expr(e, ctx)?;
+            let lhs = eval_expr_bitwise(eb, ctx)?;
+            ctx.rotate(lhs.as_int(">>>")?, rhs.as_int(">>>")?, false)?
+        }
+        ExprBitwise::Convert(eb, unit) => {
+            let unit = units::compound_unit(unit)?;
+            let res = eval_expr_bitwise(eb, ctx)?
+                .to_quantity("in")?
+                .convert(unit)?;
+            return ctx.checked_result(Value::Quantity(Box::new(res)));
+        }
+        ExprBitwise::Expr(e) => return eval_expr(e, ctx),
+    };
+    ctx.checked_result(Value::Int(res))
 }
 
-fn eval_expr(expr: Expr, variables: &mut HashMap<String, BigInt>) -> Result<BigInt, CalcError> {
-    match expr {
-        Expr::Sum(e, t) => Ok(eval_expr(*e, variables)? + eval_term(t, variables)?),
-        Expr::Subtract(e, t) => Ok(eval_expr(*e, variables)? - eval_term(t, variables)?),
-        Expr::Term(t) => eval_term(t, variables),
-        Expr::Negative(e) => Ok(-(eval_expr(*e, variables)?)),
-    }
+fn eval_expr<'a>(expr: &'a Expr, ctx: &Evaluation<'a>) -> Result<Cow<'a, Value>, CalcError> {
+    ctx.step()?;
+    let res = match expr {
+        Expr::Sum(e, t) => {
+            let (lhs, rhs) = (eval_expr(e, ctx)?, eval_term(t, ctx)?);
+            if has_date(&lhs, &rhs) {
+                datetime::add(&lhs, &rhs, false)?
+            } else if has_unit(&lhs, &rhs) {
+                quantity_arithmetic("+", &lhs, &rhs, ctx)?
+            } else {
+                arithmetic("+", &lhs, &rhs, |lhs, rhs| lhs + rhs, |lhs, rhs| lhs + rhs)?
+            }
+        }
+        Expr::Subtract(e, t) => {
+            let (lhs, rhs) = (eval_expr(e, ctx)?, eval_term(t, ctx)?);
+            if has_date(&lhs, &rhs) {
+                datetime::add(&lhs, &rhs, true)?
+            } else if has_unit(&lhs, &rhs) {
+                quantity_arithmetic("-", &lhs, &rhs, ctx)?
+            } else {
+                arithmetic("-", &lhs, &rhs, |lhs, rhs| lhs - rhs, |lhs, rhs| lhs - rhs)?
+            }
+        }
+        Expr::Term(t) => return eval_term(t, ctx),
+        Expr::Negative(e) => match &*eval_expr(e, ctx)? {
+            Value::Int(n) => Value::Int(-n),
+            Value::Complex(z) => Value::Complex(-z),
+            Value::Matrix(m) => Value::Matrix(m.map(|n| -n)),
+            Value::Quantity(q) => Value::Quantity(Box::new(-(**q).clone())),
+            Value::DateTime(_) => return Err(CalcError::DateTimeOperand(String::from("-"))),
+            Value::Network(_) => return Err(CalcError::NetworkOperand(String::from("-"))),
+            Value::Function(_) => return Err(CalcError::FunctionOperand(String::from("-"))),
+        },
+    };
+    ctx.checked_result(res)
 }
 
-fn eval_term(t: Term, variables: &mut HashMap<String, BigInt>) -> Result<BigInt, CalcError> {
-    match t {
-        Term::Mult(t, f) => Ok(eval_term(*t, variables)? * eval_factor(f, variables)?),
+fn eval_term<'a>(t: &'a Term, ctx: &Evaluation<'a>) -> Result<Cow<'a, Value>, CalcError> {
+    ctx.step()?;
+    let res = match t {
+        Term::Mult(t, f) => {
+            let lhs = eval_term(t, ctx)?;
+            let rhs = eval_factor(f, ctx)?;
+            // The product has at least one bit less than the sum of the operands' bits.
+            // The components of complex products can cancel out, they are checked afterwards.
+            if let (Value::Int(lhs), Value::Int(rhs)) = (&*lhs, &*rhs) {
+                ctx.check_bits((lhs.bits() + rhs.bits()).saturating_sub(1))?;
+            }
+            if has_unit(&lhs, &rhs) {
+                quantity_arithmetic("*", &lhs, &rhs, ctx)?
+            } else {
+                mul(&lhs, &rhs, ctx)?
+            }
+        }
         Term::Div(t, f) => {
-            let lhs = eval_term(*t, variables)?;
-            lhs.checked_div(&eval_factor(f, variables)?)
-                .ok_or(CalcError::DivisionByZero(lhs))
+            let lhs = eval_term(t, ctx)?;
+            let rhs = eval_factor(f, ctx)?;
+            if has_unit(&lhs, &rhs) {
+                quantity_arithmetic("/", &lhs, &rhs, ctx)?
+            } else {
+                ctx.div(&lhs, &rhs)?
+            }
         }
-        Term::Modulo(t, f) => Ok(eval_term(*t, variables)? % eval_factor(f, variables)?),
-        Term::Factor(f) => eval_factor(f, variables),
-    }
+        Term::Modulo(t, f) => {
+            let lhs = eval_term(t, ctx)?;
+            let rhs = eval_factor(f, ctx)?;
+            let (lhs, rhs) = (lhs.as_int("%")?, rhs.as_int("%")?);
+            if rhs.is_zero() {
+                return Err(CalcError::DivisionByZero(lhs.clone().into()));
+            }
+            Value::Int(lhs % rhs)
+        }
+        Term::Factor(f) => return eval_factor(f, ctx),
+    };
+    ctx.checked_result(res)
 }
 
-fn eval_factor(f: Factor, variables: &mut HashMap<String, BigInt>) -> Result<BigInt, CalcError> {
+fn eval_factor<'a>(f: &'a Factor, ctx: &Evaluation<'a>) -> Result<Cow<'a, Value>, CalcError> {
+    ctx.step()?;
     match f {
-        Factor::Number(n) => Ok(BigInt::from_biguint(Sign::Plus, n)),
-        Factor::Parenthesis(e) => eval_expr(*e, variables),
-        Factor::Variable(var) => variables
-            .get(var.as_str())
-            .ok_or(CalcError::UnknownVariable(var))
-            .cloned(),
+        Factor::Number(n) => Ok(Cow::Borrowed(n)),
+        Factor::Imaginary(n) => ctx.checked_result(Value::complex(BigInt::zero(), n.clone())),
+        Factor::Decimal(n) => ctx.checked_result(Quantity::plain(n.clone()).into()),
+        Factor::Quantity(parts) => ctx.checked_result(Value::Quantity(Box::new(quantity(parts)?))),
+        Factor::Network(network) => Ok(Cow::Owned(Value::Network(network.clone()))),
+        Factor::DateTime(nanos) => {
+            datetime::check_range(nanos)?;
+            Ok(Cow::Owned(Value::DateTime(nanos.clone())))
+        }
+        Factor::Parenthesis(e) => eval_expr_bitwise(e, ctx),
+        Factor::Matrix(rows) => {
+            let rows = rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|element| {
+                            eval_expr_bitwise(element, ctx)?
+                                .into_owned()
+                                .into_int("matrix")
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            ctx.checked_result(Value::Matrix(Matrix::from_rows(rows)?))
+        }
+        Factor::Variable(var) => match ctx.variables.get(var) {
+            Some(val) => Ok(val),
+            // A function name without arguments refers to the function, e.g. in `map(f, list)`
+            None if ctx.is_function(var) => Ok(Cow::Owned(Value::Function(var.clone()))),
+            None => Err(CalcError::UnknownVariable(var.clone())),
+        },
+        Factor::Call(name, args) => {
+            ctx.resolve(name, args.len())?;
+            let args = args
+                .iter()
+                .map(|arg| eval_expr_bitwise(arg, ctx))
+                .collect::<Result<Vec<_>, _>>()?;
+            let args: Vec<&Value> = args.iter().map(AsRef::as_ref).collect();
+            ctx.checked_result(ctx.call(name, &args)?)
+        }
+        Factor::Power(base, exponent) => {
+            let base = eval_factor(base, ctx)?;
+            let exponent =
+                ctx.without_modulus(|ctx| eval_factor(exponent, ctx).map(Cow::into_owned))?;
+            ctx.checked_result(ctx.pow(&base, exponent.as_int("**")?)?)
+        }
+        Factor::Factorial(f) => ctx.checked_result(Value::Int(combinatorics::factorial(
+            eval_factor(f, ctx)?.as_int("!")?,
+            ctx,
+        )?)),
+        Factor::DoubleFactorial(f) => ctx.checked_result(Value::Int(
+            combinatorics::double_factorial(eval_factor(f, ctx)?.as_int("!!")?, ctx)?,
+        )),
     }
 }
 
 #[cfg(test)]
-mod tests {
-    use num_bigint::BigUint;
+pub(crate) mod tests {
+    use std::collections::HashMap;
 
     use super::*;
+    use crate::variable_store::{LayeredStore, ReadOnlyStore};
+
+    /// Evaluation with the default limits
+    fn evaluation(variables: &dyn VariableStore) -> Evaluation<'_> {
+        Evaluation::new(variables, EvalOptions::default())
+    }
+
+    /// Evaluation without variables and with the default limits, for the tests of the functions
+    pub(crate) fn empty_evaluation() -> Evaluation<'static> {
+        struct NoVariables;
+        impl VariableStore for NoVariables {
+            fn get(&self, _var: &str) -> Option<Cow<'_, Value>> {
+                None
+            }
+            fn set(&mut self, _var: &str, _val: Value) -> Result<(), CalcError> {
+                Ok(())
+            }
+        }
+        Evaluation::new(&NoVariables, EvalOptions::default())
+    }
 
     #[test]
     fn test_evaluation_paranthesis() {
         assert_eq!(
             eval_expr(
-                Expr::Term(Term::Factor(Factor::Parenthesis(Box::from(Expr::Term(
-                    Term::Div(
-                        Box::from(Term::Factor(Factor::Number(BigUint::from(120usize)))),
-                        Factor::Number(BigUint::from(24usize)),
-                    ),
-                ))))),
-                &mut HashMap::new()
+                &Expr::Term(Term::Factor(Factor::Parenthesis(Box::from(
+                    ExprBitwise::Expr(Expr::Term(Term::Div(
+                        Box::from(Term::Factor(Factor::Number(BigInt::from(120usize).into()))),
+                        Factor::Number(BigInt::from(24usize).into()),
+                    )))
+                )))),
+                &evaluation(&HashMap::new())
             ),
-            Ok(BigInt::from(5))
+            Ok(Cow::Owned(Value::Int(BigInt::from(5))))
         )
     }
 
@@ -127,13 +817,13 @@ mod tests {
     fn test_evaluation_div_by_zero() {
         assert_eq!(
             eval_expr(
-                Expr::Term(Term::Div(
-                    Box::from(Term::Factor(Factor::Number(BigUint::from(120usize)))),
-                    Factor::Number(BigUint::from(0usize)),
+                &Expr::Term(Term::Div(
+                    Box::from(Term::Factor(Factor::Number(BigInt::from(120usize).into()))),
+                    Factor::Number(BigInt::from(0usize).into()),
                 )),
-                &mut HashMap::new()
+                &evaluation(&HashMap::new())
             ),
-            Err(CalcError::DivisionByZero(BigInt::from(120)))
+            Err(CalcError::DivisionByZero(BigInt::from(120).into()))
         )
     }
 
@@ -141,12 +831,12 @@ mod tests {
     fn test_evaluation_negative() {
         assert_eq!(
             eval_expr(
-                Expr::Negative(Box::new(Expr::Term(Term::Factor(Factor::Number(
-                    BigUint::from(120usize)
+                &Expr::Negative(Box::new(Expr::Term(Term::Factor(Factor::Number(
+                    BigInt::from(120usize).into()
                 ))))),
-                &mut HashMap::new()
+                &evaluation(&HashMap::new())
             ),
-            Ok(BigInt::from(-120))
+            Ok(Cow::Owned(Value::Int(BigInt::from(-120))))
         )
     }
 
@@ -155,46 +845,86 @@ mod tests {
         let mut vars = HashMap::new();
         assert_eq!(
             eval_assignment(
-                Assign::Assign(
+                &Assign::Assign(
                     String::from("asd"),
-                    ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Number(BigUint::from(
-                        123usize
-                    ))))),
+                    ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Number(
+                        BigInt::from(123usize).into()
+                    )))),
                 ),
                 &mut vars,
             ),
             Ok(String::from("asd"))
         );
-        assert_eq!(vars[&String::from("asd")], BigInt::from(123));
+        assert_eq!(vars[&String::from("asd")], Value::Int(BigInt::from(123)));
     }
 
     #[test]
     fn test_evaluation_variable() {
-        let mut vars = HashMap::from([(String::from("asd"), BigInt::from(123))]);
+        let vars = HashMap::from([(String::from("asd"), Value::Int(BigInt::from(123)))]);
 
         assert_eq!(
-            eval_factor(Factor::Variable(String::from("asd")), &mut vars),
-            Ok(BigInt::from(123))
+            eval_factor(&Factor::Variable(String::from("asd")), &evaluation(&vars)),
+            Ok(Cow::Owned(Value::Int(BigInt::from(123))))
         )
     }
 
+    #[test]
+    fn test_evaluation_variable_borrowed() {
+        // Variables must not be cloned while evaluating
+        let vars = HashMap::from([(String::from("asd"), Value::Int(BigInt::from(123)))]);
+
+        assert!(matches!(
+            eval_factor(&Factor::Variable(String::from("asd")), &evaluation(&vars)),
+            Ok(Cow::Borrowed(_))
+        ));
+    }
+
+    #[test]
+    fn test_evaluation_number_borrowed() {
+        // Literals are stored as values so they are not cloned either
+        assert!(matches!(
+            eval_factor(
+                &Factor::Number(BigInt::from(7).into()),
+                &evaluation(&HashMap::new())
+            ),
+            Ok(Cow::Borrowed(_))
+        ));
+    }
+
+    #[test]
+    fn test_evaluation_reuse_expression() {
+        // The same expression can be evaluated against different variables
+        let ass = Assign::ExprBitwise(ExprBitwise::Expr(Expr::Term(Term::Mult(
+            Box::new(Term::Factor(Factor::Variable(String::from("asd")))),
+            Factor::Number(BigInt::from(2).into()),
+        ))));
+        let mut first = HashMap::from([(String::from("asd"), Value::Int(BigInt::from(1)))]);
+        let mut second = HashMap::from([(String::from("asd"), Value::Int(BigInt::from(10)))]);
+
+        eval_assignment(&ass, &mut first).unwrap();
+        eval_assignment(&ass, &mut second).unwrap();
+
+        assert_eq!(first[&RES_VAR.to_string()], Value::Int(BigInt::from(2)));
+        assert_eq!(second[&RES_VAR.to_string()], Value::Int(BigInt::from(20)));
+    }
+
     #[test]
     fn test_evaluation_assign_twice() {
-        let mut vars = HashMap::from([(String::from("asd"), BigInt::from(123))]);
+        let mut vars = HashMap::from([(String::from("asd"), Value::Int(BigInt::from(123)))]);
 
         assert_eq!(
             eval_assignment(
-                Assign::Assign(
+                &Assign::Assign(
                     String::from("asd"),
-                    ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Number(BigUint::from(
-                        10usize
-                    ))))),
+                    ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Number(
+                        BigInt::from(10usize).into()
+                    )))),
                 ),
                 &mut vars,
             ),
             Ok(String::from("asd"))
         );
-        assert_eq!(vars[&String::from("asd")], BigInt::from(10));
+        assert_eq!(vars[&String::from("asd")], Value::Int(BigInt::from(10)));
     }
 
     #[test]
@@ -203,27 +933,50 @@ mod tests {
         let mut vars = HashMap::new();
 
         eval_assignment(
-            Assign::ExprBitwise(ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Number(
-                BigUint::from(120usize),
+            &Assign::ExprBitwise(ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Number(
+                BigInt::from(120usize).into(),
             ))))),
             &mut vars,
         )
         .unwrap();
 
-        assert_eq!(vars[&RES_VAR.to_string()], BigInt::from(120));
+        assert_eq!(vars[&RES_VAR.to_string()], Value::Int(BigInt::from(120)));
+    }
+
+    #[test]
+    fn test_evaluation_layered_store() {
+        // Results are kept in the scope while the variables of the parent are still visible
+        let parent = ReadOnlyStore(HashMap::from([(
+            String::from("asd"),
+            Value::Int(BigInt::from(123)),
+        )]));
+        let mut store = LayeredStore::new(&parent);
+
+        eval_assignment(
+            &Assign::ExprBitwise(ExprBitwise::Expr(Expr::Term(Term::Factor(
+                Factor::Variable(String::from("asd")),
+            )))),
+            &mut store,
+        )
+        .unwrap();
+
+        assert_eq!(
+            store.scope[&RES_VAR.to_string()],
+            Value::Int(BigInt::from(123))
+        );
     }
 
     #[test]
     fn test_evaluation_modulo() {
         assert_eq!(
             eval_term(
-                Term::Modulo(
-                    Box::from(Term::Factor(Factor::Number(BigUint::from(120usize)))),
-                    Factor::Number(BigUint::from(17usize)),
+                &Term::Modulo(
+                    Box::from(Term::Factor(Factor::Number(BigInt::from(120usize).into()))),
+                    Factor::Number(BigInt::from(17usize).into()),
                 ),
-                &mut HashMap::new()
+                &evaluation(&HashMap::new())
             ),
-            Ok(BigInt::from(1))
+            Ok(Cow::Owned(Value::Int(BigInt::from(1))))
         )
     }
 
@@ -231,18 +984,18 @@ mod tests {
     fn test_evaluation_shift_large() {
         assert_eq!(
             eval_expr_bitwise(
-                ExprBitwise::BitshiftRight(
+                &ExprBitwise::BitshiftLeft(
                     Box::new(ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Number(
-                        BigUint::from(1usize)
+                        BigInt::from(1usize).into()
                     ))))),
-                    Expr::Term(Term::Factor(Factor::Number(BigUint::from(
-                        u16::MAX as usize + 1
-                    ))))
+                    Expr::Term(Term::Factor(Factor::Number(
+                        BigInt::from(u32::MAX as usize + 1).into()
+                    )))
                 ),
-                &mut HashMap::new()
+                &evaluation(&HashMap::new())
             ),
             Err(CalcError::InvalidBitShiftTooLarge(BigInt::from(
-                u16::MAX as usize + 1
+                u32::MAX as usize + 1
             )))
         );
     }
@@ -251,17 +1004,147 @@ mod tests {
     fn test_evaluation_shift_negative() {
         assert_eq!(
             eval_expr_bitwise(
-                ExprBitwise::BitshiftRight(
+                &ExprBitwise::BitshiftRight(
                     Box::new(ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Number(
-                        BigUint::from(1usize),
+                        BigInt::from(1usize).into(),
                     ))))),
                     Expr::Negative(Box::new(Expr::Term(Term::Factor(Factor::Number(
-                        BigUint::from(1usize),
+                        BigInt::from(1usize).into(),
                     ))))),
                 ),
-                &mut HashMap::new(),
+                &evaluation(&HashMap::new()),
             ),
             Err(CalcError::InvalidBitShiftNegative)
         );
     }
+
+    #[test]
+    fn test_evaluation_power() {
+        let power = |base: i64, exponent: i64| {
+            eval_factor(
+                &Factor::Power(
+                    Box::new(Factor::Number(BigInt::from(base).into())),
+                    Box::new(Factor::Number(BigInt::from(exponent).into())),
+                ),
+                &evaluation(&HashMap::new()),
+            )
+            .and_then(|res| res.into_owned().into_int("**"))
+        };
+
+        assert_eq!(power(-3, 3), Ok(BigInt::from(-27)));
+        assert_eq!(power(0, 0), Ok(BigInt::from(1)));
+        assert_eq!(power(-1, i64::MAX), Ok(BigInt::from(-1)));
+        assert_eq!(
+            power(2, -1),
+            Err(CalcError::NegativeExponent(BigInt::from(-1)))
+        );
+        assert_eq!(power(2, 1 << 24), Err(CalcError::ResultTooLarge(1 << 24)));
+    }
+
+    #[test]
+    fn test_evaluation_modular() {
+        let modulus = BigInt::from(7);
+        let options = EvalOptions {
+            modulus: Some(&modulus),
+            ..Default::default()
+        };
+        let number = |n: i64| Box::new(Factor::Number(BigInt::from(n).into()));
+        let variables = HashMap::new();
+
+        // 3 / 2 is 3 * 4 mod 7
+        assert_eq!(
+            eval_term(
+                &Term::Div(Box::new(Term::Factor(*number(3))), *number(2)),
+                &Evaluation::new(&variables, options)
+            ),
+            Ok(Cow::Owned(Value::Int(BigInt::from(5))))
+        );
+        assert_eq!(
+            eval_term(
+                &Term::Div(Box::new(Term::Factor(*number(3))), *number(14)),
+                &Evaluation::new(&variables, options)
+            ),
+            Err(CalcError::NoModularInverse(
+                BigInt::from(14).into(),
+                modulus.clone()
+            ))
+        );
+        // 2 ** -1 is the inverse of 2
+        assert_eq!(
+            eval_factor(
+                &Factor::Power(number(2), number(-1)),
+                &Evaluation::new(&variables, options)
+            ),
+            Ok(Cow::Owned(Value::Int(BigInt::from(4))))
+        );
+        // Results are never negative
+        assert_eq!(
+            eval_expr(
+                &Expr::Negative(Box::new(Expr::Term(Term::Factor(*number(1))))),
+                &Evaluation::new(&variables, options)
+            ),
+            Ok(Cow::Owned(Value::Int(BigInt::from(6))))
+        );
+    }
+
+    #[test]
+    fn test_evaluation_interrupted() {
+        // Sets the flag as soon as a variable is read, like a user pressing Ctrl-C
+        struct InterruptingStore<'a>(&'a AtomicBool);
+        impl VariableStore for InterruptingStore<'_> {
+            fn get(&self, _var: &str) -> Option<Cow<'_, Value>> {
+                self.0.store(true, Ordering::Relaxed);
+                Some(Cow::Owned(Value::Int(BigInt::from(1))))
+            }
+            fn set(&mut self, _var: &str, _val: Value) -> Result<(), CalcError> {
+                Ok(())
+            }
+        }
+
+        let flag = AtomicBool::new(false);
+        assert_eq!(
+            eval_assignment_with(
+                &Assign::ExprBitwise(ExprBitwise::Expr(Expr::Sum(
+                    Box::new(Expr::Term(Term::Factor(Factor::Variable(String::from(
+                        "asd"
+                    ))))),
+                    Term::Factor(Factor::Number(BigInt::from(1).into())),
+                ))),
+                &mut InterruptingStore(&flag),
+                EvalOptions {
+                    interrupt: Some(&flag),
+                    ..Default::default()
+                },
+            ),
+            Err(CalcError::EvaluationInterrupted)
+        );
+    }
+
+    #[test]
+    fn test_evaluation_unlimited() {
+        let limits = Limits {
+            max_bits: None,
+            max_shift: None,
+            max_steps: None,
+            timeout: None,
+        };
+        let vars = HashMap::from([(String::from("asd"), Value::Int(BigInt::from(1) << 100_000))]);
+
+        assert_eq!(
+            eval_term(
+                &Term::Mult(
+                    Box::new(Term::Factor(Factor::Variable(String::from("asd")))),
+                    Factor::Variable(String::from("asd")),
+                ),
+                &Evaluation::new(
+                    &vars,
+                    EvalOptions {
+                        limits,
+                        ..Default::default()
+                    }
+                )
+            ),
+            Ok(Cow::Owned(Value::Int(BigInt::from(1) << 200_000)))
+        );
+    }
 }
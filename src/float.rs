@@ -0,0 +1,278 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+/// An IEEE-754 binary interchange format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    pub name: &'static str,
+    pub exponent_bits: u32,
+    /// Stored bits of the significand, without the implicit leading one
+    pub mantissa_bits: u32,
+}
+
+pub const F16: Format = Format {
+    name: "f16",
+    exponent_bits: 5,
+    mantissa_bits: 10,
+};
+pub const BF16: Format = Format {
+    name: "bf16",
+    exponent_bits: 8,
+    mantissa_bits: 7,
+};
+pub const F32: Format = Format {
+    name: "f32",
+    exponent_bits: 8,
+    mantissa_bits: 23,
+};
+pub const F64: Format = Format {
+    name: "f64",
+    exponent_bits: 11,
+    mantissa_bits: 52,
+};
+
+/// The format with the given name, e.g. `f32`
+pub fn format(name: &str) -> Option<Format> {
+    [F16, BF16, F32, F64]
+        .iter()
+        .copied()
+        .find(|format| format.name == name)
+}
+
+impl Format {
+    pub fn width(&self) -> u32 {
+        1 + self.exponent_bits + self.mantissa_bits
+    }
+
+    pub fn bias(&self) -> i64 {
+        (1 << (self.exponent_bits - 1)) - 1
+    }
+
+    /// The biased exponent of infinity and NaN
+    fn max_exponent(&self) -> u64 {
+        (1 << self.exponent_bits) - 1
+    }
+
+    /// Whether the value fits in the width of the format
+    pub fn contains(&self, bits: &BigInt) -> bool {
+        !bits.is_negative() && bits.bits() <= u64::from(self.width())
+    }
+}
+
+/// Sign, biased exponent and mantissa of a bit pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fields {
+    pub negative: bool,
+    pub exponent: u64,
+    pub mantissa: BigInt,
+}
+
+impl Fields {
+    /// Splits a bit pattern which fits in the width of the format
+    pub fn new(bits: &BigInt, format: Format) -> Fields {
+        let mantissa_mask = (BigInt::one() << format.mantissa_bits) - 1;
+        let exponent = (bits >> format.mantissa_bits).to_u64().unwrap_or(0);
+        Fields {
+            negative: exponent >> format.exponent_bits == 1,
+            exponent: exponent & format.max_exponent(),
+            mantissa: bits & mantissa_mask,
+        }
+    }
+
+    /// The exact value, `None` for infinity and NaN
+    pub fn value(&self, format: Format) -> Option<BigRational> {
+        if self.exponent == format.max_exponent() {
+            return None;
+        }
+        // Subnormal numbers have the exponent of the smallest normal one without the leading one
+        let (significand, exponent) = match self.exponent {
+            0 => (self.mantissa.clone(), 1 - format.bias()),
+            exponent => (
+                (BigInt::one() << format.mantissa_bits) + &self.mantissa,
+                exponent as i64 - format.bias(),
+            ),
+        };
+        let value = shifted(
+            BigRational::from_integer(significand),
+            exponent - i64::from(format.mantissa_bits),
+        );
+        Some(if self.negative { -value } else { value })
+    }
+}
+
+/// `r * 2**shift`
+fn shifted(r: BigRational, shift: i64) -> BigRational {
+    let (numer, denom) = r.into();
+    match shift {
+        0.. => BigRational::new(numer << shift, denom),
+        _ => BigRational::new(numer, denom << -shift),
+    }
+}
+
+/// The bit pattern of the nearest representable number, ties to even.
+/// Values too large for the format become infinity.
+pub fn encode(value: &BigRational, format: Format) -> BigInt {
+    let sign = BigInt::from(value.is_negative() as u8) << (format.width() - 1);
+    let value = value.abs();
+    if value.is_zero() {
+        return sign;
+    }
+
+    // Floor of the binary logarithm, but at least the exponent of the smallest normal number
+    let mut exponent = value.numer().bits() as i64 - value.denom().bits() as i64;
+    if value < shifted(BigRational::one(), exponent) {
+        exponent -= 1;
+    }
+    let mut exponent = exponent.max(1 - format.bias());
+
+    let scaled = shifted(value, i64::from(format.mantissa_bits) - exponent);
+    let (mut significand, rem) = scaled.numer().div_rem(scaled.denom());
+    match (rem << 1u8).cmp(scaled.denom()) {
+        std::cmp::Ordering::Greater => significand += 1,
+        std::cmp::Ordering::Equal if significand.is_odd() => significand += 1,
+        _ => {}
+    }
+    if significand.bits() > u64::from(format.mantissa_bits) + 1 {
+        significand >>= 1;
+        exponent += 1;
+    }
+
+    let implicit = BigInt::one() << format.mantissa_bits;
+    let (biased, mantissa) = match significand >= implicit {
+        _ if exponent > format.bias() => (format.max_exponent(), BigInt::zero()),
+        true => ((exponent + format.bias()) as u64, significand - implicit),
+        false => (0, significand),
+    };
+    sign | (BigInt::from(biased) << format.mantissa_bits) | mantissa
+}
+
+/// The shortest decimal which is encoded to the same bits, e.g. `3.1415927` for `0x40490FDB`
+/// in `f32`. Very large and very small values are written with an exponent, like `1e-40`.
+pub fn format_float(bits: &BigInt, format: Format) -> String {
+    let fields = Fields::new(bits, format);
+    let sign = if fields.negative { "-" } else { "" };
+    let Some(value) = fields.value(format) else {
+        return match fields.mantissa.is_zero() {
+            true => format!("{}inf", sign),
+            false => String::from("NaN"),
+        };
+    };
+    if value.is_zero() {
+        return format!("{}0", sign);
+    }
+
+    // Every value of the formats is exact in an `f64`
+    let double = f64::from_bits(encode(&value, F64).to_u64().unwrap_or(0));
+    let digits = (0..17)
+        .map(|precision| format!("{:.*e}", precision, double.abs()))
+        .find(|digits| encode(&parse_scientific(digits), format) == encode(&value.abs(), format))
+        .unwrap_or_else(|| format!("{:e}", double.abs()));
+
+    let (mantissa, exponent) = digits.split_once('e').unwrap_or((&digits, "0"));
+    let exponent: i64 = exponent.parse().unwrap_or(0);
+    let mantissa = mantissa.replace('.', "");
+    if !(-5..=15).contains(&exponent) {
+        let (first, rest) = mantissa.split_at(1);
+        let point = if rest.is_empty() { "" } else { "." };
+        return format!("{}{}{}{}e{}", sign, first, point, rest, exponent);
+    }
+
+    // Position of the decimal point in the digits
+    let point = exponent + 1;
+    let plain = match point {
+        ..=0 => format!("0.{}{}", "0".repeat(-point as usize), mantissa),
+        _ if point as usize >= mantissa.len() => {
+            format!(
+                "{}{}",
+                mantissa,
+                "0".repeat(point as usize - mantissa.len())
+            )
+        }
+        _ => format!(
+            "{}.{}",
+            &mantissa[..point as usize],
+            &mantissa[point as usize..]
+        ),
+    };
+    format!("{}{}", sign, plain)
+}
+
+/// The exact value of a number like `3.14e-5`
+fn parse_scientific(s: &str) -> BigRational {
+    let (mantissa, exponent) = s.split_once('e').unwrap_or((s, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let fraction_len = mantissa.split_once('.').map_or(0, |(_, f)| f.len() as i32);
+    let digits: BigInt = mantissa.replace('.', "").parse().unwrap_or_default();
+    let ten = BigRational::from_integer(BigInt::from(10));
+    BigRational::from_integer(digits) * num_traits::Pow::pow(ten, exponent - fraction_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(numer: i64, denom: i64) -> BigRational {
+        BigRational::new(BigInt::from(numer), BigInt::from(denom))
+    }
+
+    #[test]
+    fn test_encode() {
+        let pi = ratio(31415927, 10000000);
+        assert_eq!(encode(&pi, F32), BigInt::from(0x40490FDB));
+        assert_eq!(encode(&pi, F16), BigInt::from(0x4248));
+        assert_eq!(encode(&pi, BF16), BigInt::from(0x4049));
+        assert_eq!(
+            encode(&ratio(-1, 1), F64),
+            BigInt::from(0xBFF0000000000000u64)
+        );
+        assert_eq!(encode(&ratio(0, 1), F32), BigInt::zero());
+        assert_eq!(encode(&ratio(65520, 1), F16), BigInt::from(0x7C00));
+        assert_eq!(encode(&ratio(65519, 1), F16), BigInt::from(0x7BFF));
+        // The smallest subnormal, and half of it which is a tie rounded to zero
+        assert_eq!(encode(&shifted(ratio(1, 1), -24), F16), BigInt::one());
+        assert_eq!(encode(&shifted(ratio(1, 1), -25), F16), BigInt::zero());
+        assert_eq!(encode(&ratio(1, 10), F64), BigInt::from(0.1f64.to_bits()));
+        assert_eq!(
+            encode(&ratio(1, 3), F32),
+            BigInt::from((1.0f32 / 3.0).to_bits())
+        );
+    }
+
+    #[test]
+    fn test_fields() {
+        let fields = Fields::new(&BigInt::from(0xC0490FDBu32), F32);
+        assert_eq!(
+            fields,
+            Fields {
+                negative: true,
+                exponent: 128,
+                mantissa: BigInt::from(0x490FDB),
+            }
+        );
+        assert_eq!(fields.value(F32), Some(-ratio(13176795, 4194304)));
+        assert_eq!(Fields::new(&BigInt::from(0x7C00), F16).value(F16), None);
+        assert_eq!(
+            Fields::new(&BigInt::one(), F16).value(F16),
+            Some(shifted(ratio(1, 1), -24))
+        );
+    }
+
+    #[test]
+    fn test_format_float() {
+        let format = |bits: u64, format: Format| format_float(&BigInt::from(bits), format);
+        assert_eq!(format(0x40490FDB, F32), "3.1415927");
+        assert_eq!(format(0x400921FB54442D18, F64), "3.141592653589793");
+        assert_eq!(format(0x3C00, F16), "1");
+        assert_eq!(format(0x3555, F16), "0.3333");
+        assert_eq!(format(0x4049, BF16), "3.14");
+        assert_eq!(format(0x7BFF, F16), "65500");
+        assert_eq!(format(0x0001, F16), "6e-8");
+        assert_eq!(format(0x7F7FFFFF, F32), "3.4028235e38");
+        assert_eq!(format(0x3DCCCCCD, F32), "0.1");
+        assert_eq!(format(0x8000, F16), "-0");
+        assert_eq!(format(0xFC00, F16), "-inf");
+        assert_eq!(format(0x7FC00000, F32), "NaN");
+    }
+}
@@ -0,0 +1,341 @@
+use num_bigint::BigInt;
+use num_complex::Complex;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use crate::{
+    bytes, complex, datetime, ip,
+    matrix::Matrix,
+    special_function::{DisplayMode, RuntimeVariables},
+    text,
+    units::{self, Quantity},
+    value::Value,
+};
+
+/// Formats the value in the given display mode
+pub fn format_value(val: &BigInt, display_mode: &DisplayMode) -> String {
+    match display_mode {
+        DisplayMode::Binary => format!("0b{:b}", val),
+        DisplayMode::Decimal => format!("{}", val),
+        DisplayMode::Hex => format!("0x{:X}", val),
+        // Values which are not addresses are shown in decimal
+        DisplayMode::Ip => ip::format_address(val).unwrap_or_else(|| val.to_string()),
+        DisplayMode::Ascii => text::format_text(val, false).unwrap_or_else(|| val.to_string()),
+        DisplayMode::Utf8 => text::format_text(val, true).unwrap_or_else(|| val.to_string()),
+        // Negative values have no bytes without a width, they are shown in hex
+        DisplayMode::Bytes(_) if val.is_negative() => format_value(val, &DisplayMode::Hex),
+        DisplayMode::Bytes(syntax) => match val.is_zero() {
+            true => bytes::format_bytes(&[0], *syntax),
+            false => bytes::format_bytes(&val.to_bytes_be().1, *syntax),
+        },
+    }
+}
+
+/// Formats an integer, a complex number or a matrix in the given display mode.
+/// Complex numbers are written as `3+4i`, or as `5 ∠ 53.1301°` in polar form.
+pub fn format_number(val: &Value, display_mode: &DisplayMode, polar: bool) -> String {
+    match val {
+        Value::Matrix(m) => match (display_mode, byte_list(m)) {
+            (DisplayMode::Bytes(syntax), Some(bytes)) => bytes::format_bytes(&bytes, *syntax),
+            // Other matrices would mix the arrays of their elements
+            (DisplayMode::Bytes(_), None) => format_matrix(m, &DisplayMode::Hex),
+            _ => format_matrix(m, display_mode),
+        },
+        Value::Function(name) => name.clone(),
+        Value::Quantity(q) => format_quantity(q, display_mode),
+        Value::DateTime(nanos) => datetime::format_datetime(nanos),
+        Value::Network(network) => network.to_string(),
+        Value::Int(n) => format_value(n, display_mode),
+        // The parts of complex numbers are not shown as bytes
+        Value::Complex(_) if matches!(display_mode, DisplayMode::Bytes(_)) => {
+            format_number(val, &DisplayMode::Hex, polar)
+        }
+        Value::Complex(z) if polar => format_polar(z),
+        Value::Complex(z) if z.re.is_zero() => format!("{}i", format_value(&z.im, display_mode)),
+        Value::Complex(z) => {
+            let sign = if z.im.is_negative() { "" } else { "+" };
+            format!(
+                "{}{}{}i",
+                format_value(&z.re, display_mode),
+                sign,
+                format_value(&z.im, display_mode)
+            )
+        }
+    }
+}
+
+/// The elements of a list of bytes, `None` for other matrices
+fn byte_list(m: &Matrix) -> Option<Vec<u8>> {
+    match m.is_vector() && !m.elements().is_empty() {
+        true => m.elements().iter().map(|n| n.to_u8()).collect(),
+        false => None,
+    }
+}
+
+/// The amount in the unit of the quantity followed by the unit, e.g. `62.5 ns`
+pub fn format_quantity(q: &Quantity, display_mode: &DisplayMode) -> String {
+    // Amounts are not addresses, text or bytes
+    let display_mode = match display_mode {
+        DisplayMode::Binary | DisplayMode::Decimal | DisplayMode::Hex => display_mode,
+        _ => &DisplayMode::Decimal,
+    };
+    if q.unit.name == units::DURATION {
+        return format_duration(&q.value, display_mode);
+    }
+
+    let amount = format_fraction(&q.amount(), display_mode);
+    match q.unit.name.as_str() {
+        "" => amount,
+        unit => format!("{} {}", amount, unit),
+    }
+}
+
+/// Days, hours, minutes and seconds without the zero ones, e.g. `3d 4h 0.5s`
+fn format_duration(seconds: &BigRational, display_mode: &DisplayMode) -> String {
+    let sign = if seconds.is_negative() { "-" } else { "" };
+    let seconds = seconds.abs();
+    let (days, rest) = seconds.trunc().to_integer().div_rem(&BigInt::from(86400));
+    let (hours, rest) = rest.div_rem(&BigInt::from(3600));
+    let (minutes, rest) = rest.div_rem(&BigInt::from(60));
+    let rest = BigRational::from_integer(rest) + seconds.fract();
+
+    let mut parts = vec![];
+    for (amount, unit) in [(days, "d"), (hours, "h"), (minutes, "min")] {
+        if !amount.is_zero() {
+            parts.push(format!("{}{}", format_value(&amount, display_mode), unit));
+        }
+    }
+    if !rest.is_zero() || parts.is_empty() {
+        parts.push(format!("{}s", format_fraction(&rest, display_mode)));
+    }
+    format!("{}{}", sign, parts.join(" "))
+}
+
+/// Integers in the display mode, other fractions as decimals cut off after 10 significant digits
+pub fn format_fraction(r: &BigRational, display_mode: &DisplayMode) -> String {
+    if r.is_integer() {
+        return format_value(&r.to_integer(), display_mode);
+    }
+
+    let integer = r.abs().trunc().to_integer();
+    let mut fraction = r.abs().fract();
+    let mut digits = String::new();
+    let mut significant = 0;
+    while !fraction.is_zero() && significant < 10 {
+        fraction *= BigInt::from(10);
+        let digit = fraction.trunc().to_integer();
+        fraction = fraction.fract();
+        // Leading zeros of a number below 1 are not significant
+        if !(integer.is_zero() && significant == 0 && digit.is_zero()) {
+            significant += 1;
+        }
+        digits.push_str(&digit.to_string());
+    }
+
+    let sign = if r.is_negative() { "-" } else { "" };
+    match digits.trim_end_matches('0') {
+        "" => format!("{}{}", sign, integer),
+        digits => format!("{}{}.{}", sign, integer, digits),
+    }
+}
+
+/// One line per row with the columns aligned to the right, e.g.
+/// ```text
+/// [ 1  -2 ]
+/// [ 3  40 ]
+/// ```
+pub fn format_matrix(m: &Matrix, display_mode: &DisplayMode) -> String {
+    if m.elements().is_empty() {
+        return String::from("[]");
+    }
+
+    let elements: Vec<String> = m
+        .elements()
+        .iter()
+        .map(|n| format_value(n, display_mode))
+        .collect();
+    let widths: Vec<usize> = (0..m.cols())
+        .map(|j| {
+            (0..m.rows())
+                .map(|i| elements[i * m.cols() + j].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let rows: Vec<String> = elements
+        .chunks(m.cols())
+        .map(|row| {
+            let row: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(element, width)| format!("{:>1$}", element, width))
+                .collect();
+            format!("[ {} ]", row.join("  "))
+        })
+        .collect();
+    rows.join("\n")
+}
+
+/// Absolute value and angle in degrees, both rounded to 4 decimals
+fn format_polar(z: &Complex<BigInt>) -> String {
+    let (magnitude, scale, angle) = complex::polar(z);
+    let magnitude = if scale == 0 {
+        format_decimal(magnitude)
+    } else {
+        // Too large for a float, written as mantissa and exponent
+        let log = magnitude.log10() + scale as f64 * 2f64.log10();
+        let exponent = log.floor();
+        format!(
+            "{}e{}",
+            format_decimal(10f64.powf(log - exponent)),
+            exponent
+        )
+    };
+    format!("{} ∠ {}°", magnitude, format_decimal(angle))
+}
+
+/// Rounded to 4 decimals without trailing zeros
+fn format_decimal(x: f64) -> String {
+    let s = format!("{:.4}", x);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    match s {
+        "-0" => String::from("0"),
+        s => s.to_string(),
+    }
+}
+
+/// Returns the name of the variable and its value formatted in the current display mode
+pub fn format_variable(runtime_vars: &RuntimeVariables, var: &str) -> (String, String) {
+    let Some(val) = runtime_vars.vars.get(var) else {
+        // This should never happen
+        panic!(
+            "Tried to format non-existing variable {}. \nVariables:{:?}",
+            var, runtime_vars.vars
+        );
+    };
+
+    (
+        var.to_string(),
+        format_number(val, &runtime_vars.display_mode, runtime_vars.polar),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytes::ByteSyntax;
+
+    #[test]
+    fn test_format_value() {
+        let val = BigInt::from(12);
+        assert_eq!(format_value(&val, &DisplayMode::Decimal), "12");
+        assert_eq!(format_value(&val, &DisplayMode::Hex), "0xC");
+        assert_eq!(format_value(&val, &DisplayMode::Binary), "0b1100");
+        assert_eq!(format_value(&val, &DisplayMode::Ip), "0.0.0.12");
+        let val = BigInt::from(1) << 127;
+        assert_eq!(format_value(&val, &DisplayMode::Ip), "8000::");
+        assert_eq!(
+            format_value(&-val, &DisplayMode::Ip),
+            "-170141183460469231731687303715884105728"
+        );
+        let val = BigInt::from(0x48C3AF);
+        assert_eq!(format_value(&val, &DisplayMode::Ascii), r#""H\xC3\xAF""#);
+        assert_eq!(format_value(&val, &DisplayMode::Utf8), "\"Hï\"");
+        let bytes = DisplayMode::Bytes(ByteSyntax::C);
+        assert_eq!(format_value(&val, &bytes), "{ 0x48, 0xC3, 0xAF }");
+        assert_eq!(format_value(&BigInt::zero(), &bytes), "{ 0x00 }");
+        assert_eq!(
+            format_value(&-&val, &bytes),
+            format_value(&-val, &DisplayMode::Hex)
+        );
+    }
+
+    #[test]
+    fn test_format_number() {
+        let z = |re: i64, im: i64| Value::complex(BigInt::from(re), BigInt::from(im));
+
+        assert_eq!(
+            format_number(&z(3, -4), &DisplayMode::Decimal, false),
+            "3-4i"
+        );
+        assert_eq!(format_number(&z(0, 10), &DisplayMode::Hex, false), "0xAi");
+        assert_eq!(
+            format_number(&z(10, 1), &DisplayMode::Hex, false),
+            "0xA+0x1i"
+        );
+        assert_eq!(format_number(&z(7, 0), &DisplayMode::Decimal, true), "7");
+        assert_eq!(
+            format_number(&z(3, 4), &DisplayMode::Decimal, true),
+            "5 ∠ 53.1301°"
+        );
+        assert_eq!(
+            format_number(&z(-1, -1), &DisplayMode::Decimal, true),
+            "1.4142 ∠ -135°"
+        );
+        let huge = Value::complex(BigInt::from(1) << 2000, BigInt::from(1) << 2000);
+        assert_eq!(
+            format_number(&huge, &DisplayMode::Decimal, true),
+            "1.6237e602 ∠ 45°"
+        );
+        assert_eq!(
+            format_number(&z(0, 1), &DisplayMode::Bytes(ByteSyntax::C), false),
+            "0x1i"
+        );
+    }
+
+    #[test]
+    fn test_format_fraction() {
+        let r = |num: i64, den: i64| BigRational::new(BigInt::from(num), BigInt::from(den));
+
+        assert_eq!(format_fraction(&r(-5, 1), &DisplayMode::Hex), "0x-5");
+        assert_eq!(format_fraction(&r(-125, 2), &DisplayMode::Decimal), "-62.5");
+        assert_eq!(
+            format_fraction(&r(2, 3), &DisplayMode::Decimal),
+            "0.6666666666"
+        );
+        assert_eq!(
+            format_fraction(&r(1, 3_000_000), &DisplayMode::Hex),
+            "0.0000003333333333"
+        );
+        assert_eq!(
+            format_fraction(
+                &r(1_000_000_000_001, 1_000_000_000_000),
+                &DisplayMode::Decimal
+            ),
+            "1"
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        let duration = |num: i64, den: i64| {
+            format_duration(
+                &BigRational::new(num.into(), den.into()),
+                &DisplayMode::Decimal,
+            )
+        };
+        assert_eq!(duration(3 * 86400 + 4 * 3600, 1), "3d 4h");
+        assert_eq!(duration(-3661, 1), "-1h 1min 1s");
+        assert_eq!(duration(121, 2), "1min 0.5s");
+        assert_eq!(duration(0, 1), "0s");
+    }
+
+    #[test]
+    fn test_format_matrix() {
+        let m = Matrix::from_rows(vec![
+            vec![BigInt::from(1), BigInt::from(-2)],
+            vec![BigInt::from(3), BigInt::from(40)],
+        ])
+        .unwrap();
+        assert_eq!(
+            format_matrix(&m, &DisplayMode::Decimal),
+            "[ 1  -2 ]\n[ 3  40 ]"
+        );
+        assert_eq!(
+            format_matrix(&m.transpose(), &DisplayMode::Hex),
+            "[  0x1   0x3 ]\n[ 0x-2  0x28 ]"
+        );
+    }
+}
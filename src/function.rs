@@ -0,0 +1,1104 @@
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use phf::phf_map;
+
+use crate::{
+    bitwise, bytes,
+    checksum::{self, Crc},
+    combinatorics, complex, datetime,
+    error::CalcError,
+    evaluator::Evaluation,
+    float::{self, Fields, Format},
+    ip::Network,
+    matrix::Matrix,
+    number_theory,
+    parser::ExprBitwise,
+    random, roots, statistics,
+    units::{self, Quantity},
+    value::Value,
+};
+
+/// Number of the arguments a function must be called with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+    /// Both bounds are included
+    Between(usize, usize),
+}
+
+impl Arity {
+    pub fn accepts(self, count: usize) -> bool {
+        match self {
+            Arity::Exact(arity) => count == arity,
+            Arity::AtLeast(arity) => count >= arity,
+            Arity::Between(min, max) => (min..=max).contains(&count),
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arity::Exact(arity) => write!(f, "{}", arity),
+            Arity::AtLeast(arity) => write!(f, "at least {}", arity),
+            Arity::Between(min, max) => write!(f, "{} to {}", min, max),
+        }
+    }
+}
+
+/// A built-in function which can be called in expressions, e.g. `mod(a, 3)`
+pub struct Function {
+    pub arity: Arity,
+    pub call: Call,
+}
+
+/// Implementation of a built-in function.
+/// Gets the evaluation to check its limits during long computations.
+pub enum Call {
+    /// Only defined for integers, complex arguments are rejected
+    Integer(fn(&[&BigInt], &Evaluation) -> Result<BigInt, CalcError>),
+    /// Takes any values
+    Value(fn(&[&Value], &Evaluation) -> Result<Value, CalcError>),
+}
+
+impl Function {
+    /// Calls the function with arguments already checked against its arity
+    pub(crate) fn apply(
+        &self,
+        name: &str,
+        args: &[&Value],
+        ctx: &Evaluation,
+    ) -> Result<Value, CalcError> {
+        match self.call {
+            Call::Integer(call) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.as_int(name))
+                    .collect::<Result<Vec<_>, _>>()?;
+                call(&args, ctx).map(Value::Int)
+            }
+            Call::Value(call) => call(args, ctx),
+        }
+    }
+}
+
+/// A function defined in the session, e.g. `f(x, y) = x**2 + y`
+#[derive(Debug, PartialEq)]
+pub struct UserFunction {
+    pub params: Vec<String>,
+    pub body: ExprBitwise,
+}
+
+impl UserFunction {
+    pub fn arity(&self) -> Arity {
+        Arity::Exact(self.params.len())
+    }
+}
+
+pub static FUNCTIONS: phf::Map<&'static str, Function> = phf_map! {
+    "rem" => Function { arity: Arity::Exact(2), call: Call::Integer(rem_function) },
+    "mod" => Function { arity: Arity::Exact(2), call: Call::Integer(mod_function) },
+    "emod" => Function { arity: Arity::Exact(2), call: Call::Integer(emod_function) },
+    "gcd" => Function { arity: Arity::AtLeast(2), call: Call::Integer(gcd_function) },
+    "lcm" => Function { arity: Arity::AtLeast(2), call: Call::Integer(lcm_function) },
+    "invmod" => Function { arity: Arity::Exact(2), call: Call::Integer(invmod_function) },
+    "crt" => Function { arity: Arity::AtLeast(2), call: Call::Integer(crt_function) },
+    "isprime" => Function { arity: Arity::Exact(1), call: Call::Integer(isprime_function) },
+    "nextprime" => Function { arity: Arity::Exact(1), call: Call::Integer(nextprime_function) },
+    "prevprime" => Function { arity: Arity::Exact(1), call: Call::Integer(prevprime_function) },
+    "totient" => Function { arity: Arity::Exact(1), call: Call::Integer(totient_function) },
+    "factor" => Function { arity: Arity::Exact(1), call: Call::Value(factor_function) },
+    "egcd" => Function { arity: Arity::Exact(2), call: Call::Value(egcd_function) },
+    "jacobi" => Function { arity: Arity::Exact(2), call: Call::Integer(jacobi_function) },
+    "legendre" => Function { arity: Arity::Exact(2), call: Call::Integer(legendre_function) },
+    "binom" => Function { arity: Arity::Exact(2), call: Call::Integer(binom_function) },
+    "perm" => Function { arity: Arity::Exact(2), call: Call::Integer(perm_function) },
+    "dfact" => Function { arity: Arity::Exact(1), call: Call::Integer(dfact_function) },
+    "fib" => Function { arity: Arity::Exact(1), call: Call::Integer(fib_function) },
+    "lucas" => Function { arity: Arity::Exact(1), call: Call::Integer(lucas_function) },
+    "catalan" => Function { arity: Arity::Exact(1), call: Call::Integer(catalan_function) },
+    "bell" => Function { arity: Arity::Exact(1), call: Call::Integer(bell_function) },
+    "popcount" => Function { arity: Arity::Exact(1), call: Call::Integer(popcount_function) },
+    "clz" => Function { arity: Arity::Exact(2), call: Call::Integer(clz_function) },
+    "ctz" => Function { arity: Arity::Exact(2), call: Call::Integer(ctz_function) },
+    "bitlen" => Function { arity: Arity::Exact(1), call: Call::Integer(bitlen_function) },
+    "bit" => Function { arity: Arity::Exact(2), call: Call::Integer(bit_function) },
+    "setbit" => Function { arity: Arity::Exact(2), call: Call::Integer(setbit_function) },
+    "clearbit" => Function { arity: Arity::Exact(2), call: Call::Integer(clearbit_function) },
+    "togglebit" => Function { arity: Arity::Exact(2), call: Call::Integer(togglebit_function) },
+    "bits" => Function { arity: Arity::Exact(3), call: Call::Integer(bits_function) },
+    "insert" => Function { arity: Arity::Exact(4), call: Call::Integer(insert_function) },
+    "bswap16" => Function { arity: Arity::Exact(1), call: Call::Integer(bswap16_function) },
+    "bswap32" => Function { arity: Arity::Exact(1), call: Call::Integer(bswap32_function) },
+    "bswap64" => Function { arity: Arity::Exact(1), call: Call::Integer(bswap64_function) },
+    "bswap" => Function { arity: Arity::Exact(2), call: Call::Integer(bswap_function) },
+    "tobytes" => Function { arity: Arity::Between(1, 2), call: Call::Value(tobytes_function) },
+    "tobytes_le" => Function { arity: Arity::Between(1, 2), call: Call::Value(tobytes_le_function) },
+    "frombytes" => Function { arity: Arity::Exact(1), call: Call::Value(frombytes_function) },
+    "frombytes_le" => Function { arity: Arity::Exact(1), call: Call::Value(frombytes_le_function) },
+    "bitrev" => Function { arity: Arity::Exact(2), call: Call::Integer(bitrev_function) },
+    "nextpow2" => Function { arity: Arity::Exact(1), call: Call::Integer(nextpow2_function) },
+    "parity" => Function { arity: Arity::Exact(1), call: Call::Integer(parity_function) },
+    "rand" => Function { arity: Arity::Exact(2), call: Call::Integer(rand_function) },
+    "randbits" => Function { arity: Arity::Exact(1), call: Call::Integer(randbits_function) },
+    "randprime" => Function { arity: Arity::Exact(1), call: Call::Integer(randprime_function) },
+    "isqrt" => Function { arity: Arity::Exact(1), call: Call::Integer(isqrt_function) },
+    "iroot" => Function { arity: Arity::Exact(2), call: Call::Integer(iroot_function) },
+    "ilog" => Function { arity: Arity::Exact(2), call: Call::Integer(ilog_function) },
+    "ilog2" => Function { arity: Arity::Exact(1), call: Call::Integer(ilog2_function) },
+    "ilog10" => Function { arity: Arity::Exact(1), call: Call::Integer(ilog10_function) },
+    "is_square" => Function { arity: Arity::Exact(1), call: Call::Integer(is_square_function) },
+    "is_power" => Function { arity: Arity::Exact(1), call: Call::Integer(is_power_function) },
+    "re" => Function { arity: Arity::Exact(1), call: Call::Value(re_function) },
+    "im" => Function { arity: Arity::Exact(1), call: Call::Value(im_function) },
+    "conj" => Function { arity: Arity::Exact(1), call: Call::Value(conj_function) },
+    "abs" => Function { arity: Arity::Exact(1), call: Call::Value(abs_function) },
+    "arg" => Function { arity: Arity::Exact(1), call: Call::Value(arg_function) },
+    "det" => Function { arity: Arity::Exact(1), call: Call::Value(det_function) },
+    "transpose" => Function { arity: Arity::Exact(1), call: Call::Value(transpose_function) },
+    "inverse" => Function { arity: Arity::Exact(1), call: Call::Value(inverse_function) },
+    "sum" => Function { arity: Arity::AtLeast(1), call: Call::Value(sum_function) },
+    "product" => Function { arity: Arity::AtLeast(1), call: Call::Value(product_function) },
+    "min" => Function { arity: Arity::AtLeast(1), call: Call::Value(min_function) },
+    "max" => Function { arity: Arity::AtLeast(1), call: Call::Value(max_function) },
+    "mean" => Function { arity: Arity::AtLeast(1), call: Call::Value(mean_function) },
+    "median" => Function { arity: Arity::AtLeast(1), call: Call::Value(median_function) },
+    "mode" => Function { arity: Arity::AtLeast(1), call: Call::Value(mode_function) },
+    "stddev" => Function { arity: Arity::AtLeast(1), call: Call::Value(stddev_function) },
+    "len" => Function { arity: Arity::AtLeast(1), call: Call::Value(len_function) },
+    "sort" => Function { arity: Arity::AtLeast(1), call: Call::Value(sort_function) },
+    "range" => Function { arity: Arity::Between(1, 3), call: Call::Value(range_function) },
+    "map" => Function { arity: Arity::Exact(2), call: Call::Value(map_function) },
+    "filter" => Function { arity: Arity::Exact(2), call: Call::Value(filter_function) },
+    "unix" => Function { arity: Arity::Exact(1), call: Call::Value(unix_function) },
+    "unixms" => Function { arity: Arity::Exact(1), call: Call::Value(unixms_function) },
+    "unixns" => Function { arity: Arity::Exact(1), call: Call::Value(unixns_function) },
+    "fromunix" => Function { arity: Arity::Exact(1), call: Call::Value(fromunix_function) },
+    "fromunixms" => Function { arity: Arity::Exact(1), call: Call::Value(fromunixms_function) },
+    "fromunixns" => Function { arity: Arity::Exact(1), call: Call::Value(fromunixns_function) },
+    "cidr" => Function { arity: Arity::Exact(2), call: Call::Value(cidr_function) },
+    "network" => Function { arity: Arity::Exact(1), call: Call::Value(network_function) },
+    "broadcast" => Function { arity: Arity::Exact(1), call: Call::Value(broadcast_function) },
+    "netmask" => Function { arity: Arity::Exact(1), call: Call::Value(netmask_function) },
+    "hosts" => Function { arity: Arity::Exact(1), call: Call::Value(hosts_function) },
+    "contains" => Function { arity: Arity::Exact(2), call: Call::Value(contains_function) },
+    "f16bits" => Function { arity: Arity::Exact(1), call: Call::Value(f16bits_function) },
+    "bf16bits" => Function { arity: Arity::Exact(1), call: Call::Value(bf16bits_function) },
+    "f32bits" => Function { arity: Arity::Exact(1), call: Call::Value(f32bits_function) },
+    "f64bits" => Function { arity: Arity::Exact(1), call: Call::Value(f64bits_function) },
+    "fromf16bits" => Function { arity: Arity::Exact(1), call: Call::Value(fromf16bits_function) },
+    "frombf16bits" => Function { arity: Arity::Exact(1), call: Call::Value(frombf16bits_function) },
+    "fromf32bits" => Function { arity: Arity::Exact(1), call: Call::Value(fromf32bits_function) },
+    "fromf64bits" => Function { arity: Arity::Exact(1), call: Call::Value(fromf64bits_function) },
+    "crc" => Function { arity: Arity::Exact(6), call: Call::Value(crc_function) },
+    "crc8" => Function { arity: Arity::Between(1, 2), call: Call::Value(crc8_function) },
+    "crc8maxim" => Function { arity: Arity::Between(1, 2), call: Call::Value(crc8maxim_function) },
+    "crc16" => Function { arity: Arity::Between(1, 2), call: Call::Value(crc16_function) },
+    "crc16ccitt" => Function { arity: Arity::Between(1, 2), call: Call::Value(crc16ccitt_function) },
+    "crc16modbus" => Function { arity: Arity::Between(1, 2), call: Call::Value(crc16modbus_function) },
+    "crc16xmodem" => Function { arity: Arity::Between(1, 2), call: Call::Value(crc16xmodem_function) },
+    "crc32" => Function { arity: Arity::Between(1, 2), call: Call::Value(crc32_function) },
+    "crc32c" => Function { arity: Arity::Between(1, 2), call: Call::Value(crc32c_function) },
+    "crc32mpeg2" => Function { arity: Arity::Between(1, 2), call: Call::Value(crc32mpeg2_function) },
+    "adler32" => Function { arity: Arity::Between(1, 2), call: Call::Value(adler32_function) },
+    "fletcher16" => Function { arity: Arity::Between(1, 2), call: Call::Value(fletcher16_function) },
+    "fletcher32" => Function { arity: Arity::Between(1, 2), call: Call::Value(fletcher32_function) },
+    "sha256" => Function { arity: Arity::Between(1, 2), call: Call::Value(sha256_function) },
+    "md5" => Function { arity: Arity::Between(1, 2), call: Call::Value(md5_function) },
+};
+
+fn invalid_argument(function: &str, reason: &str) -> CalcError {
+    CalcError::InvalidArgument(function.to_string(), reason.to_string())
+}
+
+/// Truncated remainder, has the sign of the dividend. Same as the `%` operator.
+fn rem_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let (lhs, rhs) = (args[0], args[1]);
+    if rhs.is_zero() {
+        return Err(CalcError::DivisionByZero(lhs.clone().into()));
+    }
+    Ok(lhs % rhs)
+}
+
+/// Floored modulo, has the sign of the divisor
+fn mod_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let (lhs, rhs) = (args[0], args[1]);
+    if rhs.is_zero() {
+        return Err(CalcError::DivisionByZero(lhs.clone().into()));
+    }
+    Ok(lhs.mod_floor(rhs))
+}
+
+/// Euclidean modulo, never negative
+fn emod_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let (lhs, rhs) = (args[0], args[1]);
+    if rhs.is_zero() {
+        return Err(CalcError::DivisionByZero(lhs.clone().into()));
+    }
+    Ok(lhs.mod_floor(&rhs.abs()))
+}
+
+fn gcd_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    Ok(args.iter().fold(BigInt::zero(), |gcd, arg| gcd.gcd(arg)))
+}
+
+fn lcm_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    Ok(args.iter().fold(BigInt::one(), |lcm, arg| lcm.lcm(arg)))
+}
+
+/// Modular inverse, in the range `0..|n|`
+fn invmod_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    number_theory::mod_inverse(args[0], args[1])
+}
+
+/// Chinese remainder theorem, `crt(a1, n1, a2, n2, ...)` solves x = a_i (mod n_i)
+fn crt_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    if !args.len().is_multiple_of(2) {
+        return Err(invalid_argument(
+            "crt",
+            "expects pairs of remainders and moduli",
+        ));
+    }
+    if args.iter().skip(1).step_by(2).any(|n| !n.is_positive()) {
+        return Err(invalid_argument("crt", "the moduli must be positive"));
+    }
+
+    let congruences: Vec<_> = args.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+    number_theory::crt(&congruences)
+        .ok_or_else(|| invalid_argument("crt", "the congruences have no common solution"))
+}
+
+/// 1 if the number is prime, 0 otherwise. Deterministic below 2^64, probabilistic above.
+fn isprime_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    Ok(BigInt::from(u8::from(number_theory::is_prime(
+        args[0], ctx,
+    )?)))
+}
+
+fn nextprime_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    number_theory::next_prime(args[0], ctx)
+}
+
+fn prevprime_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    number_theory::prev_prime(args[0], ctx)?.ok_or_else(|| {
+        invalid_argument("prevprime", &format!("there is no prime below {}", args[0]))
+    })
+}
+
+/// Euler's totient
+fn totient_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    if !args[0].is_positive() {
+        return Err(invalid_argument("totient", "expects a positive number"));
+    }
+    number_theory::totient(args[0], ctx)
+}
+
+/// `factor(n)` is a matrix with a row `[p, e]` for every prime power `p**e` of `n`, with
+/// a first row `[-1, 1]` for negative numbers and only `[1, 1]` for 1
+fn factor_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    let n = args[0].as_int("factor")?;
+    if n.is_zero() {
+        return Err(invalid_argument("factor", "0 has no prime factorization"));
+    }
+    let mut rows: Vec<Vec<BigInt>> = number_theory::factorize(n, ctx)?
+        .into_iter()
+        .map(|(p, exponent)| vec![p, BigInt::from(exponent)])
+        .collect();
+    if n.is_negative() {
+        rows.insert(0, vec![BigInt::from(-1), BigInt::one()]);
+    }
+    if rows.is_empty() {
+        rows.push(vec![BigInt::one(), BigInt::one()]);
+    }
+    Ok(Value::Matrix(Matrix::from_rows(rows)?))
+}
+
+/// `egcd(a, b)` is the row `[gcd, x, y]` with `a*x + b*y = gcd`
+fn egcd_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    let (gcd, x, y) = number_theory::extended_gcd(args[0].as_int("egcd")?, args[1].as_int("egcd")?);
+    Ok(Value::Matrix(Matrix::vector(vec![gcd, x, y], false)))
+}
+
+fn jacobi_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let (a, n) = (args[0], args[1]);
+    if !n.is_positive() || n.is_even() {
+        return Err(invalid_argument(
+            "jacobi",
+            "the modulus must be positive and odd",
+        ));
+    }
+    Ok(BigInt::from(number_theory::jacobi(a, n)))
+}
+
+fn legendre_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let (a, p) = (args[0], args[1]);
+    if p.is_even() || !number_theory::is_prime(p, ctx)? {
+        return Err(invalid_argument(
+            "legendre",
+            "the modulus must be an odd prime",
+        ));
+    }
+    Ok(BigInt::from(number_theory::jacobi(a, p)))
+}
+
+fn binom_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    combinatorics::binomial(args[0], args[1], ctx)
+}
+
+fn perm_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    combinatorics::permutations(args[0], args[1], ctx)
+}
+
+/// Double factorial, same as `n!!`
+fn dfact_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    combinatorics::double_factorial(args[0], ctx)
+}
+
+fn fib_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    combinatorics::fibonacci(args[0], ctx)
+}
+
+fn lucas_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    combinatorics::lucas(args[0], ctx)
+}
+
+fn catalan_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    combinatorics::catalan(args[0], ctx)
+}
+
+fn bell_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    combinatorics::bell(args[0], ctx)
+}
+
+/// Number of the set bits. Negative values are taken in two's complement within the session width.
+fn popcount_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::popcount(args[0], ctx)
+}
+
+/// `clz(x, width)`, number of the leading zeros of x as a value of the given width
+fn clz_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::leading_zeros(args[0], args[1], ctx)
+}
+
+/// `ctz(x, width)`, number of the trailing zeros, the width for zero
+fn ctz_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::trailing_zeros(args[0], args[1], ctx)
+}
+
+fn bitlen_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    Ok(bitwise::bit_length(args[0]))
+}
+
+/// `bit(x, n)`, 1 if bit n of x is set
+fn bit_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::bit(args[0], args[1])
+}
+
+fn setbit_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::change_bit(args[0], args[1], "setbit", |_| true, ctx)
+}
+
+fn clearbit_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::change_bit(args[0], args[1], "clearbit", |_| false, ctx)
+}
+
+fn togglebit_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::change_bit(args[0], args[1], "togglebit", |bit| !bit, ctx)
+}
+
+/// `bits(x, hi, lo)`, the field of bits hi down to lo
+fn bits_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::extract(args[0], args[1], args[2], ctx)
+}
+
+/// `insert(x, v, hi, lo)`, x with the field of bits hi down to lo replaced by v
+fn insert_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::insert(args[0], args[1], args[2], args[3], ctx)
+}
+
+fn bswap16_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::swap_bytes(args[0], 16, "bswap16", ctx)
+}
+
+fn bswap32_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::swap_bytes(args[0], 32, "bswap32", ctx)
+}
+
+fn bswap64_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::swap_bytes(args[0], 64, "bswap64", ctx)
+}
+
+/// `bswap(x, len)` reverses the order of the `len` bytes of `x`
+fn bswap_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bytes::swap(args[0], args[1], ctx)
+}
+
+/// `bitrev(x, width)`, reverses the order of the lowest width bits
+fn bitrev_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::reverse_bits(args[0], args[1], ctx)
+}
+
+fn nextpow2_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::next_power_of_two(args[0], ctx)
+}
+
+fn parity_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    bitwise::parity(args[0], ctx)
+}
+
+fn rand_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    random::random_range(args[0], args[1], ctx)
+}
+
+fn randbits_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    random::random_bits(args[0], ctx)
+}
+
+fn randprime_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    random::random_prime(args[0], ctx)
+}
+
+fn isqrt_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    roots::isqrt(args[0])
+}
+
+fn iroot_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    roots::iroot(args[0], args[1])
+}
+
+fn ilog_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    roots::ilog(args[0], args[1], ctx)
+}
+
+fn ilog2_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    roots::ilog2(args[0])
+}
+
+fn ilog10_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    roots::ilog(args[0], &BigInt::from(10), ctx)
+}
+
+fn is_square_function(args: &[&BigInt], _ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    Ok(BigInt::from(u8::from(roots::is_square(args[0]))))
+}
+
+/// 0, 1 and -1 are powers of themselves
+fn is_power_function(args: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let x = args[0];
+    let is_power = x.magnitude() <= &One::one() || roots::perfect_power(x, ctx)?.is_some();
+    Ok(BigInt::from(u8::from(is_power)))
+}
+
+fn re_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(Value::Int(args[0].to_complex("re")?.re))
+}
+
+fn im_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(Value::Int(args[0].to_complex("im")?.im))
+}
+
+fn conj_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(args[0].to_complex("conj")?.conj().into())
+}
+
+/// Floor of the absolute value
+fn abs_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(Value::Int(complex::abs(&args[0].to_complex("abs")?)))
+}
+
+/// Angle in whole degrees, rounded
+fn arg_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(Value::Int(BigInt::from(
+        complex::arg(&args[0].to_complex("arg")?).round() as i64,
+    )))
+}
+
+fn det_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(Value::Int(args[0].as_matrix("det")?.det(ctx)?))
+}
+
+fn transpose_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(Value::Matrix(args[0].as_matrix("transpose")?.transpose()))
+}
+
+/// Exact inverse, in modular mode modulo the modulus
+fn inverse_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    let m = args[0].as_matrix("inverse")?;
+    Ok(Value::Matrix(m.inverse(ctx.modulus(), ctx)?))
+}
+
+/// The elements of a single list argument, or the arguments themselves, e.g. `sum(a, b, c)`
+fn list_elements<'v>(function: &str, args: &[&'v Value]) -> Result<Vec<&'v BigInt>, CalcError> {
+    match args {
+        [Value::Matrix(m)] => Ok(m.elements().iter().collect()),
+        _ => args.iter().map(|arg| arg.as_int(function)).collect(),
+    }
+}
+
+/// The list argument, or a row vector of the arguments
+fn list_argument(function: &str, args: &[&Value]) -> Result<Matrix, CalcError> {
+    match args {
+        [Value::Matrix(m)] if m.is_vector() => Ok(m.clone()),
+        [Value::Matrix(m)] => Err(invalid_argument(
+            function,
+            &format!("a {} matrix is not a list", m.shape()),
+        )),
+        _ => Ok(Matrix::vector(
+            list_elements(function, args)?
+                .into_iter()
+                .cloned()
+                .collect(),
+            false,
+        )),
+    }
+}
+
+fn sum_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(Value::Int(statistics::sum(&list_elements("sum", args)?)))
+}
+
+fn product_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    statistics::product(&list_elements("product", args)?, ctx).map(Value::Int)
+}
+
+fn min_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    statistics::min(&list_elements("min", args)?).map(Value::Int)
+}
+
+fn max_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    statistics::max(&list_elements("max", args)?).map(Value::Int)
+}
+
+fn mean_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    statistics::mean(&list_elements("mean", args)?).map(Value::Int)
+}
+
+fn median_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    statistics::median(&list_elements("median", args)?).map(Value::Int)
+}
+
+fn mode_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    statistics::mode(&list_elements("mode", args)?).map(Value::Int)
+}
+
+fn stddev_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    statistics::stddev(&list_elements("stddev", args)?).map(Value::Int)
+}
+
+fn len_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(Value::Int(BigInt::from(list_elements("len", args)?.len())))
+}
+
+/// Ascending, a column stays a column
+fn sort_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    let list = list_argument("sort", args)?;
+    let sorted = statistics::sort(&list.elements().iter().collect::<Vec<_>>());
+    Ok(Value::Matrix(Matrix::vector(sorted, list.is_column())))
+}
+
+/// `range(end)`, `range(start, end)` or `range(start, end, step)`, the end is not included
+fn range_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    let args = args
+        .iter()
+        .map(|arg| arg.as_int("range"))
+        .collect::<Result<Vec<_>, _>>()?;
+    let zero = BigInt::zero();
+    let one = BigInt::one();
+    let (start, end, step) = match args[..] {
+        [end] => (&zero, end, &one),
+        [start, end] => (start, end, &one),
+        _ => (args[0], args[1], args[2]),
+    };
+    let list = statistics::range(start, end, step, ctx)?;
+    Ok(Value::Matrix(Matrix::vector(list, false)))
+}
+
+fn function_argument<'v>(function: &str, arg: &'v Value) -> Result<&'v str, CalcError> {
+    match arg {
+        Value::Function(name) => Ok(name),
+        _ => Err(invalid_argument(function, "expected a function name")),
+    }
+}
+
+/// Applies the function to every element of a list or a matrix
+fn map_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    let name = function_argument("map", args[0])?;
+    let m = args[1].as_matrix("map")?;
+    let res = m.try_map(|element| {
+        ctx.call(name, &[&Value::Int(element.clone())])?
+            .into_int(name)
+    })?;
+    Ok(Value::Matrix(res))
+}
+
+/// The elements of the list for which the function is not zero
+fn filter_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    let name = function_argument("filter", args[0])?;
+    let list = list_argument("filter", &args[1..])?;
+
+    let mut kept = vec![];
+    for element in list.elements() {
+        let keep = ctx.call(name, &[&Value::Int(element.clone())])?;
+        if !keep.is_zero() {
+            kept.push(element.clone());
+        }
+    }
+    Ok(Value::Matrix(Matrix::vector(kept, list.is_column())))
+}
+
+fn date_argument<'v>(function: &str, arg: &'v Value) -> Result<&'v BigInt, CalcError> {
+    match arg {
+        Value::DateTime(nanos) => Ok(nanos),
+        _ => Err(invalid_argument(function, "expected a date")),
+    }
+}
+
+fn unix_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    let date = date_argument("unix", args[0])?;
+    Ok(Value::Int(datetime::to_unix(date, 1_000_000_000)))
+}
+
+fn unixms_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    let date = date_argument("unixms", args[0])?;
+    Ok(Value::Int(datetime::to_unix(date, 1_000_000)))
+}
+
+fn unixns_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    let date = date_argument("unixns", args[0])?;
+    Ok(Value::Int(datetime::to_unix(date, 1)))
+}
+
+fn fromunix_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    datetime::from_unix(args[0].as_int("fromunix")?, 1_000_000_000).map(Value::DateTime)
+}
+
+fn fromunixms_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    datetime::from_unix(args[0].as_int("fromunixms")?, 1_000_000).map(Value::DateTime)
+}
+
+fn fromunixns_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    datetime::from_unix(args[0].as_int("fromunixns")?, 1).map(Value::DateTime)
+}
+
+fn network_argument<'v>(function: &str, arg: &'v Value) -> Result<&'v Network, CalcError> {
+    match arg {
+        Value::Network(network) => Ok(network),
+        _ => Err(invalid_argument(
+            function,
+            "expected a network like 10.0.0.0/8",
+        )),
+    }
+}
+
+/// The network of an address with the prefix length, IPv4 if both fit
+fn cidr_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    let (address, prefix) = (args[0].as_int("cidr")?, args[1].as_int("cidr")?);
+    Network::guess(address.clone(), prefix).map(Value::Network)
+}
+
+fn network_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(Value::Int(network_argument("network", args[0])?.network()))
+}
+
+fn broadcast_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(Value::Int(
+        network_argument("broadcast", args[0])?.broadcast(),
+    ))
+}
+
+fn netmask_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(Value::Int(network_argument("netmask", args[0])?.netmask()))
+}
+
+fn hosts_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    Ok(Value::Int(network_argument("hosts", args[0])?.hosts()))
+}
+
+/// `1` if the address or the network is in the network, `0` otherwise
+fn contains_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    let network = network_argument("contains", args[0])?;
+    let contained = match args[1] {
+        Value::Network(other) => network.contains_network(other),
+        address => network.contains(address.as_int("contains")?),
+    };
+    Ok(Value::Int(BigInt::from(contained as u8)))
+}
+
+/// The bit pattern of the nearest number in the format, an integer or a number like `3.14`
+fn float_bits(function: &str, arg: &Value, format: Format) -> Result<Value, CalcError> {
+    let value = match arg {
+        Value::Quantity(q) if q.dimension() == units::DIMENSIONLESS => q.value.clone(),
+        Value::Quantity(_) => return Err(invalid_argument(function, "expected a plain number")),
+        n => n.as_int(function)?.clone().into(),
+    };
+    Ok(Value::Int(float::encode(&value, format)))
+}
+
+/// The exact value of a bit pattern, which must be a finite number
+fn from_float_bits(function: &str, arg: &Value, format: Format) -> Result<Value, CalcError> {
+    let bits = arg.as_int(function)?;
+    if !format.contains(bits) {
+        let reason = format!("expected a {} bit pattern", format.width());
+        return Err(invalid_argument(function, &reason));
+    }
+    match Fields::new(bits, format).value(format) {
+        Some(value) => Ok(Quantity::plain(value).into()),
+        None => Err(invalid_argument(function, "the bits are infinity or NaN")),
+    }
+}
+
+fn f16bits_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    float_bits("f16bits", args[0], float::F16)
+}
+
+fn bf16bits_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    float_bits("bf16bits", args[0], float::BF16)
+}
+
+fn f32bits_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    float_bits("f32bits", args[0], float::F32)
+}
+
+fn f64bits_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    float_bits("f64bits", args[0], float::F64)
+}
+
+fn fromf16bits_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    from_float_bits("fromf16bits", args[0], float::F16)
+}
+
+fn frombf16bits_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    from_float_bits("frombf16bits", args[0], float::BF16)
+}
+
+fn fromf32bits_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    from_float_bits("fromf32bits", args[0], float::F32)
+}
+
+fn fromf64bits_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    from_float_bits("fromf64bits", args[0], float::F64)
+}
+
+/// The elements of a list of bytes
+fn byte_list(function: &str, arg: &Value) -> Result<Vec<u8>, CalcError> {
+    match arg {
+        Value::Matrix(m) if m.is_vector() => m
+            .elements()
+            .iter()
+            .map(|byte| {
+                byte.to_u8()
+                    .ok_or_else(|| invalid_argument(function, "the list must contain bytes"))
+            })
+            .collect(),
+        Value::Matrix(m) => Err(invalid_argument(
+            function,
+            &format!("a {} matrix is not a list", m.shape()),
+        )),
+        _ => Err(invalid_argument(function, "expected a list of bytes")),
+    }
+}
+
+/// The bytes of the arguments `x` or `x, len` from the most significant one, or the elements
+/// of a list of bytes
+fn data_argument(function: &str, args: &[&Value], ctx: &Evaluation) -> Result<Vec<u8>, CalcError> {
+    match args {
+        [list @ Value::Matrix(_)] => byte_list(function, list),
+        _ => encoded_argument(function, args, false, ctx),
+    }
+}
+
+/// The bytes of the arguments `x` or `x, len`, from the least significant one if
+/// `little_endian`
+fn encoded_argument(
+    function: &str,
+    args: &[&Value],
+    little_endian: bool,
+    ctx: &Evaluation,
+) -> Result<Vec<u8>, CalcError> {
+    let x = args[0].as_int(function)?;
+    let len = match args.get(1) {
+        Some(len) => Some(bytes::byte_count(function, len.as_int(function)?)?),
+        None => None,
+    };
+    bytes::to_bytes(x, len, little_endian, function, ctx)
+}
+
+fn checksum_function<F, T>(
+    function: &str,
+    args: &[&Value],
+    ctx: &Evaluation,
+    checksum: F,
+) -> Result<Value, CalcError>
+where
+    F: Fn(&[u8]) -> T,
+    BigInt: From<T>,
+{
+    let data = data_argument(function, args, ctx)?;
+    Ok(Value::Int(BigInt::from(checksum(&data))))
+}
+
+/// `crc(x, width, poly, init, reflect, xorout)` with the parameters of the Rocksoft model
+fn crc_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    let width = args[1]
+        .as_int("crc")?
+        .to_u32()
+        .filter(|width| (1..=64).contains(width))
+        .ok_or_else(|| invalid_argument("crc", "the width must be 1 to 64 bits"))?;
+    let parameter = |arg: &Value| -> Result<u64, CalcError> {
+        let n = arg.as_int("crc")?;
+        match n.to_u64() {
+            Some(n) if n.checked_shr(width).unwrap_or(0) == 0 => Ok(n),
+            _ => Err(invalid_argument(
+                "crc",
+                &format!("{} does not fit in {} bits", n, width),
+            )),
+        }
+    };
+    let crc = Crc {
+        width,
+        poly: parameter(args[2])?,
+        init: parameter(args[3])?,
+        reflect: !args[4].as_int("crc")?.is_zero(),
+        xorout: parameter(args[5])?,
+    };
+    checksum_function("crc", &args[..1], ctx, |data| crc.checksum(data))
+}
+
+fn crc8_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("crc8", args, ctx, |data| checksum::CRC8.checksum(data))
+}
+
+fn crc8maxim_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("crc8maxim", args, ctx, |data| {
+        checksum::CRC8_MAXIM.checksum(data)
+    })
+}
+
+fn crc16_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("crc16", args, ctx, |data| checksum::CRC16.checksum(data))
+}
+
+fn crc16ccitt_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("crc16ccitt", args, ctx, |data| {
+        checksum::CRC16_CCITT.checksum(data)
+    })
+}
+
+fn crc16modbus_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("crc16modbus", args, ctx, |data| {
+        checksum::CRC16_MODBUS.checksum(data)
+    })
+}
+
+fn crc16xmodem_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("crc16xmodem", args, ctx, |data| {
+        checksum::CRC16_XMODEM.checksum(data)
+    })
+}
+
+fn crc32_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("crc32", args, ctx, |data| checksum::CRC32.checksum(data))
+}
+
+fn crc32c_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("crc32c", args, ctx, |data| checksum::CRC32C.checksum(data))
+}
+
+fn crc32mpeg2_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("crc32mpeg2", args, ctx, |data| {
+        checksum::CRC32_MPEG2.checksum(data)
+    })
+}
+
+fn adler32_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("adler32", args, ctx, checksum::adler32)
+}
+
+fn fletcher16_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("fletcher16", args, ctx, checksum::fletcher16)
+}
+
+fn fletcher32_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("fletcher32", args, ctx, checksum::fletcher32)
+}
+
+fn sha256_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("sha256", args, ctx, checksum::sha256)
+}
+
+fn md5_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    checksum_function("md5", args, ctx, checksum::md5)
+}
+
+/// `tobytes(x)` or `tobytes(x, len)` is the list of the bytes of `x` from the most significant one
+fn tobytes_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    byte_list_value(encoded_argument("tobytes", args, false, ctx)?)
+}
+
+/// `tobytes_le(x)` or `tobytes_le(x, len)` is the list of the bytes of `x` from the least
+/// significant one
+fn tobytes_le_function(args: &[&Value], ctx: &Evaluation) -> Result<Value, CalcError> {
+    byte_list_value(encoded_argument("tobytes_le", args, true, ctx)?)
+}
+
+fn byte_list_value(bytes: Vec<u8>) -> Result<Value, CalcError> {
+    let elements = bytes.into_iter().map(BigInt::from).collect();
+    Ok(Value::Matrix(Matrix::vector(elements, false)))
+}
+
+/// `frombytes(list)` joins the bytes into one number, the first one is the most significant
+fn frombytes_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    let bytes = byte_list("frombytes", args[0])?;
+    Ok(Value::Int(bytes::from_bytes(&bytes, false)))
+}
+
+/// `frombytes_le(list)` joins the bytes into one number, the first one is the least significant
+fn frombytes_le_function(args: &[&Value], _ctx: &Evaluation) -> Result<Value, CalcError> {
+    let bytes = byte_list("frombytes_le", args[0])?;
+    Ok(Value::Int(bytes::from_bytes(&bytes, true)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::tests::empty_evaluation;
+
+    fn call(name: &str, args: &[i64]) -> Result<BigInt, CalcError> {
+        let args: Vec<Value> = args.iter().map(|&arg| BigInt::from(arg).into()).collect();
+        let args: Vec<&Value> = args.iter().collect();
+        FUNCTIONS[name]
+            .apply(name, &args, &empty_evaluation())
+            .and_then(|res| res.into_int(name))
+    }
+
+    #[test]
+    fn test_modulo_signs() {
+        // (dividend, divisor, rem, mod, emod)
+        let cases = [
+            (7, 3, 1, 1, 1),
+            (-7, 3, -1, 2, 2),
+            (7, -3, 1, -2, 1),
+            (-7, -3, -1, -1, 2),
+            (6, -3, 0, 0, 0),
+        ];
+
+        for (lhs, rhs, rem, modulo, emod) in cases {
+            assert_eq!(call("rem", &[lhs, rhs]), Ok(BigInt::from(rem)));
+            assert_eq!(call("mod", &[lhs, rhs]), Ok(BigInt::from(modulo)));
+            assert_eq!(call("emod", &[lhs, rhs]), Ok(BigInt::from(emod)));
+        }
+    }
+
+    #[test]
+    fn test_modulo_by_zero() {
+        for name in ["rem", "mod", "emod"] {
+            assert_eq!(
+                call(name, &[5, 0]),
+                Err(CalcError::DivisionByZero(BigInt::from(5).into()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_primes() {
+        assert_eq!(call("isprime", &[97]), Ok(BigInt::from(1)));
+        assert_eq!(call("isprime", &[-7]), Ok(BigInt::from(0)));
+        assert_eq!(call("nextprime", &[-10]), Ok(BigInt::from(2)));
+        assert_eq!(call("nextprime", &[89]), Ok(BigInt::from(97)));
+        assert_eq!(call("prevprime", &[97]), Ok(BigInt::from(89)));
+        assert_eq!(call("prevprime", &[3]), Ok(BigInt::from(2)));
+        assert_eq!(
+            call("prevprime", &[2]),
+            Err(CalcError::InvalidArgument(
+                String::from("prevprime"),
+                String::from("there is no prime below 2")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_totient() {
+        assert_eq!(call("totient", &[1]), Ok(BigInt::from(1)));
+        assert_eq!(call("totient", &[36]), Ok(BigInt::from(12)));
+        assert!(call("totient", &[0]).is_err());
+    }
+
+    #[test]
+    fn test_symbols() {
+        assert_eq!(call("legendre", &[2, 7]), Ok(BigInt::from(1)));
+        assert_eq!(call("legendre", &[3, 7]), Ok(BigInt::from(-1)));
+        assert_eq!(call("legendre", &[14, 7]), Ok(BigInt::from(0)));
+        assert!(call("legendre", &[2, 9]).is_err());
+        assert_eq!(call("jacobi", &[2, 9]), Ok(BigInt::from(1)));
+        assert!(call("jacobi", &[2, 8]).is_err());
+    }
+
+    #[test]
+    fn test_gcd_lcm_invmod_crt() {
+        assert_eq!(call("gcd", &[-12, 18, 8]), Ok(BigInt::from(2)));
+        assert_eq!(call("lcm", &[4, -6]), Ok(BigInt::from(12)));
+        assert_eq!(call("invmod", &[3, 7]), Ok(BigInt::from(5)));
+        assert_eq!(call("invmod", &[-3, 7]), Ok(BigInt::from(2)));
+        assert_eq!(
+            call("invmod", &[4, 6]),
+            Err(CalcError::NoModularInverse(
+                BigInt::from(4).into(),
+                BigInt::from(6)
+            ))
+        );
+        assert_eq!(call("crt", &[2, 3, 3, 5, 2, 7]), Ok(BigInt::from(23)));
+        assert!(call("crt", &[2, 3, 3]).is_err());
+        assert!(call("crt", &[2, 0]).is_err());
+    }
+
+    #[test]
+    fn test_bit_functions() {
+        assert_eq!(call("popcount", &[0xFF00]), Ok(BigInt::from(8)));
+        assert_eq!(call("clz", &[1, 32]), Ok(BigInt::from(31)));
+        assert_eq!(call("clz", &[-1, 32]), Ok(BigInt::from(0)));
+        assert_eq!(call("ctz", &[0, 32]), Ok(BigInt::from(32)));
+        assert_eq!(call("ctz", &[0x50, 32]), Ok(BigInt::from(4)));
+        assert!(call("clz", &[1 << 32, 32]).is_err());
+        assert_eq!(call("bitlen", &[-256]), Ok(BigInt::from(9)));
+        assert_eq!(call("bit", &[-2, 100]), Ok(BigInt::from(1)));
+        assert_eq!(call("setbit", &[0, 4]), Ok(BigInt::from(16)));
+        assert_eq!(call("clearbit", &[-1, 0]), Ok(BigInt::from(-2)));
+        assert_eq!(call("togglebit", &[5, 2]), Ok(BigInt::from(1)));
+        assert!(call("setbit", &[0, -1]).is_err());
+        assert_eq!(call("bits", &[0xABCD, 7, 4]), Ok(BigInt::from(0xC)));
+        assert_eq!(call("insert", &[0, 3, 5, 4]), Ok(BigInt::from(0x30)));
+        assert_eq!(call("bswap16", &[0x1234]), Ok(BigInt::from(0x3412)));
+        assert_eq!(call("bswap32", &[-2]), Ok(BigInt::from(0xFEFF_FFFFu32)));
+        assert_eq!(
+            call("bswap64", &[0x0102030405060708]),
+            Ok(BigInt::from(0x0807060504030201u64))
+        );
+        assert!(call("bswap16", &[0x10000]).is_err());
+        assert_eq!(call("bswap", &[0x123456, 3]), Ok(BigInt::from(0x563412)));
+        assert_eq!(call("bswap", &[0x1234, 4]), Ok(BigInt::from(0x34120000)));
+        assert_eq!(call("bitrev", &[1, 32]), Ok(BigInt::from(1u32 << 31)));
+        assert_eq!(call("nextpow2", &[0]), Ok(BigInt::from(1)));
+        assert_eq!(call("nextpow2", &[64]), Ok(BigInt::from(64)));
+        assert_eq!(call("nextpow2", &[65]), Ok(BigInt::from(128)));
+        assert_eq!(call("parity", &[7]), Ok(BigInt::from(1)));
+    }
+
+    #[test]
+    fn test_root_functions() {
+        assert_eq!(call("isqrt", &[1 << 40]), Ok(BigInt::from(1 << 20)));
+        assert_eq!(call("iroot", &[-9, 3]), Ok(BigInt::from(-3)));
+        assert_eq!(call("ilog10", &[12345]), Ok(BigInt::from(4)));
+        assert_eq!(
+            call("ilog2", &[-8]),
+            Err(CalcError::NonPositiveLogarithm(BigInt::from(-8)))
+        );
+        assert_eq!(call("is_square", &[49]), Ok(BigInt::from(1)));
+        assert_eq!(call("is_power", &[1]), Ok(BigInt::from(1)));
+        assert_eq!(call("is_power", &[-27]), Ok(BigInt::from(1)));
+        assert_eq!(call("is_power", &[10]), Ok(BigInt::from(0)));
+    }
+}
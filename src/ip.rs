@@ -0,0 +1,218 @@
+use std::{
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive};
+
+use crate::error::CalcError;
+
+/// An IPv4 or IPv6 network in CIDR notation, e.g. `192.168.1.0/24`.
+/// The address is kept as written, it doesn't have to be the first one of the network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    pub address: BigInt,
+    pub prefix: u32,
+    /// 32 for IPv4, 128 for IPv6
+    pub width: u32,
+}
+
+impl Network {
+    pub fn new(address: BigInt, prefix: u32, width: u32) -> Result<Network, CalcError> {
+        if prefix > width || address.is_negative() || address.bits() > u64::from(width) {
+            let address = format_with_width(&address, width).unwrap_or_else(|| address.to_string());
+            return Err(CalcError::InvalidAddress(format!("{}/{}", address, prefix)));
+        }
+        Ok(Network {
+            address,
+            prefix,
+            width,
+        })
+    }
+
+    /// The network of an IPv4 address if it fits in 32 bits and the prefix is at most 32,
+    /// otherwise of an IPv6 address
+    pub fn guess(address: BigInt, prefix: &BigInt) -> Result<Network, CalcError> {
+        let prefix = prefix.to_u32().unwrap_or(u32::MAX);
+        let width = if address.bits() <= 32 && prefix <= 32 {
+            32
+        } else {
+            128
+        };
+        Network::new(address, prefix, width)
+    }
+
+    /// The prefix length ones followed by zeros
+    pub fn netmask(&self) -> BigInt {
+        let ones = (BigInt::one() << self.prefix) - 1;
+        ones << (self.width - self.prefix)
+    }
+
+    /// The first address
+    pub fn network(&self) -> BigInt {
+        &self.address & self.netmask()
+    }
+
+    /// The last address
+    pub fn broadcast(&self) -> BigInt {
+        let host_mask = (BigInt::one() << (self.width - self.prefix)) - 1;
+        self.network() | host_mask
+    }
+
+    /// Number of the usable addresses. IPv4 networks don't count their network and broadcast
+    /// addresses, except the point-to-point `/31` and the single host `/32` networks.
+    pub fn hosts(&self) -> BigInt {
+        let size = BigInt::one() << (self.width - self.prefix);
+        match self.width - self.prefix {
+            0 | 1 if self.width == 32 => size,
+            _ if self.width == 32 => size - 2,
+            _ => size,
+        }
+    }
+
+    /// Whether the address is in the network
+    pub fn contains(&self, address: &BigInt) -> bool {
+        !address.is_negative()
+            && address.bits() <= u64::from(self.width)
+            && (address & self.netmask()) == self.network()
+    }
+
+    /// Whether the other network is a part of this one
+    pub fn contains_network(&self, other: &Network) -> bool {
+        other.width == self.width && other.prefix >= self.prefix && self.contains(&other.address)
+    }
+}
+
+/// The address in CIDR notation, e.g. `10.0.0.0/8` or `2001:db8::/32`
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let address = format_with_width(&self.address, self.width).unwrap_or_default();
+        write!(f, "{}/{}", address, self.prefix)
+    }
+}
+
+fn format_with_width(address: &BigInt, width: u32) -> Option<String> {
+    match width {
+        32 => Some(Ipv4Addr::from(address.to_u32()?).to_string()),
+        _ => Some(Ipv6Addr::from(address.to_u128()?).to_string()),
+    }
+}
+
+/// Dotted-quad for values below 2^32, IPv6 notation for values below 2^128.
+/// `None` for negative and larger values.
+pub fn format_address(address: &BigInt) -> Option<String> {
+    match address.bits() {
+        _ if address.is_negative() => None,
+        0..=32 => format_with_width(address, 32),
+        33..=128 => format_with_width(address, 128),
+        _ => None,
+    }
+}
+
+/// Parses an IP address at the start of the string, like `192.168.1.1`, `::1` or `fe80::1`.
+/// Returns the address, its width in bits and the length of the literal, or `None` if the
+/// string doesn't start with an address.
+pub fn parse_address(s: &str) -> Result<Option<(BigInt, u32, usize)>, CalcError> {
+    let len = s
+        .find(|c: char| !(c.is_ascii_hexdigit() || c == ':' || c == '.'))
+        .unwrap_or(s.len());
+    let literal = &s[..len];
+
+    if literal.contains(':') {
+        // Otherwise the colon is an invalid token
+        return Ok(literal
+            .parse::<Ipv6Addr>()
+            .ok()
+            .map(|address| (BigInt::from(u128::from(address)), 128, len)));
+    }
+    // Four numbers separated by dots can only be an IPv4 address
+    if literal.matches('.').count() == 3 && literal.bytes().all(|b| b == b'.' || b.is_ascii_digit())
+    {
+        return match literal.parse::<Ipv4Addr>() {
+            Ok(address) => Ok(Some((BigInt::from(u32::from(address)), 32, len))),
+            Err(_) => Err(CalcError::InvalidAddress(literal.to_string())),
+        };
+    }
+    Ok(None)
+}
+
+/// The prefix length of a CIDR literal after the address, e.g. `/24`, and its length
+pub fn parse_prefix(s: &str) -> Option<(u32, usize)> {
+    let digits = s.strip_prefix('/')?;
+    let len = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    if len == 0 {
+        return None;
+    }
+    Some((digits[..len].parse().unwrap_or(u32::MAX), len + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(s: &str) -> Network {
+        let (address, width, len) = parse_address(s).unwrap().unwrap();
+        let (prefix, _) = parse_prefix(&s[len..]).unwrap();
+        Network::new(address, prefix, width).unwrap()
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            parse_address("192.168.1.10 + 1"),
+            Ok(Some((BigInt::from(0xC0A8010Au32), 32, 12)))
+        );
+        assert_eq!(
+            parse_address("fe80::1/64"),
+            Ok(Some((BigInt::from(0xfe80u128 << 112 | 1), 128, 7)))
+        );
+        assert_eq!(parse_address("::"), Ok(Some((BigInt::from(0), 128, 2))));
+        assert_eq!(parse_address("abc + 1"), Ok(None));
+        assert_eq!(parse_address("12:30"), Ok(None));
+        assert_eq!(parse_address("1.5"), Ok(None));
+        assert_eq!(
+            parse_address("256.0.0.1"),
+            Err(CalcError::InvalidAddress(String::from("256.0.0.1")))
+        );
+        assert_eq!(parse_prefix("/24 + 1"), Some((24, 3)));
+        assert_eq!(parse_prefix("/ 24"), None);
+    }
+
+    #[test]
+    fn test_network() {
+        let net = network("192.168.1.77/24");
+        assert_eq!(net.to_string(), "192.168.1.77/24");
+        assert_eq!(format_address(&net.network()).unwrap(), "192.168.1.0");
+        assert_eq!(format_address(&net.broadcast()).unwrap(), "192.168.1.255");
+        assert_eq!(format_address(&net.netmask()).unwrap(), "255.255.255.0");
+        assert_eq!(net.hosts(), BigInt::from(254));
+        assert!(net.contains(&BigInt::from(0xC0A801FFu32)));
+        assert!(!net.contains(&BigInt::from(0xC0A802FFu32)));
+        assert!(net.contains_network(&network("192.168.1.128/25")));
+        assert!(!net.contains_network(&network("192.168.0.0/16")));
+
+        assert_eq!(network("10.0.0.0/31").hosts(), BigInt::from(2));
+        assert_eq!(network("10.0.0.1/32").hosts(), BigInt::one());
+        assert_eq!(network("10.0.0.0/0").hosts(), (BigInt::one() << 32) - 2);
+
+        let net = network("2001:db8::1/32");
+        assert_eq!(net.to_string(), "2001:db8::1/32");
+        assert_eq!(format_address(&net.network()).unwrap(), "2001:db8::");
+        assert_eq!(
+            format_address(&net.broadcast()).unwrap(),
+            "2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"
+        );
+        assert_eq!(net.hosts(), BigInt::one() << 96);
+
+        assert_eq!(
+            Network::new(BigInt::one(), 129, 128),
+            Err(CalcError::InvalidAddress(String::from("::1/129")))
+        );
+        assert!(Network::new(BigInt::one(), 33, 32).is_err());
+        assert_eq!(format_address(&-BigInt::one()), None);
+        assert_eq!(format_address(&(BigInt::one() << 128)), None);
+    }
+}
@@ -0,0 +1,233 @@
+use std::fmt;
+
+use num_bigint::BigInt;
+
+use crate::{bitwise, error::CalcError, evaluator::Evaluation};
+
+/// A named range of bits from `hi` down to `lo`, both inclusive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub hi: u32,
+    pub lo: u32,
+}
+
+impl Field {
+    pub fn width(&self) -> u32 {
+        self.hi - self.lo + 1
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.hi == self.lo {
+            write!(f, "{}:{}", self.name, self.lo)
+        } else {
+            write!(f, "{}:{}..{}", self.name, self.hi, self.lo)
+        }
+    }
+}
+
+/// Named bit fields of a register, e.g. `en:0, mode:3..1, irq:7`.
+/// The fields don't overlap, bits outside of them are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub fields: Vec<Field>,
+}
+
+impl Layout {
+    /// Parses the comma separated `name:bit` and `name:hi..lo` fields.
+    /// The bits of the fields must be within the result size limit.
+    pub fn parse(spec: &str, ctx: &Evaluation) -> Result<Layout, CalcError> {
+        let mut fields: Vec<Field> = vec![];
+        for part in spec.split(',').map(str::trim) {
+            let invalid = || CalcError::InvalidCommandArgument(part.to_string());
+
+            let (name, range) = part.split_once(':').ok_or_else(invalid)?;
+            let (name, range) = (name.trim(), range.trim());
+            let mut chars = name.chars();
+            if !chars.next().is_some_and(char::is_alphabetic)
+                || !chars.all(|c| c.is_alphanumeric() || c == '_')
+            {
+                return Err(invalid());
+            }
+
+            let position = |n: &str| n.trim().parse::<u32>().map_err(|_| invalid());
+            let (hi, lo) = match range.split_once("..") {
+                Some((a, b)) => {
+                    let (a, b) = (position(a)?, position(b)?);
+                    (a.max(b), a.min(b))
+                }
+                None => (position(range)?, position(range)?),
+            };
+            ctx.check_bits(u64::from(hi) + 1)?;
+
+            if fields
+                .iter()
+                .any(|field| field.name == name || (field.lo <= hi && lo <= field.hi))
+            {
+                return Err(invalid());
+            }
+            fields.push(Field {
+                name: name.to_string(),
+                hi,
+                lo,
+            });
+        }
+        Ok(Layout { fields })
+    }
+
+    /// Number of the bits up to the highest field
+    pub fn width(&self) -> u32 {
+        self.fields
+            .iter()
+            .map(|field| field.hi + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Values of the fields, negative values are taken in two's complement
+    pub fn decode(&self, val: &BigInt, ctx: &Evaluation) -> Result<Vec<(&str, BigInt)>, CalcError> {
+        self.fields
+            .iter()
+            .map(|field| {
+                let (hi, lo) = (BigInt::from(field.hi), BigInt::from(field.lo));
+                Ok((field.name.as_str(), bitwise::extract(val, &hi, &lo, ctx)?))
+            })
+            .collect()
+    }
+
+    /// Builds a value from the values of some of the fields, the rest of the bits are zero.
+    /// A field value can be negative if it fits in the field in two's complement.
+    pub fn pack(&self, vals: &[(String, BigInt)], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+        let invalid = |reason: String| CalcError::InvalidArgument(String::from("pack"), reason);
+
+        let mut res = BigInt::from(0);
+        let mut assigned: Vec<&str> = vec![];
+        for (name, val) in vals {
+            let field = self
+                .fields
+                .iter()
+                .find(|field| &field.name == name)
+                .ok_or_else(|| invalid(format!("there is no field {}", name)))?;
+            if assigned.contains(&name.as_str()) {
+                return Err(invalid(format!("{} is assigned more than once", name)));
+            }
+            assigned.push(name);
+
+            let val = bitwise::to_width(val, u64::from(field.width()), "pack", ctx)?;
+            res |= val << field.lo;
+        }
+        Ok(res)
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", field)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints the bits of a non-negative value of the given width below their positions.
+/// The bits are grouped by nibble with a wider gap between the bytes, the position of the
+/// highest bit of each nibble is written above it.
+pub fn bit_ruler(val: &BigInt, width: u32) -> String {
+    let mut ruler = String::new();
+    let mut digits = String::new();
+    let nibbles = width.div_ceil(4);
+    for nibble in (0..nibbles).rev() {
+        let lo = nibble * 4;
+        let hi = (lo + 3).min(width - 1);
+        for n in (lo..=hi).rev() {
+            digits.push(if val.bit(u64::from(n)) { '1' } else { '0' });
+        }
+        let label = hi.to_string();
+        let gap = match nibble {
+            0 => 0,
+            _ if lo % 8 == 0 => 2,
+            _ => 1,
+        };
+        // Labels which would run into the next one are left out
+        let space = (hi - lo + 1 + gap) as usize;
+        if label.len() < space || nibble == 0 {
+            ruler.push_str(&format!("{:<1$}", label, space));
+        } else {
+            ruler.push_str(&" ".repeat(space));
+        }
+        digits.push_str(&" ".repeat(gap as usize));
+    }
+    format!("{}\n{}", ruler.trim_end(), digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::tests::empty_evaluation;
+
+    #[test]
+    fn test_parse() {
+        let ctx = empty_evaluation();
+
+        let layout = Layout::parse("en:0, mode : 1..3,irq:7", &ctx).unwrap();
+        assert_eq!(layout.to_string(), "en:0, mode:3..1, irq:7");
+        assert_eq!(layout.width(), 8);
+        for spec in [
+            "en",
+            "en:",
+            "1en:0",
+            "en:0, en:1",
+            "en:0, mode:3..0",
+            "en:-1",
+        ] {
+            assert_eq!(
+                Layout::parse(spec, &ctx),
+                Err(CalcError::InvalidCommandArgument(
+                    spec.rsplit(',').next().unwrap().trim().to_string()
+                ))
+            );
+        }
+        assert_eq!(
+            Layout::parse("big:4000000000", &ctx),
+            Err(CalcError::ResultTooLarge(1 << 24))
+        );
+    }
+
+    #[test]
+    fn test_decode_pack() {
+        let ctx = empty_evaluation();
+        let layout = Layout::parse("en:0, mode:3..1, irq:7", &ctx).unwrap();
+
+        assert_eq!(
+            layout.decode(&BigInt::from(0x8B), &ctx),
+            Ok(vec![
+                ("en", BigInt::from(1)),
+                ("mode", BigInt::from(5)),
+                ("irq", BigInt::from(1))
+            ])
+        );
+        let vals = [
+            (String::from("en"), BigInt::from(1)),
+            (String::from("mode"), BigInt::from(-3)),
+        ];
+        assert_eq!(layout.pack(&vals, &ctx), Ok(BigInt::from(0xB)));
+        let vals = [(String::from("mode"), BigInt::from(8))];
+        assert!(layout.pack(&vals, &ctx).is_err());
+        let vals = [(String::from("ctrl"), BigInt::from(1))];
+        assert!(layout.pack(&vals, &ctx).is_err());
+    }
+
+    #[test]
+    fn test_bit_ruler() {
+        assert_eq!(
+            bit_ruler(&BigInt::from(0xABCD), 16),
+            "15   11    7    3\n1010 1011  1100 1101"
+        );
+        assert_eq!(bit_ruler(&BigInt::from(5), 6), "5  3\n00 0101");
+    }
+}
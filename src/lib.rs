@@ -0,0 +1,40 @@
+//! A basic integer arithmetic calculator supporting arbitrarily large numbers and variables.
+//!
+//! [`Calculator`] keeps the state of a session and evaluates it line by line.
+//! The single steps are also available on their own, [`tokenize`] -> [`parse_assignment`] -> [`eval_assignment`].
+
+pub mod bitwise;
+pub mod bytes;
+pub mod calculator;
+pub mod checksum;
+pub mod combinatorics;
+pub mod complex;
+pub mod datetime;
+pub mod error;
+pub mod evaluator;
+pub mod float;
+pub mod format;
+pub mod function;
+pub mod ip;
+pub mod layout;
+pub mod matrix;
+pub mod number_theory;
+pub mod parser;
+pub mod random;
+pub mod roots;
+pub mod special_function;
+pub mod statistics;
+pub mod text;
+pub mod tokenizer;
+pub mod units;
+pub mod value;
+pub mod variable_store;
+
+pub use calculator::{Calculator, Output};
+pub use error::CalcError;
+pub use evaluator::{eval_assignment, eval_assignment_with, EvalOptions, Limits};
+pub use parser::{parse_assignment, Assign, Expr, ExprBitwise, Factor, Term, RES_VAR};
+pub use special_function::{DisplayMode, RuntimeVariables};
+pub use tokenizer::{tokenize, Token};
+pub use value::Value;
+pub use variable_store::{LayeredStore, ReadOnlyStore, VariableStore};
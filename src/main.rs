@@ -1,43 +1,100 @@
-use evaluator::eval_assignment;
-use parser::parse_assignment;
-use special_function::{DisplayMode, RuntimeVariables, SPECIAL_FUNCTIONS};
+use color::{paint, Color};
+use repl_helper::ReplHelper;
+use rustyline::{error::ReadlineError, CompletionType, Config, Editor};
+use simple_calculator::{format::format_value, Calculator, Output};
+use std::{
+    process::exit,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use crate::tokenizer::tokenize;
-use std::{collections::HashMap, process::exit};
-
-mod error;
-mod evaluator;
-mod parser;
-mod special_function;
-mod tokenizer;
+mod color;
+mod repl_helper;
 
 fn main() {
-    let stdin = std::io::stdin();
-
-    let mut runtime_vars = RuntimeVariables {
-        vars: HashMap::new(),
-        display_mode: DisplayMode::Decimal,
+    // List all the candidates below the prompt when a completion is ambiguous
+    let config = Config::builder()
+        .completion_type(CompletionType::List)
+        .auto_add_history(true)
+        .build();
+    let mut editor = match Editor::with_config(config) {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!("Could not start the REPL: {}", err);
+            exit(1)
+        }
     };
+    editor.set_helper(Some(ReplHelper::default()));
+
+    let mut calculator = Calculator::new();
+
+    // Ctrl-C while evaluating only stops the evaluation. Outside of evaluations
+    // the editor handles it, unless the input is not a terminal.
+    let evaluating = Arc::new(AtomicBool::new(false));
+    let interrupt = calculator.interrupt_flag();
+    let handler_evaluating = Arc::clone(&evaluating);
+    if let Err(err) = ctrlc::set_handler(move || {
+        if handler_evaluating.load(Ordering::Relaxed) {
+            interrupt.store(true, Ordering::Relaxed);
+        } else {
+            exit(130);
+        }
+    }) {
+        eprintln!("Could not set the Ctrl-C handler: {}", err);
+    }
 
     loop {
-        let mut line = String::new();
-        if stdin.read_line(&mut line).is_err() {
-            eprintln!("Unexpected IO Error Occurred");
-            exit(1)
+        // User-defined functions are completed like variables
+        if let Some(helper) = editor.helper_mut() {
+            let runtime_vars = calculator.runtime_vars();
+            helper.variables = (runtime_vars.vars.keys())
+                .chain(runtime_vars.functions.keys())
+                .cloned()
+                .collect();
+        }
+
+        // The active modulus is shown in the prompt
+        let runtime_vars = calculator.runtime_vars();
+        let prompt = match &runtime_vars.modulus {
+            Some(modulus) => format!(
+                "mod {}> ",
+                format_value(modulus, &runtime_vars.display_mode)
+            ),
+            None => String::new(),
         };
-        let line = line.trim();
-
-        match SPECIAL_FUNCTIONS.get(line) {
-            Some(f) => f(&mut runtime_vars),
-            None => {
-                if let Err(err) = tokenize(line)
-                    .and_then(|tokens| parse_assignment(&tokens))
-                    .and_then(|expr| eval_assignment(expr, &mut runtime_vars.vars))
-                    .map(|var| {
-                        // Print in correct display mode
-                        print_variable(&runtime_vars, &var);
-                    })
-                {
+
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            // Ctrl-C only discards the current line
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => exit(0),
+            Err(_) => {
+                eprintln!("Unexpected IO Error Occurred");
+                exit(1)
+            }
+        };
+
+        evaluating.store(true, Ordering::Relaxed);
+        let res = calculator.evaluate(&line);
+        evaluating.store(false, Ordering::Relaxed);
+
+        match res {
+            Ok(Output::Variables(vars)) => {
+                for (var, val) in vars {
+                    print_variable(&var, &val);
+                }
+            }
+            Ok(Output::Text(text)) => println!("{}", text),
+            // https://stackoverflow.com/a/62101709/7611589
+            Ok(Output::ClearScreen) => println!("\x1B[2J\x1B[1;1H"),
+            Ok(Output::Exit) => exit(0),
+            Ok(Output::None) => {}
+            Err(err) => {
+                if color::stderr_colors() {
+                    eprintln!("{}", paint(&err.to_string(), Color::Red));
+                } else {
                     eprintln!("{}", err);
                 }
             }
@@ -45,17 +102,13 @@ fn main() {
     }
 }
 
-/// Print the given variable with its value and correct display mode
-fn print_variable(runtime_vars: &RuntimeVariables, var: &str) {
-    let Some(val) = runtime_vars.vars.get(var) else {
-        // This should never happen
-        panic!("Tried to print non-existing variable {}. \nVariables:{:?}", var, runtime_vars.vars);
-    };
-
-    print!("\\> {} = ", var);
-    match runtime_vars.display_mode {
-        DisplayMode::Binary => println!("0b{:b}", val),
-        DisplayMode::Decimal => println!("{}", val),
-        DisplayMode::Hex => println!("0x{:X}", val),
+/// Print the given variable with its already formatted value.
+/// Values spanning multiple lines, like matrices, start below the name.
+fn print_variable(var: &str, val: &str) {
+    let separator = if val.contains('\n') { "\n" } else { " " };
+    if color::stdout_colors() {
+        println!("\\> {} ={}{}", paint(var, Color::Dimmed), separator, val);
+    } else {
+        println!("\\> {} ={}{}", var, separator, val);
     }
 }
@@ -0,0 +1,382 @@
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_traits::{One, Signed, Zero};
+
+use crate::{error::CalcError, evaluator::Evaluation, number_theory};
+
+/// Matrix of integers, the elements are stored row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    elements: Vec<BigInt>,
+}
+
+impl Matrix {
+    /// Fails if the rows do not all have the same length
+    pub fn from_rows(rows: Vec<Vec<BigInt>>) -> Result<Matrix, CalcError> {
+        let cols = rows.first().map_or(0, Vec::len);
+        if let Some((i, row)) = rows.iter().enumerate().find(|(_, row)| row.len() != cols) {
+            return Err(CalcError::MatrixRowLength(i + 1, row.len(), cols));
+        }
+
+        Ok(Matrix {
+            rows: rows.len(),
+            cols,
+            elements: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    /// A list of values as a row vector, or as a column vector if `column` is set
+    pub fn vector(elements: Vec<BigInt>, column: bool) -> Matrix {
+        let (rows, cols) = match column {
+            true => (elements.len(), 1),
+            false => (1, elements.len()),
+        };
+        Matrix {
+            rows,
+            cols,
+            elements,
+        }
+    }
+
+    pub fn identity(n: usize) -> Matrix {
+        let mut elements = vec![BigInt::zero(); n * n];
+        for i in 0..n {
+            elements[i * n + i] = BigInt::one();
+        }
+        Matrix {
+            rows: n,
+            cols: n,
+            elements,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn row(&self, i: usize) -> &[BigInt] {
+        &self.elements[i * self.cols..(i + 1) * self.cols]
+    }
+
+    /// A matrix with a single row or column, which is used as a list
+    pub fn is_vector(&self) -> bool {
+        self.rows <= 1 || self.cols == 1
+    }
+
+    pub fn is_column(&self) -> bool {
+        self.cols == 1 && self.rows != 1
+    }
+
+    pub fn elements(&self) -> &[BigInt] {
+        &self.elements
+    }
+
+    /// Number of rows and columns, e.g. `2x3`
+    pub fn shape(&self) -> String {
+        format!("{}x{}", self.rows, self.cols)
+    }
+
+    /// Bit length of the largest element
+    pub fn bits(&self) -> u64 {
+        self.elements.iter().map(BigInt::bits).max().unwrap_or(0)
+    }
+
+    /// Applies the function to every element
+    pub fn map<F: FnMut(&BigInt) -> BigInt>(&self, f: F) -> Matrix {
+        Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            elements: self.elements.iter().map(f).collect(),
+        }
+    }
+
+    /// Applies the function to every element, stops at the first error
+    pub fn try_map<F>(&self, f: F) -> Result<Matrix, CalcError>
+    where
+        F: FnMut(&BigInt) -> Result<BigInt, CalcError>,
+    {
+        Ok(Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            elements: self.elements.iter().map(f).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Applies the operation to the elements at the same positions, both matrices must have
+    /// the same shape
+    pub fn zip(
+        &self,
+        other: &Matrix,
+        operation: &str,
+        f: fn(&BigInt, &BigInt) -> BigInt,
+    ) -> Result<Matrix, CalcError> {
+        if (self.rows, self.cols) != (other.rows, other.cols) {
+            return Err(self.incompatible(other, operation));
+        }
+        Ok(Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            elements: (self.elements.iter().zip(&other.elements))
+                .map(|(a, b)| f(a, b))
+                .collect(),
+        })
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        let elements = (0..self.cols)
+            .flat_map(|j| (0..self.rows).map(move |i| (i, j)))
+            .map(|(i, j)| self.elements[i * self.cols + j].clone())
+            .collect();
+        Matrix {
+            rows: self.cols,
+            cols: self.rows,
+            elements,
+        }
+    }
+
+    /// Matrix product, the number of columns of `self` must be the number of rows of `other`
+    pub fn mul(&self, other: &Matrix, ctx: &Evaluation) -> Result<Matrix, CalcError> {
+        if self.cols != other.rows {
+            return Err(self.incompatible(other, "*"));
+        }
+
+        let mut elements = Vec::with_capacity(self.rows * other.cols);
+        for i in 0..self.rows {
+            for j in 0..other.cols {
+                ctx.step()?;
+                let element = self
+                    .row(i)
+                    .iter()
+                    .enumerate()
+                    .map(|(k, a)| a * &other.elements[k * other.cols + j])
+                    .sum();
+                elements.push(element);
+            }
+        }
+        Ok(Matrix {
+            rows: self.rows,
+            cols: other.cols,
+            elements,
+        })
+    }
+
+    pub fn det(&self, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+        self.check_square("det")?;
+        let mut rows = self.to_rows();
+        Ok(eliminate(&mut rows, false, ctx)?.unwrap_or_else(BigInt::zero))
+    }
+
+    /// Exact inverse. In modular mode the determinant must be invertible modulo the modulus,
+    /// otherwise it must be 1 or -1 so the inverse has integer elements.
+    pub fn inverse(&self, modulus: Option<&BigInt>, ctx: &Evaluation) -> Result<Matrix, CalcError> {
+        self.check_square("inverse")?;
+        let n = self.rows;
+
+        // Eliminating `[A | I]` leaves `[d*I | d*A^-1]`, with `d` the determinant up to the sign
+        let mut rows = self.to_rows();
+        for (i, row) in rows.iter_mut().enumerate() {
+            row.extend((0..n).map(|j| BigInt::from(u8::from(i == j))));
+        }
+        let Some(det) = eliminate(&mut rows, true, ctx)? else {
+            return Err(CalcError::SingularMatrix);
+        };
+        let d = &rows[n - 1][n - 1];
+
+        let factor = match modulus {
+            Some(modulus) => number_theory::mod_inverse(d, modulus)
+                .map_err(|_| CalcError::NoModularInverse(det.into(), modulus.clone()))?,
+            // 1/d is d itself
+            None if d.abs().is_one() => d.clone(),
+            None => {
+                return Err(CalcError::InvalidArgument(
+                    String::from("inverse"),
+                    format!("the determinant {} is not 1 or -1", det),
+                ))
+            }
+        };
+        let elements = rows
+            .iter()
+            .flat_map(|row| &row[n..])
+            .map(|element| element * &factor)
+            .collect();
+        Ok(Matrix {
+            rows: n,
+            cols: n,
+            elements,
+        })
+    }
+
+    fn to_rows(&self) -> Vec<Vec<BigInt>> {
+        (0..self.rows).map(|i| self.row(i).to_vec()).collect()
+    }
+
+    fn check_square(&self, function: &str) -> Result<(), CalcError> {
+        if self.rows == self.cols && self.rows > 0 {
+            Ok(())
+        } else {
+            Err(CalcError::InvalidArgument(
+                function.to_string(),
+                format!("a {} matrix is not square", self.shape()),
+            ))
+        }
+    }
+
+    fn incompatible(&self, other: &Matrix, operation: &str) -> CalcError {
+        CalcError::IncompatibleMatrices(operation.to_string(), self.shape(), other.shape())
+    }
+}
+
+/// Fraction-free (Bareiss) elimination of the first `rows.len()` columns, every division is exact.
+/// Returns the determinant of those columns, or `None` if it is zero.
+/// Only the rows below the pivots are eliminated, unless `jordan` is set. Then all pivots end up
+/// equal to the last one, the determinant up to the sign of the row swaps.
+fn eliminate(
+    rows: &mut [Vec<BigInt>],
+    jordan: bool,
+    ctx: &Evaluation,
+) -> Result<Option<BigInt>, CalcError> {
+    let n = rows.len();
+    let mut previous = BigInt::one();
+    let mut negated = false;
+
+    for k in 0..n {
+        let Some(pivot_row) = (k..n).find(|&i| !rows[i][k].is_zero()) else {
+            return Ok(None);
+        };
+        if pivot_row != k {
+            rows.swap(k, pivot_row);
+            negated = !negated;
+        }
+
+        let pivot_row = rows[k].clone();
+        let pivot = &pivot_row[k];
+        for (i, row) in rows.iter_mut().enumerate() {
+            if i == k || (i < k && !jordan) {
+                continue;
+            }
+            ctx.step()?;
+            let factor = row[k].clone();
+            for (element, p) in row.iter_mut().zip(&pivot_row) {
+                *element = (pivot * &*element - &factor * p) / &previous;
+            }
+        }
+        previous = pivot.clone();
+    }
+
+    Ok(Some(if negated { -previous } else { previous }))
+}
+
+/// Written as a literal, e.g. `[1, 2; 3, 4]`
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<String> = (0..self.rows)
+            .map(|i| {
+                let row: Vec<String> = self.row(i).iter().map(ToString::to_string).collect();
+                row.join(", ")
+            })
+            .collect();
+        write!(f, "[{}]", rows.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_integer::Integer;
+
+    use super::*;
+    use crate::evaluator::tests::empty_evaluation;
+
+    fn matrix(rows: &[&[i64]]) -> Matrix {
+        Matrix::from_rows(
+            rows.iter()
+                .map(|row| row.iter().map(|&n| BigInt::from(n)).collect())
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_shape() {
+        let m = matrix(&[&[1, 2, 3], &[4, 5, 6]]);
+        assert_eq!(m.shape(), "2x3");
+        assert_eq!(m.transpose(), matrix(&[&[1, 4], &[2, 5], &[3, 6]]));
+        assert_eq!(m.to_string(), "[1, 2, 3; 4, 5, 6]");
+        assert_eq!(
+            Matrix::from_rows(vec![vec![BigInt::one()], vec![]]),
+            Err(CalcError::MatrixRowLength(2, 0, 1))
+        );
+        assert_eq!(
+            m.zip(&m.transpose(), "+", |a, b| a + b),
+            Err(CalcError::IncompatibleMatrices(
+                String::from("+"),
+                String::from("2x3"),
+                String::from("3x2")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_mul() {
+        let ctx = empty_evaluation();
+
+        let a = matrix(&[&[1, 2, 3], &[4, 5, 6]]);
+        let b = matrix(&[&[1, 0], &[0, 1], &[1, 1]]);
+        assert_eq!(a.mul(&b, &ctx), Ok(matrix(&[&[4, 5], &[10, 11]])));
+        assert!(a.mul(&a, &ctx).is_err());
+    }
+
+    #[test]
+    fn test_det() {
+        let ctx = empty_evaluation();
+
+        assert_eq!(matrix(&[&[7]]).det(&ctx), Ok(BigInt::from(7)));
+        assert_eq!(matrix(&[&[1, 2], &[3, 4]]).det(&ctx), Ok(BigInt::from(-2)));
+        // Needs a row swap
+        assert_eq!(
+            matrix(&[&[0, 1, 2], &[3, 0, 1], &[4, 5, 0]]).det(&ctx),
+            Ok(BigInt::from(34))
+        );
+        assert_eq!(
+            matrix(&[&[1, 2, 3], &[4, 5, 6], &[7, 8, 9]]).det(&ctx),
+            Ok(BigInt::zero())
+        );
+        assert!(matrix(&[&[1, 2]]).det(&ctx).is_err());
+    }
+
+    #[test]
+    fn test_inverse() {
+        let ctx = empty_evaluation();
+
+        let a = matrix(&[&[2, 3, 1], &[1, 2, 1], &[1, 1, 1]]);
+        let inverse = a.inverse(None, &ctx).unwrap();
+        assert_eq!(a.mul(&inverse, &ctx), Ok(Matrix::identity(3)));
+        let a = matrix(&[&[0, 1], &[1, 0]]);
+        assert_eq!(a.inverse(None, &ctx), Ok(a.clone()));
+
+        let a = matrix(&[&[1, 2], &[3, 4]]);
+        assert!(a.inverse(None, &ctx).is_err());
+        let modulus = BigInt::from(7);
+        let inverse = a.inverse(Some(&modulus), &ctx).unwrap();
+        let product = a.mul(&inverse, &ctx).unwrap();
+        assert_eq!(product.map(|n| n.mod_floor(&modulus)), Matrix::identity(2));
+        // Needs row swaps
+        let a = matrix(&[&[0, 2, 5, 1], &[3, 0, 1, 4], &[1, 1, 0, 2], &[7, 3, 2, 0]]);
+        let modulus = BigInt::from(101);
+        let inverse = a.inverse(Some(&modulus), &ctx).unwrap();
+        let product = a.mul(&inverse, &ctx).unwrap();
+        assert_eq!(product.map(|n| n.mod_floor(&modulus)), Matrix::identity(4));
+        // The determinant -2 is not invertible
+        assert!(a.inverse(Some(&BigInt::from(8)), &ctx).is_err());
+        assert_eq!(
+            matrix(&[&[1, 2], &[2, 4]]).inverse(None, &ctx),
+            Err(CalcError::SingularMatrix)
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+
+use crate::{error::CalcError, evaluator::Evaluation, random::splitmix64};
+
+/// Testing these bases makes Miller–Rabin deterministic for every number below 2^64
+const DETERMINISTIC_BASES: [u32; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// Number of additional pseudo-random bases tested for numbers above 2^64.
+/// A composite passes a single base with a probability of at most 1/4.
+const EXTRA_ROUNDS: usize = 20;
+
+/// Factors below this are found by trial division before trying Pollard's rho
+const TRIAL_DIVISION_LIMIT: u32 = 1 << 12;
+
+/// Number of Pollard's rho iterations between the gcd calculations
+const RHO_BATCH: usize = 64;
+
+/// Miller–Rabin primality test, exact below 2^64 and probabilistic above
+pub fn is_prime(n: &BigInt, ctx: &Evaluation) -> Result<bool, CalcError> {
+    if n < &BigInt::from(2) {
+        return Ok(false);
+    }
+    for p in DETERMINISTIC_BASES {
+        if n == &BigInt::from(p) {
+            return Ok(true);
+        }
+        if (n % p).is_zero() {
+            return Ok(false);
+        }
+    }
+
+    // n - 1 = d * 2^s with odd d
+    let n_minus_one: BigInt = n - 1;
+    let s = n_minus_one.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_one >> s;
+
+    let mut bases: Vec<BigInt> = DETERMINISTIC_BASES.iter().map(|&p| p.into()).collect();
+    if n.bits() > 64 {
+        // Derived from the number itself, so the result is the same every time
+        let mut state = n.magnitude().iter_u64_digits().next().unwrap_or(0);
+        let range = n - 3;
+        bases.extend((0..EXTRA_ROUNDS).map(|_| BigInt::from(splitmix64(&mut state)) % &range + 2));
+    }
+
+    for base in bases {
+        ctx.step()?;
+        if !is_strong_probable_prime(n, &base, &d, s, &n_minus_one) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn is_strong_probable_prime(
+    n: &BigInt,
+    base: &BigInt,
+    d: &BigInt,
+    s: u64,
+    n_minus_one: &BigInt,
+) -> bool {
+    let mut x = base.modpow(d, n);
+    if x.is_one() || &x == n_minus_one {
+        return true;
+    }
+    for _ in 1..s {
+        x = &x * &x % n;
+        if &x == n_minus_one {
+            return true;
+        }
+    }
+    false
+}
+
+/// Smallest prime larger than n
+pub fn next_prime(n: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    if n < &BigInt::from(2) {
+        return Ok(BigInt::from(2));
+    }
+    let mut candidate: BigInt = n + 1;
+    if candidate.is_even() && candidate != BigInt::from(2) {
+        candidate += 1;
+    }
+    while !is_prime(&candidate, ctx)? {
+        ctx.step()?;
+        candidate += 2;
+    }
+    Ok(candidate)
+}
+
+/// Largest prime smaller than n, `None` if there is none
+pub fn prev_prime(n: &BigInt, ctx: &Evaluation) -> Result<Option<BigInt>, CalcError> {
+    if n <= &BigInt::from(2) {
+        return Ok(None);
+    }
+    if n == &BigInt::from(3) {
+        return Ok(Some(BigInt::from(2)));
+    }
+    let mut candidate: BigInt = n - 1;
+    if candidate.is_even() {
+        candidate -= 1;
+    }
+    while !is_prime(&candidate, ctx)? {
+        ctx.step()?;
+        candidate -= 2;
+    }
+    Ok(Some(candidate))
+}
+
+/// Prime factors of |n| with their exponents, in increasing order. n must not be zero.
+pub fn factorize(n: &BigInt, ctx: &Evaluation) -> Result<Vec<(BigInt, u32)>, CalcError> {
+    let mut n = n.abs();
+    let mut factors = vec![];
+
+    let mut p = 2u32;
+    while p < TRIAL_DIVISION_LIMIT && BigInt::from(p) * p <= n {
+        ctx.step()?;
+        let mut exponent = 0;
+        while (&n % p).is_zero() {
+            n /= p;
+            exponent += 1;
+        }
+        if exponent > 0 {
+            factors.push((BigInt::from(p), exponent));
+        }
+        p = if p == 2 { 3 } else { p + 2 };
+    }
+
+    // What is left has no small factors, split it until only primes remain
+    let mut large = vec![];
+    let mut pending = vec![n];
+    while let Some(m) = pending.pop() {
+        if m.is_one() {
+            continue;
+        }
+        if is_prime(&m, ctx)? {
+            large.push(m);
+            continue;
+        }
+        let divisor = pollard_rho(&m, ctx)?;
+        pending.push(&m / &divisor);
+        pending.push(divisor);
+    }
+
+    large.sort();
+    for prime in large {
+        match factors.last_mut() {
+            Some((last, exponent)) if *last == prime => *exponent += 1,
+            _ => factors.push((prime, 1)),
+        }
+    }
+    Ok(factors)
+}
+
+/// Finds a non-trivial divisor of a composite number without small factors
+fn pollard_rho(n: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let mut c = BigInt::zero();
+    'next_polynomial: loop {
+        c += 1;
+        let f = |x: &BigInt| (x * x + &c) % n;
+        let (mut x, mut y) = (BigInt::from(2), BigInt::from(2));
+
+        loop {
+            ctx.step()?;
+            // Multiply the differences to calculate the gcd only once per batch
+            let (batch_x, batch_y) = (x.clone(), y.clone());
+            let mut product = BigInt::one();
+            for _ in 0..RHO_BATCH {
+                x = f(&x);
+                y = f(&f(&y));
+                product = product * (&x - &y).abs() % n;
+            }
+
+            let divisor = product.gcd(n);
+            if divisor.is_one() {
+                continue;
+            }
+            if &divisor != n {
+                return Ok(divisor);
+            }
+
+            // The batch went past the divisor, repeat it one iteration at a time
+            let (mut x, mut y) = (batch_x, batch_y);
+            loop {
+                x = f(&x);
+                y = f(&f(&y));
+                let divisor = (&x - &y).abs().gcd(n);
+                if &divisor == n {
+                    continue 'next_polynomial;
+                }
+                if !divisor.is_one() {
+                    return Ok(divisor);
+                }
+            }
+        }
+    }
+}
+
+/// Number of integers in 1..=n which are coprime to n. n must be positive.
+pub fn totient(n: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    Ok(factorize(n, ctx)?
+        .into_iter()
+        .map(|(p, exponent)| (&p - 1) * p.pow(exponent - 1))
+        .product())
+}
+
+/// Jacobi symbol (a/n). n must be positive and odd.
+pub fn jacobi(a: &BigInt, n: &BigInt) -> i32 {
+    let mut a = a.mod_floor(n);
+    let mut n = n.clone();
+    let mut result = 1;
+
+    while !a.is_zero() {
+        let twos = a.trailing_zeros().unwrap_or(0);
+        a >>= twos;
+        // (2/n) is -1 when n is 3 or 5 mod 8
+        let n_mod_8 = &n % 8u32;
+        if twos % 2 == 1 && (n_mod_8 == BigInt::from(3) || n_mod_8 == BigInt::from(5)) {
+            result = -result;
+        }
+
+        // Quadratic reciprocity, the sign changes when both are 3 mod 4
+        std::mem::swap(&mut a, &mut n);
+        if &a % 4u32 == BigInt::from(3) && &n % 4u32 == BigInt::from(3) {
+            result = -result;
+        }
+        a = a.mod_floor(&n);
+    }
+
+    if n.is_one() {
+        result
+    } else {
+        0
+    }
+}
+
+/// Returns (g, x, y) with a*x + b*y = g, where g is the non-negative gcd of a and b
+pub fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_x, mut x) = (BigInt::one(), BigInt::zero());
+    let (mut old_y, mut y) = (BigInt::zero(), BigInt::one());
+
+    while !r.is_zero() {
+        let quotient = &old_r / &r;
+        let next_r = &old_r - &quotient * &r;
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_x = &old_x - &quotient * &x;
+        old_x = std::mem::replace(&mut x, next_x);
+        let next_y = &old_y - &quotient * &y;
+        old_y = std::mem::replace(&mut y, next_y);
+    }
+
+    if old_r.is_negative() {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// Inverse of a modulo n in the range 0..|n|
+pub fn mod_inverse(a: &BigInt, n: &BigInt) -> Result<BigInt, CalcError> {
+    let modulus = n.abs();
+    if modulus.is_zero() {
+        return Err(CalcError::DivisionByZero(a.clone().into()));
+    }
+    let (gcd, x, _) = extended_gcd(&a.mod_floor(&modulus), &modulus);
+    if !gcd.is_one() {
+        return Err(CalcError::NoModularInverse(a.clone().into(), n.clone()));
+    }
+    Ok(x.mod_floor(&modulus))
+}
+
+/// Smallest non-negative x with x = a (mod n) for every (a, n) pair, `None` if there is none.
+/// The moduli must be positive, but don't have to be coprime.
+pub fn crt(congruences: &[(&BigInt, &BigInt)]) -> Option<BigInt> {
+    let mut x = BigInt::zero();
+    let mut modulus = BigInt::one();
+
+    for &(a, n) in congruences {
+        // Solve x + modulus * k = a (mod n) for k
+        let (gcd, inverse, _) = extended_gcd(&modulus, n);
+        let difference = a - &x;
+        if !(&difference % &gcd).is_zero() {
+            return None;
+        }
+        let n_reduced = n / &gcd;
+        let k = (difference / &gcd * inverse).mod_floor(&n_reduced);
+        x += &modulus * k;
+        modulus *= n_reduced;
+    }
+
+    Some(x.mod_floor(&modulus))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::tests::empty_evaluation;
+
+    fn is_prime_u64(n: u64) -> bool {
+        is_prime(&BigInt::from(n), &empty_evaluation()).unwrap()
+    }
+
+    #[test]
+    fn test_is_prime_small() {
+        let primes: Vec<u64> = (0..100).filter(|&n| is_prime_u64(n)).collect();
+        assert_eq!(
+            primes,
+            [
+                2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79,
+                83, 89, 97
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_prime_pseudoprimes() {
+        // Strong pseudoprimes to several of the first prime bases
+        for n in [
+            3215031751u128,
+            3825123056546413051,
+            318665857834031151167461,
+        ] {
+            let ctx = empty_evaluation();
+            assert_eq!(is_prime(&BigInt::from(n), &ctx), Ok(false));
+        }
+        assert!(is_prime_u64(18446744073709551557));
+    }
+
+    #[test]
+    fn test_factorize() {
+        let ctx = empty_evaluation();
+        // 2^3 * 3^2 * 5 * 1000003^2 * 4294967311
+        let n = BigInt::from(360u32) * BigInt::from(1000003u32).pow(2) * 4294967311u64;
+        assert_eq!(
+            factorize(&-n, &ctx),
+            Ok(vec![
+                (BigInt::from(2), 3),
+                (BigInt::from(3), 2),
+                (BigInt::from(5), 1),
+                (BigInt::from(1000003), 2),
+                (BigInt::from(4294967311u64), 1),
+            ])
+        );
+        assert_eq!(factorize(&BigInt::one(), &ctx), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_jacobi() {
+        // (a/15) for a in 0..15
+        let expected = [0, 1, 1, 0, 1, 0, 0, -1, 1, 0, 0, -1, 0, -1, -1];
+        for (a, symbol) in expected.iter().enumerate() {
+            assert_eq!(jacobi(&BigInt::from(a), &BigInt::from(15)), *symbol);
+        }
+        assert_eq!(jacobi(&BigInt::from(-1), &BigInt::from(7)), -1);
+        assert_eq!(jacobi(&BigInt::from(5), &BigInt::from(1)), 1);
+    }
+
+    #[test]
+    fn test_extended_gcd() {
+        let (a, b) = (BigInt::from(240), BigInt::from(-46));
+        let (gcd, x, y) = extended_gcd(&a, &b);
+        assert_eq!(gcd, BigInt::from(2));
+        assert_eq!(a * x + b * y, gcd);
+    }
+
+    #[test]
+    fn test_crt() {
+        let congruences = [(2, 3), (3, 5), (2, 7)].map(|(a, n)| (BigInt::from(a), BigInt::from(n)));
+        let pairs: Vec<_> = congruences.iter().map(|(a, n)| (a, n)).collect();
+        assert_eq!(crt(&pairs), Some(BigInt::from(23)));
+
+        // Moduli which are not coprime
+        let (a, b, n, m) = (
+            BigInt::from(3),
+            BigInt::from(2),
+            BigInt::from(4),
+            BigInt::from(6),
+        );
+        assert_eq!(crt(&[(&a, &n), (&b, &m)]), None);
+        let b = BigInt::from(5);
+        assert_eq!(crt(&[(&a, &n), (&b, &m)]), Some(BigInt::from(11)));
+    }
+}
@@ -1,5 +1,8 @@
-use crate::{error::CalcError, tokenizer::Token};
-use num_bigint::BigUint;
+use crate::{
+    error::CalcError, function::UserFunction, ip::Network, tokenizer::Token, value::Value,
+};
+use num_bigint::BigInt;
+use num_rational::BigRational;
 
 // This must not be an alphanumeric value in order to keep the parsing logic simple
 pub const RES_VAR: char = '$';
@@ -17,6 +20,10 @@ pub enum ExprBitwise {
     BitwiseXor(Box<ExprBitwise>, Expr),
     BitshiftLeft(Box<ExprBitwise>, Expr),
     BitshiftRight(Box<ExprBitwise>, Expr),
+    RotateLeft(Box<ExprBitwise>, Expr),
+    RotateRight(Box<ExprBitwise>, Expr),
+    /// Conversion to a unit given as the unit names with their exponents, e.g. `x in KiB/s`
+    Convert(Box<ExprBitwise>, Vec<(String, i32)>),
     Expr(Expr),
 }
 
@@ -38,9 +45,25 @@ pub enum Term {
 
 #[derive(Debug, PartialEq)]
 pub enum Factor {
-    Number(BigUint),
+    Number(Value),
+    /// Imaginary literal, e.g. `4i`
+    Imaginary(BigInt),
+    /// A number with a fraction, e.g. `3.14`
+    Decimal(BigRational),
+    /// Numbers with units which are added, e.g. `4 KiB` or `3d 4h`
+    Quantity(Vec<(BigRational, String)>),
+    /// Nanoseconds since the Unix epoch of a date literal
+    DateTime(BigInt),
+    /// A network literal like `10.0.0.0/8`
+    Network(Network),
     Variable(String),
-    Parenthesis(Box<Expr>),
+    Parenthesis(Box<ExprBitwise>),
+    /// Rows of a matrix literal, e.g. `[1, 2; 3, 4]`
+    Matrix(Vec<Vec<ExprBitwise>>),
+    Call(String, Vec<ExprBitwise>),
+    Power(Box<Factor>, Box<Factor>),
+    Factorial(Box<Factor>),
+    DoubleFactorial(Box<Factor>),
 }
 
 pub fn parse_assignment(tokens: &[Token]) -> Result<Assign, CalcError> {
@@ -61,161 +84,360 @@ pub fn parse_assignment(tokens: &[Token]) -> Result<Assign, CalcError> {
     Ok(Assign::ExprBitwise(parse_bitwise_expr(tokens)?))
 }
 
-fn parse_bitwise_expr(tokens: &[Token]) -> Result<ExprBitwise, CalcError> {
-    let it = tokens.iter().enumerate().peekable();
-
-    for (index, token) in it {
-        match token {
-            Token::BitwiseAnd => {
-                return Ok(ExprBitwise::BitwiseAnd(
-                    Box::new(parse_bitwise_expr(&tokens[0..index])?),
-                    parse_expr(&tokens[index + 1..])?,
-                ))
-            }
-            Token::BitwiseOr => {
-                return Ok(ExprBitwise::BitwiseOr(
-                    Box::new(parse_bitwise_expr(&tokens[0..index])?),
-                    parse_expr(&tokens[index + 1..])?,
-                ))
-            }
-            Token::BitwiseXor => {
-                return Ok(ExprBitwise::BitwiseXor(
-                    Box::new(parse_bitwise_expr(&tokens[0..index])?),
-                    parse_expr(&tokens[index + 1..])?,
-                ))
-            }
-            Token::BitshiftRight => {
-                return Ok(ExprBitwise::BitshiftRight(
-                    Box::new(parse_bitwise_expr(&tokens[0..index])?),
-                    parse_expr(&tokens[index + 1..])?,
-                ))
-            }
-            Token::BitshiftLeft => {
-                return Ok(ExprBitwise::BitshiftLeft(
-                    Box::new(parse_bitwise_expr(&tokens[0..index])?),
-                    parse_expr(&tokens[index + 1..])?,
-                ))
-            }
-            _ => continue,
+/// Parses a function definition like `f(x, y) = x**2 + y`.
+/// Returns `None` if the tokens are not a definition.
+pub fn parse_function_definition(
+    tokens: &[Token],
+) -> Result<Option<(String, UserFunction)>, CalcError> {
+    let (Some(Token::Function(name)), Some(equals)) = (
+        tokens.first(),
+        tokens.iter().position(|token| token == &Token::Equals),
+    ) else {
+        return Ok(None);
+    };
+
+    // The parameters are single names in the parentheses before the equals sign
+    let (Some(Token::LeftPar), Some(Token::RightPar)) = (tokens.get(1), tokens.get(equals - 1))
+    else {
+        return Err(CalcError::InvalidExpression);
+    };
+    let mut params: Vec<String> = vec![];
+    for param in split_arguments(tokens.get(2..equals - 1).unwrap_or_default())? {
+        match param {
+            [Token::Variable(param)] if !params.contains(param) => params.push(param.clone()),
+            _ => return Err(CalcError::InvalidExpression),
         }
     }
 
-    // matched nothing so must be a normal expression
-    Ok(ExprBitwise::Expr(parse_expr(tokens)?))
+    let body = parse_bitwise_expr(&tokens[equals + 1..])?;
+    Ok(Some((name.clone(), UserFunction { params, body })))
+}
+
+// Binary operators are searched from the end of the expression and the expression is split at the
+// last one, outside of any parentheses. This keeps them left associative, `1-2-3` is `(1-2)-3`.
+
+fn parse_bitwise_expr(tokens: &[Token]) -> Result<ExprBitwise, CalcError> {
+    // A conversion applies to the whole expression before it, `1/16MHz in ns`
+    if let Some(index) = find_last_operator(tokens, |_, token| token == &Token::In)? {
+        return Ok(ExprBitwise::Convert(
+            Box::new(parse_bitwise_expr(&tokens[..index])?),
+            parse_unit(&tokens[index + 1..])?,
+        ));
+    }
+
+    let operator = find_last_operator(tokens, |_, token| {
+        matches!(
+            token,
+            Token::BitwiseAnd
+                | Token::BitwiseOr
+                | Token::BitwiseXor
+                | Token::BitshiftRight
+                | Token::BitshiftLeft
+                | Token::RotateRight
+                | Token::RotateLeft
+        )
+    })?;
+
+    let Some(index) = operator else {
+        // matched nothing so must be a normal expression
+        return Ok(ExprBitwise::Expr(parse_expr(tokens)?));
+    };
+
+    let lhs = Box::new(parse_bitwise_expr(&tokens[0..index])?);
+    let rhs = parse_expr(&tokens[index + 1..])?;
+    Ok(match tokens[index] {
+        Token::BitwiseAnd => ExprBitwise::BitwiseAnd(lhs, rhs),
+        Token::BitwiseOr => ExprBitwise::BitwiseOr(lhs, rhs),
+        Token::BitwiseXor => ExprBitwise::BitwiseXor(lhs, rhs),
+        Token::BitshiftRight => ExprBitwise::BitshiftRight(lhs, rhs),
+        Token::BitshiftLeft => ExprBitwise::BitshiftLeft(lhs, rhs),
+        Token::RotateRight => ExprBitwise::RotateRight(lhs, rhs),
+        Token::RotateLeft => ExprBitwise::RotateLeft(lhs, rhs),
+        _ => panic!(), // Cannot happen as checked above
+    })
 }
 
 fn parse_expr(tokens: &[Token]) -> Result<Expr, CalcError> {
-    let mut it = tokens.iter().enumerate().peekable();
+    let operator = find_last_operator(tokens, |index, token| match token {
+        Token::Plus => true,
+        // A minus which doesn't follow an operand is a negative, not a subtraction
+        Token::Minus => index > 0 && ends_operand(&tokens[index - 1]),
+        _ => false,
+    })?;
+
+    if let Some(index) = operator {
+        let lhs = Box::from(parse_expr(&tokens[0..index])?);
+        let rhs = parse_term(&tokens[index + 1..])?;
+        return Ok(match tokens[index] {
+            Token::Plus => Expr::Sum(lhs, rhs),
+            _ => Expr::Subtract(lhs, rhs),
+        });
+    }
 
     // If the first token is a minus, this is a negative
-    if let Some((_, &Token::Minus)) = it.peek() {
+    if let Some(Token::Minus) = tokens.first() {
         // avoid double negative expressions
-        it.next();
-        if let Some((_, &Token::Minus)) = it.peek() {
+        if let Some(Token::Minus) = tokens.get(1) {
             return Err(CalcError::InvalidExpression);
         }
 
         return Ok(Expr::Negative(Box::new(parse_expr(&tokens[1..])?)));
     }
 
-    while let Some((index, token)) = it.next() {
-        match token {
-            Token::Plus => {
-                return Ok(Expr::Sum(
-                    Box::from(parse_expr(&tokens[0..index])?),
-                    parse_term(&tokens[index + 1..])?,
-                ));
-            }
-            Token::Minus if index > 0 => {
-                return Ok(Expr::Subtract(
-                    Box::from(parse_expr(&tokens[0..index])?),
-                    parse_term(&tokens[index + 1..])?,
-                ));
-            }
-            Token::LeftPar => {
-                if !matching_paranthesis(it.by_ref()) {
-                    return Err(CalcError::UnclosedParanthesis);
-                }
-            }
-            _ => continue,
-        }
-    }
-
     // reached the end of the expression without matching -> must be a term
     Ok(Expr::Term(parse_term(tokens)?))
 }
 
 fn parse_term(v: &[Token]) -> Result<Term, CalcError> {
-    let mut it = v.iter().enumerate();
+    let operator = find_last_operator(v, |_, token| {
+        matches!(token, Token::Mult | Token::Div | Token::Modulo)
+    })?;
+
+    let Some(index) = operator else {
+        // reached the end of the expression without matching must be a factor
+        return Ok(Term::Factor(parse_factor(v)?));
+    };
+
+    let lhs = Box::from(parse_term(&v[0..index])?);
+    let rhs = parse_factor(&v[index + 1..])?;
+    Ok(match v[index] {
+        Token::Mult => Term::Mult(lhs, rhs),
+        Token::Div => Term::Div(lhs, rhs),
+        Token::Modulo => Term::Modulo(lhs, rhs),
+        _ => panic!(), // Cannot happen as checked above
+    })
+}
 
-    while let Some((index, token)) = it.next() {
+fn parse_factor(tokens: &[Token]) -> Result<Factor, CalcError> {
+    // Powers are split at the first `**`, so they are right associative, `2**3**2` is `2**(3**2)`
+    let mut depth = 0;
+    let power = tokens.iter().position(|token| {
         match token {
-            Token::Mult | Token::Div | Token::Modulo => {
-                let lhs = Box::from(parse_term(&v[0..index])?);
-                let rhs = parse_factor(&v[index + 1..])?;
-                return Ok(match token {
-                    Token::Mult => Term::Mult(lhs, rhs),
-                    Token::Div => Term::Div(lhs, rhs),
-                    Token::Modulo => Term::Modulo(lhs, rhs),
-                    _ => panic!(), // Cannot happen as checked above
-                });
-            }
-            Token::LeftPar => {
-                if !matching_paranthesis(it.by_ref()) {
-                    return Err(CalcError::UnclosedParanthesis);
-                }
-            }
-            _ => {
-                continue;
-            }
+            Token::LeftPar | Token::LeftBracket => depth += 1,
+            Token::RightPar | Token::RightBracket => depth -= 1,
+            Token::Power => return depth == 0,
+            _ => {}
         }
+        false
+    });
+    if let Some(index) = power {
+        // A negative exponent needs no parentheses, `2**-1`
+        let exponent = match &tokens[index + 1..] {
+            [Token::Minus, exponent @ ..] => Factor::Parenthesis(Box::new(ExprBitwise::Expr(
+                Expr::Negative(Box::new(Expr::Term(Term::Factor(parse_factor(exponent)?)))),
+            ))),
+            exponent => parse_factor(exponent)?,
+        };
+        return Ok(Factor::Power(
+            Box::new(parse_factor(&tokens[..index])?),
+            Box::new(exponent),
+        ));
     }
 
-    // reached the end of the expression without matching must be a factor
-    Ok(Term::Factor(parse_factor(v)?))
-}
+    // Postfix factorials bind tighter than powers, `2**3!` is `2**(3!)`
+    match tokens.split_last() {
+        Some((Token::Factorial, operand)) => {
+            return Ok(Factor::Factorial(Box::new(parse_factor(operand)?)))
+        }
+        Some((Token::DoubleFactorial, operand)) => {
+            return Ok(Factor::DoubleFactorial(Box::new(parse_factor(operand)?)))
+        }
+        _ => {}
+    }
+
+    // A number followed by a unit, or several of them like `3d 4h`
+    if tokens.len() >= 2 && tokens.len().is_multiple_of(2) {
+        let parts = tokens
+            .chunks(2)
+            .map(|pair| match pair {
+                [Token::Number(n), Token::Unit(unit)] => Some((
+                    BigRational::from_integer(BigInt::from(n.clone())),
+                    unit.clone(),
+                )),
+                [Token::Decimal(n), Token::Unit(unit)] => Some((n.clone(), unit.clone())),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>();
+        if let Some(parts) = parts {
+            return Ok(Factor::Quantity(parts));
+        }
+    }
 
-fn parse_factor(tokens: &[Token]) -> Result<Factor, CalcError> {
     let mut it = tokens.iter();
 
     match &mut it.next() {
-        Some(Token::Number(n)) if it.next().is_none() => Ok(Factor::Number(n.clone())),
+        Some(Token::Number(n)) if it.next().is_none() => {
+            Ok(Factor::Number(Value::Int(BigInt::from(n.clone()))))
+        }
+        Some(Token::Imaginary(n)) if it.next().is_none() => {
+            Ok(Factor::Imaginary(BigInt::from(n.clone())))
+        }
+        Some(Token::Decimal(n)) if it.next().is_none() => Ok(Factor::Decimal(n.clone())),
+        Some(Token::DateTime(nanos)) if it.next().is_none() => Ok(Factor::DateTime(nanos.clone())),
+        Some(Token::Network(network)) if it.next().is_none() => {
+            Ok(Factor::Network(network.clone()))
+        }
         Some(Token::Variable(var)) if it.next().is_none() => Ok(Factor::Variable(var.to_string())),
         Some(Token::ResultVariable) if it.next().is_none() => {
             Ok(Factor::Variable(RES_VAR.to_string()))
         }
         Some(Token::LeftPar) => {
             if let Some(Token::RightPar) = it.last() {
-                Ok(Factor::Parenthesis(Box::from(parse_expr(
+                Ok(Factor::Parenthesis(Box::from(parse_bitwise_expr(
                     &tokens[1..tokens.len() - 1],
                 )?)))
             } else {
                 Err(CalcError::UnclosedParanthesis)
             }
         }
+        Some(Token::LeftBracket) => {
+            let Some(Token::RightBracket) = it.last() else {
+                return Err(CalcError::UnclosedBracket);
+            };
+            let rows = split_rows(&tokens[1..tokens.len() - 1])?
+                .into_iter()
+                .map(|row| match split_arguments(row)?.as_slice() {
+                    // An empty row, e.g. in `[1; ]`
+                    [] => Err(CalcError::InvalidExpression),
+                    elements => elements.iter().map(|e| parse_bitwise_expr(e)).collect(),
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(Factor::Matrix(rows))
+        }
+        Some(Token::Function(name)) => {
+            // The arguments must follow the function name in parentheses
+            let (Some(Token::LeftPar), Some(Token::RightPar)) = (it.next(), it.last()) else {
+                return Err(CalcError::UnclosedParanthesis);
+            };
+            let args = split_arguments(&tokens[2..tokens.len() - 1])?
+                .into_iter()
+                .map(parse_bitwise_expr)
+                .collect::<Result<_, _>>()?;
+            Ok(Factor::Call(name.to_string(), args))
+        }
         _ => Err(CalcError::InvalidExpression),
     }
 }
 
-/// iterate until the matching right parenthesis
-fn matching_paranthesis(it: &mut dyn Iterator<Item = (usize, &Token)>) -> bool {
-    let mut left_count = 1;
-    for (_, t) in it {
-        left_count += match t {
-            Token::LeftPar => 1,
-            Token::RightPar => -1,
-            _ => 0,
-        };
-        if left_count == 0 {
-            break;
+/// Parses the target of a conversion, units separated by `*` or `/`, e.g. `KiB/s`
+fn parse_unit(tokens: &[Token]) -> Result<Vec<(String, i32)>, CalcError> {
+    if tokens.len().is_multiple_of(2) {
+        return Err(CalcError::InvalidExpression);
+    }
+
+    let mut units = vec![];
+    let mut exponent = 1;
+    for (index, token) in tokens.iter().enumerate() {
+        match (index % 2, token) {
+            (0, Token::Unit(unit)) => units.push((unit.clone(), exponent)),
+            (1, Token::Mult) => exponent = 1,
+            (1, Token::Div) => exponent = -1,
+            _ => return Err(CalcError::InvalidExpression),
+        }
+    }
+    Ok(units)
+}
+
+/// Parses the comma separated expressions given as the arguments of a command
+pub fn parse_arguments(tokens: &[Token]) -> Result<Vec<ExprBitwise>, CalcError> {
+    split_arguments(tokens)?
+        .into_iter()
+        .map(parse_bitwise_expr)
+        .collect()
+}
+
+/// Parses the comma separated `name = expr` pairs given as the arguments of a command
+pub fn parse_field_assignments(tokens: &[Token]) -> Result<Vec<(String, ExprBitwise)>, CalcError> {
+    split_arguments(tokens)?
+        .into_iter()
+        .map(|tokens| match parse_assignment(tokens)? {
+            Assign::Assign(name, expr) => Ok((name, expr)),
+            Assign::ExprBitwise(_) => Err(CalcError::InvalidExpression),
+        })
+        .collect()
+}
+
+/// Index of the last operator accepted by the predicate which is not inside parentheses
+fn find_last_operator<F>(tokens: &[Token], is_operator: F) -> Result<Option<usize>, CalcError>
+where
+    F: Fn(usize, &Token) -> bool,
+{
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().rev() {
+        match token {
+            Token::RightPar | Token::RightBracket => depth += 1,
+            // a left parenthesis which was never closed
+            Token::LeftPar if depth == 0 => return Err(CalcError::UnclosedParanthesis),
+            Token::LeftBracket if depth == 0 => return Err(CalcError::UnclosedBracket),
+            Token::LeftPar | Token::LeftBracket => depth -= 1,
+            _ if depth == 0 && is_operator(index, token) => return Ok(Some(index)),
+            _ => continue,
+        }
+    }
+    Ok(None)
+}
+
+/// Whether the token can be the last token of an operand
+fn ends_operand(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Number(_)
+            | Token::Imaginary(_)
+            | Token::Decimal(_)
+            | Token::DateTime(_)
+            | Token::Network(_)
+            | Token::Variable(_)
+            | Token::Unit(_)
+            | Token::ResultVariable
+            | Token::RightPar
+            | Token::RightBracket
+            | Token::Factorial
+            | Token::DoubleFactorial
+    )
+}
+
+/// Splits the arguments of a function call at the commas which are not inside parentheses
+fn split_arguments(tokens: &[Token]) -> Result<Vec<&[Token]>, CalcError> {
+    split_at(tokens, &Token::Comma)
+}
+
+/// Splits the rows of a matrix literal at the semicolons which are not inside parentheses
+fn split_rows(tokens: &[Token]) -> Result<Vec<&[Token]>, CalcError> {
+    split_at(tokens, &Token::Semicolon)
+}
+
+fn split_at<'t>(tokens: &'t [Token], separator: &Token) -> Result<Vec<&'t [Token]>, CalcError> {
+    if tokens.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut args = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, token) in tokens.iter().enumerate() {
+        match token {
+            Token::LeftPar | Token::LeftBracket => depth += 1,
+            // the parenthesis after the function name was closed before the end
+            Token::RightPar | Token::RightBracket if depth == 0 => {
+                return Err(CalcError::InvalidExpression)
+            }
+            Token::RightPar | Token::RightBracket => depth -= 1,
+            token if depth == 0 && token == separator => {
+                args.push(&tokens[start..index]);
+                start = index + 1;
+            }
+            _ => continue,
         }
     }
-    left_count == 0
+    args.push(&tokens[start..]);
+
+    Ok(args)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tokenizer::tokenize;
 
     #[test]
     fn test_parser_expr() {
@@ -232,13 +454,15 @@ mod tests {
                 Token::RightPar
             ]),
             Ok(Expr::Sum(
-                Box::from(Expr::Term(Term::Factor(Factor::Number(1usize.into())))),
+                Box::from(Expr::Term(Term::Factor(Factor::Number(
+                    BigInt::from(1usize).into()
+                )))),
                 Term::Mult(
-                    Box::from(Term::Factor(Factor::Number(123usize.into()))),
-                    Factor::Parenthesis(Box::from(Expr::Term(Term::Div(
-                        Box::from(Term::Factor(Factor::Number(12usize.into()))),
-                        Factor::Number(234usize.into())
-                    )))),
+                    Box::from(Term::Factor(Factor::Number(BigInt::from(123usize).into()))),
+                    Factor::Parenthesis(Box::from(ExprBitwise::Expr(Expr::Term(Term::Div(
+                        Box::from(Term::Factor(Factor::Number(BigInt::from(12usize).into()))),
+                        Factor::Number(BigInt::from(234usize).into())
+                    ))))),
                 ),
             ))
         );
@@ -254,7 +478,9 @@ mod tests {
             ]),
             Ok(Assign::Assign(
                 "a".to_string(),
-                ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Number(12usize.into()))))
+                ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Number(
+                    BigInt::from(12usize).into()
+                ))))
             ))
         )
     }
@@ -269,10 +495,12 @@ mod tests {
                 Token::Number(234usize.into()),
                 Token::RightPar
             ]),
-            Ok(Factor::Parenthesis(Box::new(Expr::Term(Term::Div(
-                Box::new(Term::Factor(Factor::Number(12usize.into()))),
-                Factor::Number(234usize.into())
-            )))))
+            Ok(Factor::Parenthesis(Box::new(ExprBitwise::Expr(
+                Expr::Term(Term::Div(
+                    Box::new(Term::Factor(Factor::Number(BigInt::from(12usize).into()))),
+                    Factor::Number(BigInt::from(234usize).into())
+                ))
+            ))))
         )
     }
 
@@ -323,4 +551,302 @@ mod tests {
             Err(CalcError::InvalidExpression)
         )
     }
+
+    #[test]
+    fn test_parser_left_associative() {
+        // 10-2-3 must be (10-2)-3
+        assert_eq!(
+            parse_expr(&[
+                Token::Number(10usize.into()),
+                Token::Minus,
+                Token::Number(2usize.into()),
+                Token::Minus,
+                Token::Number(3usize.into()),
+            ]),
+            Ok(Expr::Subtract(
+                Box::new(Expr::Subtract(
+                    Box::new(Expr::Term(Term::Factor(Factor::Number(
+                        BigInt::from(10usize).into()
+                    )))),
+                    Term::Factor(Factor::Number(BigInt::from(2usize).into()))
+                )),
+                Term::Factor(Factor::Number(BigInt::from(3usize).into()))
+            ))
+        )
+    }
+
+    #[test]
+    fn test_parser_term_left_associative() {
+        // 8/4/2 must be (8/4)/2
+        assert_eq!(
+            parse_term(&[
+                Token::Number(8usize.into()),
+                Token::Div,
+                Token::Number(4usize.into()),
+                Token::Div,
+                Token::Number(2usize.into()),
+            ]),
+            Ok(Term::Div(
+                Box::new(Term::Div(
+                    Box::new(Term::Factor(Factor::Number(BigInt::from(8usize).into()))),
+                    Factor::Number(BigInt::from(4usize).into())
+                )),
+                Factor::Number(BigInt::from(2usize).into())
+            ))
+        )
+    }
+
+    #[test]
+    fn test_parser_bitwise_left_associative() {
+        // 1<<2<<3 must be (1<<2)<<3
+        let number = |n: usize| Expr::Term(Term::Factor(Factor::Number(BigInt::from(n).into())));
+        assert_eq!(
+            parse_bitwise_expr(&[
+                Token::Number(1usize.into()),
+                Token::BitshiftLeft,
+                Token::Number(2usize.into()),
+                Token::BitshiftLeft,
+                Token::Number(3usize.into()),
+            ]),
+            Ok(ExprBitwise::BitshiftLeft(
+                Box::new(ExprBitwise::BitshiftLeft(
+                    Box::new(ExprBitwise::Expr(number(1))),
+                    number(2)
+                )),
+                number(3)
+            ))
+        )
+    }
+
+    #[test]
+    fn test_parser_negative_sum() {
+        // The negative only applies to the first operand
+        assert_eq!(
+            parse_expr(&[
+                Token::Minus,
+                Token::Variable("a".to_string()),
+                Token::Plus,
+                Token::Variable("b".to_string()),
+            ]),
+            Ok(Expr::Sum(
+                Box::new(Expr::Negative(Box::new(Expr::Term(Term::Factor(
+                    Factor::Variable("a".to_string())
+                ))))),
+                Term::Factor(Factor::Variable("b".to_string()))
+            ))
+        )
+    }
+
+    #[test]
+    fn test_parser_power_right_associative() {
+        let number = |n: usize| Box::new(Factor::Number(BigInt::from(n).into()));
+        assert_eq!(
+            parse_factor(&[
+                Token::Number(2usize.into()),
+                Token::Power,
+                Token::Number(3usize.into()),
+                Token::Power,
+                Token::Number(2usize.into()),
+            ]),
+            Ok(Factor::Power(
+                number(2),
+                Box::new(Factor::Power(number(3), number(2)))
+            ))
+        )
+    }
+
+    #[test]
+    fn test_parser_negative_exponent() {
+        let number = |n: usize| Box::new(Factor::Number(BigInt::from(n).into()));
+        assert_eq!(
+            parse_factor(&[
+                Token::Number(2usize.into()),
+                Token::Power,
+                Token::Minus,
+                Token::Number(1usize.into()),
+            ]),
+            Ok(Factor::Power(
+                number(2),
+                Box::new(Factor::Parenthesis(Box::new(ExprBitwise::Expr(
+                    Expr::Negative(Box::new(Expr::Term(Term::Factor(*number(1)))))
+                ))))
+            ))
+        )
+    }
+
+    #[test]
+    fn test_parser_factorial() {
+        let number = |n: usize| Box::new(Factor::Number(BigInt::from(n).into()));
+        assert_eq!(
+            parse_expr(&[
+                Token::Number(2usize.into()),
+                Token::Power,
+                Token::Number(3usize.into()),
+                Token::Factorial,
+                Token::Minus,
+                Token::Number(1usize.into()),
+            ]),
+            Ok(Expr::Subtract(
+                Box::new(Expr::Term(Term::Factor(Factor::Power(
+                    number(2),
+                    Box::new(Factor::Factorial(number(3)))
+                )))),
+                Term::Factor(*number(1))
+            ))
+        )
+    }
+
+    #[test]
+    fn test_parser_call() {
+        assert_eq!(
+            parse_factor(&[
+                Token::Function("mod".to_string()),
+                Token::LeftPar,
+                Token::LeftPar,
+                Token::Variable("a".to_string()),
+                Token::BitwiseOr,
+                Token::Number(1usize.into()),
+                Token::RightPar,
+                Token::Comma,
+                Token::Number(3usize.into()),
+                Token::RightPar,
+            ]),
+            Ok(Factor::Call(
+                "mod".to_string(),
+                vec![
+                    ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Parenthesis(Box::new(
+                        ExprBitwise::BitwiseOr(
+                            Box::new(ExprBitwise::Expr(Expr::Term(Term::Factor(
+                                Factor::Variable("a".to_string())
+                            )))),
+                            Expr::Term(Term::Factor(Factor::Number(BigInt::from(1usize).into())))
+                        )
+                    ))))),
+                    ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Number(
+                        BigInt::from(3usize).into()
+                    )))),
+                ]
+            ))
+        )
+    }
+
+    #[test]
+    fn test_parser_call_without_arguments() {
+        assert_eq!(
+            parse_factor(&[
+                Token::Function("f".to_string()),
+                Token::LeftPar,
+                Token::RightPar,
+            ]),
+            Ok(Factor::Call("f".to_string(), vec![]))
+        )
+    }
+
+    #[test]
+    fn test_parser_call_empty_argument() {
+        assert_eq!(
+            parse_factor(&[
+                Token::Function("f".to_string()),
+                Token::LeftPar,
+                Token::Number(1usize.into()),
+                Token::Comma,
+                Token::RightPar,
+            ]),
+            Err(CalcError::InvalidExpression)
+        )
+    }
+
+    #[test]
+    fn test_parser_unclosed_paranthesis() {
+        assert_eq!(
+            parse_assignment(&[
+                Token::LeftPar,
+                Token::Number(1usize.into()),
+                Token::Plus,
+                Token::Number(2usize.into()),
+            ]),
+            Err(CalcError::UnclosedParanthesis)
+        )
+    }
+
+    #[test]
+    fn test_parser_matrix() {
+        let number = |n: usize| {
+            ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Number(
+                BigInt::from(n).into(),
+            ))))
+        };
+        assert_eq!(
+            parse_factor(&tokenize("[1, 2; 3, (4)]").unwrap()),
+            Ok(Factor::Matrix(vec![
+                vec![number(1), number(2)],
+                vec![
+                    number(3),
+                    ExprBitwise::Expr(Expr::Term(Term::Factor(Factor::Parenthesis(Box::new(
+                        number(4)
+                    )))))
+                ],
+            ]))
+        );
+        assert_eq!(
+            parse_factor(&tokenize("[1, 2; ]").unwrap()),
+            Err(CalcError::InvalidExpression)
+        );
+        assert_eq!(
+            parse_assignment(&tokenize("[1, 2] + [3").unwrap()),
+            Err(CalcError::UnclosedBracket)
+        );
+    }
+
+    #[test]
+    fn test_parser_function_definition() {
+        let var = |name: &str| Factor::Variable(name.to_string());
+        assert_eq!(
+            parse_function_definition(&tokenize("f(x, y) = x * y").unwrap()),
+            Ok(Some((
+                String::from("f"),
+                UserFunction {
+                    params: vec![String::from("x"), String::from("y")],
+                    body: ExprBitwise::Expr(Expr::Term(Term::Mult(
+                        Box::new(Term::Factor(var("x"))),
+                        var("y")
+                    ))),
+                }
+            )))
+        );
+        assert_eq!(
+            parse_function_definition(&tokenize("f(2) + 1").unwrap()),
+            Ok(None)
+        );
+        assert_eq!(
+            parse_function_definition(&tokenize("f(2) = 1").unwrap()),
+            Err(CalcError::InvalidExpression)
+        );
+    }
+
+    #[test]
+    fn test_parser_units() {
+        let quantity =
+            |n: usize, unit: &str| (BigRational::from_integer(n.into()), unit.to_string());
+        assert_eq!(
+            parse_assignment(&tokenize("4 KiB + 1h 2 s to KiB/s").unwrap()),
+            Ok(Assign::ExprBitwise(ExprBitwise::Convert(
+                Box::new(ExprBitwise::Expr(Expr::Sum(
+                    Box::new(Expr::Term(Term::Factor(Factor::Quantity(vec![quantity(
+                        4, "KiB"
+                    )])))),
+                    Term::Factor(Factor::Quantity(vec![quantity(1, "h"), quantity(2, "s")]))
+                ))),
+                vec![(String::from("KiB"), 1), (String::from("s"), -1)]
+            )))
+        );
+        assert_eq!(
+            parse_assignment(&tokenize("1 s in").unwrap()),
+            Err(CalcError::InvalidExpression)
+        );
+        assert_eq!(
+            parse_assignment(&tokenize("1 s in ns ms").unwrap()),
+            Err(CalcError::InvalidExpression)
+        );
+    }
 }
@@ -0,0 +1,192 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Signed, ToPrimitive};
+
+use crate::{error::CalcError, evaluator::Evaluation, number_theory};
+
+pub(crate) fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Deterministic pseudo-random generator (xoshiro256**). The same seed always produces the
+/// same numbers, on every platform. Not suitable for cryptography.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Random {
+    seed: u64,
+    state: [u64; 4],
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        let mut s = seed;
+        Random {
+            seed,
+            state: [
+                splitmix64(&mut s),
+                splitmix64(&mut s),
+                splitmix64(&mut s),
+                splitmix64(&mut s),
+            ],
+        }
+    }
+
+    /// The seed the generator was started with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let res = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        res
+    }
+
+    /// Uniformly distributed in `0..2^bits`
+    pub fn bits(&mut self, bits: u64) -> BigInt {
+        let digits = bits.div_ceil(32);
+        let x = BigUint::new(
+            (0..digits)
+                .map(|_| (self.next_u64() >> 32) as u32)
+                .collect(),
+        );
+        BigInt::from(x >> (digits * 32 - bits))
+    }
+
+    /// Uniformly distributed in `0..n` for a positive `n`
+    fn below(&mut self, n: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+        // Rejection sampling, every try succeeds with a probability above 1/2
+        let bits = (n - 1u32).bits();
+        loop {
+            ctx.step()?;
+            let x = self.bits(bits);
+            if &x < n {
+                return Ok(x);
+            }
+        }
+    }
+}
+
+/// Seeded from the current time, the seed can be read back to repeat the numbers
+impl Default for Random {
+    fn default() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        let mut state = nanos;
+        Random::new(splitmix64(&mut state))
+    }
+}
+
+/// Uniformly distributed in `lo..=hi`
+pub fn random_range(lo: &BigInt, hi: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    if lo > hi {
+        return Err(CalcError::InvalidArgument(
+            String::from("rand"),
+            format!("{} is larger than {}", lo, hi),
+        ));
+    }
+    let range: BigInt = hi - lo + 1;
+    ctx.check_bits(range.bits())?;
+    Ok(lo + ctx.random().below(&range, ctx)?)
+}
+
+/// Uniformly distributed in `0..2^bits`
+pub fn random_bits(bits: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    if bits.is_negative() {
+        return Err(CalcError::InvalidArgument(
+            String::from("randbits"),
+            String::from("expects a non-negative number of bits"),
+        ));
+    }
+    let bits = bits.to_u64().unwrap_or(u64::MAX);
+    ctx.check_bits(bits)?;
+    Ok(ctx.random().bits(bits))
+}
+
+/// A random prime with exactly the given number of bits
+pub fn random_prime(bits: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    if bits < &BigInt::from(2) {
+        return Err(CalcError::InvalidArgument(
+            String::from("randprime"),
+            String::from("a prime has at least 2 bits"),
+        ));
+    }
+    let bits = bits.to_u64().unwrap_or(u64::MAX);
+    ctx.check_bits(bits)?;
+
+    let top = BigInt::one() << (bits - 1);
+    loop {
+        ctx.step()?;
+        let mut candidate = &top | ctx.random().bits(bits - 1);
+        // Only 2 and 3 have two bits, both are tried
+        if bits > 2 {
+            candidate.set_bit(0, true);
+        }
+        if number_theory::is_prime(&candidate, ctx)? {
+            return Ok(candidate);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashMap};
+
+    use super::*;
+    use crate::evaluator::EvalOptions;
+
+    #[test]
+    fn test_seeded() {
+        let mut a = Random::new(42);
+        let mut b = Random::new(42);
+        let a: Vec<u64> = (0..4).map(|_| a.next_u64()).collect();
+        let b: Vec<u64> = (0..4).map(|_| b.next_u64()).collect();
+        assert_eq!(a, b);
+        assert_ne!(Random::new(42).next_u64(), Random::new(43).next_u64());
+        assert_eq!(Random::new(42).seed(), 42);
+    }
+
+    #[test]
+    fn test_random_functions() {
+        let rng = RefCell::new(Random::new(1));
+        let variables = HashMap::new();
+        let ctx = Evaluation::new(
+            &variables,
+            EvalOptions {
+                rng: Some(&rng),
+                ..Default::default()
+            },
+        );
+
+        for _ in 0..100 {
+            let x = random_range(&BigInt::from(-3), &BigInt::from(3), &ctx).unwrap();
+            assert!(x >= BigInt::from(-3) && x <= BigInt::from(3));
+            assert!(random_bits(&BigInt::from(10), &ctx).unwrap().bits() <= 10);
+        }
+        assert_eq!(
+            random_range(&BigInt::from(5), &BigInt::from(5), &ctx),
+            Ok(BigInt::from(5))
+        );
+        assert!(random_range(&BigInt::from(5), &BigInt::from(4), &ctx).is_err());
+        assert_eq!(random_bits(&BigInt::from(0), &ctx), Ok(BigInt::from(0)));
+
+        for bits in [2, 3, 64, 256] {
+            let p = random_prime(&BigInt::from(bits), &ctx).unwrap();
+            assert_eq!(p.bits(), bits);
+            assert_eq!(number_theory::is_prime(&p, &ctx), Ok(true));
+        }
+        assert!(random_prime(&BigInt::from(1), &ctx).is_err());
+    }
+}
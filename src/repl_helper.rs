@@ -0,0 +1,272 @@
+use std::borrow::Cow;
+
+use rustyline::{
+    completion::Completer,
+    highlight::{CmdKind, Highlighter},
+    hint::Hinter,
+    validate::Validator,
+    Context, Helper,
+};
+
+use simple_calculator::{function::FUNCTIONS, special_function::SPECIAL_FUNCTIONS, RES_VAR};
+
+use crate::color::{self, paint, Color};
+
+/// Rustyline helper of the REPL. Provides tab completion of variable, function and command names
+/// and highlights the line while it is typed.
+#[derive(Default)]
+pub struct ReplHelper {
+    /// Names of the currently stored variables. Must be refreshed before reading every line
+    pub variables: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(complete_word(line, pos, &self.variables))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        if color::stdout_colors() {
+            Cow::Owned(highlight_line(line))
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, _kind: CmdKind) -> bool {
+        // Every typed character can change the color of the others, e.g. by closing a parenthesis
+        color::stdout_colors()
+    }
+}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Finds the word the cursor is at and returns its starting index together with
+/// all the names it can be completed to, sorted alphabetically.
+/// Commands are only offered at the beginning of the line, since they cannot be part of an expression.
+fn complete_word(line: &str, pos: usize, variables: &[String]) -> (usize, Vec<String>) {
+    let start = line[..pos]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()
+        .map_or(pos, |(i, _)| i);
+    let word = &line[start..pos];
+
+    let commands = SPECIAL_FUNCTIONS
+        .keys()
+        .copied()
+        .filter(|_| line[..start].trim().is_empty());
+
+    let mut candidates: Vec<String> = variables
+        .iter()
+        .map(String::as_str)
+        .chain(FUNCTIONS.keys().copied())
+        .chain(commands)
+        .filter(|name| name.starts_with(word))
+        .map(String::from)
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+
+    (start, candidates)
+}
+
+/// Colors numbers, text literals, variables, functions, commands and operators of the line.
+/// Parentheses without a matching pair are marked as errors.
+fn highlight_line(line: &str) -> String {
+    let unmatched = unmatched_parentheses(line);
+    let mut highlighted = String::with_capacity(line.len());
+    let mut it = line.char_indices().peekable();
+
+    while let Some((index, c)) = it.next() {
+        if c.is_alphanumeric() {
+            let mut end = index + c.len_utf8();
+            while let Some((i, next)) = it.peek() {
+                if !(next.is_alphanumeric() || *next == '_') {
+                    break;
+                }
+                end = i + next.len_utf8();
+                it.next();
+            }
+
+            let word = &line[index..end];
+            let color = if c.is_ascii_digit() {
+                Color::Cyan
+            } else if let Some((_, '(')) = it.peek() {
+                Color::Blue
+            } else if SPECIAL_FUNCTIONS.contains_key(word) {
+                Color::Magenta
+            } else {
+                Color::Green
+            };
+            highlighted.push_str(&paint(word, color));
+            continue;
+        }
+
+        // A text literal is a number, up to the closing quote
+        if c == '\'' || c == '"' {
+            let mut end = line.len();
+            let mut escaped = false;
+            for (i, next) in it.by_ref() {
+                if next == c && !escaped {
+                    end = i + 1;
+                    break;
+                }
+                escaped = next == '\\' && !escaped;
+            }
+            highlighted.push_str(&paint(&line[index..end], Color::Cyan));
+            continue;
+        }
+
+        let color = match c {
+            RES_VAR => Some(Color::Green),
+            '(' | ')' if unmatched.contains(&index) => Some(Color::Red),
+            '+' | '-' | '*' | '/' | '%' | '&' | '|' | '^' | '<' | '>' | '=' => Some(Color::Yellow),
+            _ => None,
+        };
+        match color {
+            Some(color) => highlighted.push_str(&paint(&c.to_string(), color)),
+            None => highlighted.push(c),
+        }
+    }
+
+    highlighted
+}
+
+/// Byte indices of the parentheses which are never closed or never opened
+fn unmatched_parentheses(line: &str) -> Vec<usize> {
+    let mut open = vec![];
+    let mut unmatched = vec![];
+
+    for (index, c) in line.char_indices() {
+        match c {
+            '(' => open.push(index),
+            ')' => {
+                if open.pop().is_none() {
+                    unmatched.push(index);
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    unmatched.extend(open);
+    unmatched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_variable() {
+        let variables = vec![String::from("alpha"), String::from("beta")];
+        assert_eq!(
+            complete_word("1 + al", 6, &variables),
+            (4, vec![String::from("alpha")])
+        );
+    }
+
+    #[test]
+    fn test_complete_ambiguous() {
+        let variables = vec![String::from("value2"), String::from("value1")];
+        assert_eq!(
+            complete_word("va", 2, &variables),
+            (
+                0,
+                vec![
+                    String::from("value1"),
+                    String::from("value2"),
+                    String::from("vars")
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_complete_command_only_at_start() {
+        // `exit` is not offered in the middle of an expression
+        let variables = vec![String::from("ex")];
+        assert_eq!(complete_word("ex", 2, &variables).1, vec!["ex", "exit"]);
+        assert_eq!(complete_word("1+ex", 4, &variables).1, vec!["ex"]);
+    }
+
+    #[test]
+    fn test_complete_at_cursor() {
+        // Only the part of the word before the cursor is completed
+        let variables = vec![String::from("pqr"), String::from("pqs")];
+        assert_eq!(
+            complete_word("pqr+1", 2, &variables),
+            (0, vec![String::from("pqr"), String::from("pqs")])
+        );
+    }
+
+    #[test]
+    fn test_unmatched_parentheses() {
+        assert!(unmatched_parentheses("(1+(2*3))").is_empty());
+        assert_eq!(unmatched_parentheses(")(1+(2)"), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_highlight_line() {
+        assert_eq!(
+            highlight_line("a = 0xF+ (1"),
+            format!(
+                "{} {} {}{} {}{}",
+                paint("a", Color::Green),
+                paint("=", Color::Yellow),
+                paint("0xF", Color::Cyan),
+                paint("+", Color::Yellow),
+                paint("(", Color::Red),
+                paint("1", Color::Cyan),
+            )
+        );
+    }
+
+    #[test]
+    fn test_highlight_text() {
+        assert_eq!(
+            highlight_line(r"'a\'b'+1"),
+            format!(
+                "{}{}{}",
+                paint(r"'a\'b'", Color::Cyan),
+                paint("+", Color::Yellow),
+                paint("1", Color::Cyan)
+            )
+        );
+    }
+
+    #[test]
+    fn test_highlight_function() {
+        assert_eq!(
+            highlight_line("mod(a"),
+            format!(
+                "{}{}{}",
+                paint("mod", Color::Blue),
+                paint("(", Color::Red),
+                paint("a", Color::Green)
+            )
+        );
+    }
+
+    #[test]
+    fn test_highlight_command() {
+        assert_eq!(highlight_line("vars"), paint("vars", Color::Magenta));
+    }
+}
@@ -0,0 +1,245 @@
+use num_bigint::{BigInt, BigUint};
+use num_integer::Integer;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::{error::CalcError, evaluator::Evaluation};
+
+/// Floor of the square root
+pub fn isqrt(x: &BigInt) -> Result<BigInt, CalcError> {
+    if x.is_negative() {
+        return Err(CalcError::NegativeRoot(x.clone()));
+    }
+    Ok(x.sqrt())
+}
+
+/// Truncated n-th root of the magnitude and whether it is exact
+fn magnitude_root(m: &BigUint, n: &BigInt) -> (BigUint, bool) {
+    match n.to_u32() {
+        Some(n) if u64::from(n) <= m.bits() => {
+            let root = m.nth_root(n);
+            let exact = &root.pow(n) == m;
+            (root, exact)
+        }
+        // Roots larger than the bit length are below 2
+        _ => (BigUint::from(u8::from(!m.is_zero())), m <= &BigUint::one()),
+    }
+}
+
+/// Floor of the n-th root. Odd roots of negative numbers are negative.
+pub fn iroot(x: &BigInt, n: &BigInt) -> Result<BigInt, CalcError> {
+    if !n.is_positive() {
+        return Err(CalcError::InvalidArgument(
+            String::from("iroot"),
+            String::from("the root must be positive"),
+        ));
+    }
+    if x.is_negative() && n.is_even() {
+        return Err(CalcError::NegativeRoot(x.clone()));
+    }
+
+    let (root, exact) = magnitude_root(x.magnitude(), n);
+    let root = BigInt::from(root);
+    Ok(match x.is_negative() {
+        true if exact => -root,
+        true => -root - 1,
+        false => root,
+    })
+}
+
+fn check_logarithm(x: &BigInt) -> Result<(), CalcError> {
+    if x.is_positive() {
+        Ok(())
+    } else {
+        Err(CalcError::NonPositiveLogarithm(x.clone()))
+    }
+}
+
+/// Floor of the logarithm in the given base, at least 2
+pub fn ilog(x: &BigInt, base: &BigInt, ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    check_logarithm(x)?;
+    if base < &BigInt::from(2) {
+        return Err(CalcError::InvalidArgument(
+            String::from("ilog"),
+            String::from("the base must be at least 2"),
+        ));
+    }
+
+    // base^(2^i) for every i while it is not larger than x
+    let mut powers = vec![base.clone()];
+    while let Some(last) = powers.last().filter(|last| last.bits() * 2 - 1 <= x.bits()) {
+        ctx.step()?;
+        let square = last * last;
+        if &square > x {
+            break;
+        }
+        powers.push(square);
+    }
+
+    // Adds the exponents from the largest one while the power stays below x
+    let mut log = BigInt::zero();
+    let mut power = BigInt::one();
+    for (i, p) in powers.iter().enumerate().rev() {
+        ctx.step()?;
+        let next = &power * p;
+        if &next <= x {
+            power = next;
+            log += BigInt::one() << i;
+        }
+    }
+    Ok(log)
+}
+
+/// Floor of the binary logarithm
+pub fn ilog2(x: &BigInt) -> Result<BigInt, CalcError> {
+    check_logarithm(x)?;
+    Ok(BigInt::from(x.bits() - 1))
+}
+
+/// 1 if the number is the square of an integer
+pub fn is_square(x: &BigInt) -> bool {
+    !x.is_negative() && &x.sqrt().pow(2) == x
+}
+
+fn is_small_prime(n: u64) -> bool {
+    n >= 2
+        && (2..)
+            .take_while(|d| d * d <= n)
+            .all(|d| !n.is_multiple_of(d))
+}
+
+/// Number of the primes whose residues are tested before taking a root
+const RESIDUE_TESTS: usize = 4;
+
+/// False if `m` is certainly not a `k`-th power for the prime `k`. Modulo a prime
+/// `q = jk + 1` only one in `k` of the non-zero residues is a `k`-th power, those with
+/// `r^j = 1`. Squares are checked modulo 64 first, only 12 of the residues are squares.
+fn may_be_power(m: &BigUint, k: u64) -> bool {
+    if k == 2 {
+        let r = (m % 64u32).to_u64().unwrap_or(0);
+        if !(0..64u64).any(|s| s * s % 64 == r) {
+            return false;
+        }
+    }
+    (1..)
+        .map(|j| j * k + 1)
+        .filter(|&q| is_small_prime(q))
+        .take(RESIDUE_TESTS)
+        .all(|q| {
+            let r = m % q;
+            r.is_zero()
+                || r.modpow(&BigUint::from((q - 1) / k), &BigUint::from(q))
+                    .is_one()
+        })
+}
+
+/// `x = base^exponent` with the largest possible exponent above 1, if there is one.
+/// Negative numbers can only be odd powers. 0, 1 and -1 are not considered.
+pub fn perfect_power(x: &BigInt, ctx: &Evaluation) -> Result<Option<(BigInt, u64)>, CalcError> {
+    let mut base = x.clone();
+    let mut exponent = 1;
+
+    // Takes exact prime roots as long as there are any
+    'roots: while base.magnitude() > &BigUint::one() {
+        // The exponent of an even base divides the trailing zeros, an odd base is at least 3
+        let zeros = base.magnitude().trailing_zeros().unwrap_or(0);
+        let largest = match zeros {
+            0 => (base.bits() as f64 / 3f64.log2()) as u64,
+            zeros => zeros,
+        };
+        for k in (2..=largest).filter(|&k| is_small_prime(k)) {
+            ctx.step()?;
+            if (k == 2 && base.is_negative())
+                || !zeros.is_multiple_of(k)
+                || !may_be_power(base.magnitude(), k)
+            {
+                continue;
+            }
+            let (root, exact) = magnitude_root(base.magnitude(), &BigInt::from(k));
+            if exact {
+                base = match base.is_negative() {
+                    true => -BigInt::from(root),
+                    false => BigInt::from(root),
+                };
+                exponent *= k;
+                continue 'roots;
+            }
+        }
+        break;
+    }
+
+    Ok(match exponent {
+        1 => None,
+        _ => Some((base, exponent)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::tests::empty_evaluation;
+
+    fn n(n: i64) -> BigInt {
+        BigInt::from(n)
+    }
+
+    #[test]
+    fn test_iroot() {
+        assert_eq!(isqrt(&n(99)), Ok(n(9)));
+        assert_eq!(isqrt(&n(-1)), Err(CalcError::NegativeRoot(n(-1))));
+        assert_eq!(iroot(&n(27), &n(3)), Ok(n(3)));
+        assert_eq!(iroot(&n(26), &n(3)), Ok(n(2)));
+        // Floor, not truncated
+        assert_eq!(iroot(&n(-27), &n(3)), Ok(n(-3)));
+        assert_eq!(iroot(&n(-26), &n(3)), Ok(n(-3)));
+        assert_eq!(iroot(&n(-5), &n(1001)), Ok(n(-2)));
+        assert_eq!(iroot(&n(-1), &n(1001)), Ok(n(-1)));
+        assert_eq!(iroot(&n(5), &(n(1) << 40)), Ok(n(1)));
+        assert_eq!(iroot(&n(0), &n(5)), Ok(n(0)));
+        assert!(iroot(&n(-16), &n(4)).is_err());
+        assert!(iroot(&n(16), &n(0)).is_err());
+    }
+
+    #[test]
+    fn test_ilog() {
+        let ctx = empty_evaluation();
+
+        assert_eq!(ilog(&n(1), &n(10), &ctx), Ok(n(0)));
+        assert_eq!(ilog(&n(999), &n(10), &ctx), Ok(n(2)));
+        assert_eq!(ilog(&n(1000), &n(10), &ctx), Ok(n(3)));
+        assert_eq!(ilog(&n(80), &n(3), &ctx), Ok(n(3)));
+        assert_eq!(ilog(&n(81), &n(3), &ctx), Ok(n(4)));
+        assert_eq!(ilog(&(n(1) << 1000), &n(2), &ctx), Ok(n(1000)));
+        assert_eq!(ilog(&n(5), &n(6), &ctx), Ok(n(0)));
+        assert_eq!(
+            ilog(&n(0), &n(10), &ctx),
+            Err(CalcError::NonPositiveLogarithm(n(0)))
+        );
+        assert!(ilog(&n(10), &n(1), &ctx).is_err());
+        assert_eq!(ilog2(&n(1024)), Ok(n(10)));
+        assert_eq!(ilog2(&n(1023)), Ok(n(9)));
+    }
+
+    #[test]
+    fn test_perfect_power() {
+        let ctx = empty_evaluation();
+
+        assert!(is_square(&n(0)));
+        assert!(is_square(&n(144)));
+        assert!(!is_square(&n(143)));
+        assert!(!is_square(&n(-4)));
+
+        assert_eq!(perfect_power(&n(64), &ctx), Ok(Some((n(2), 6))));
+        assert_eq!(perfect_power(&n(-64), &ctx), Ok(Some((n(-4), 3))));
+        assert_eq!(perfect_power(&n(-16), &ctx), Ok(None));
+        assert_eq!(perfect_power(&n(36), &ctx), Ok(Some((n(6), 2))));
+        assert_eq!(perfect_power(&n(12), &ctx), Ok(None));
+        assert_eq!(perfect_power(&n(1), &ctx), Ok(None));
+        assert_eq!(perfect_power(&n(3).pow(35), &ctx), Ok(Some((n(3), 35))));
+        assert_eq!(perfect_power(&n(7).pow(101), &ctx), Ok(Some((n(7), 101))));
+        assert_eq!(perfect_power(&n(-24).pow(15), &ctx), Ok(Some((n(-24), 15))));
+        assert_eq!(perfect_power(&(n(1) << 5), &ctx), Ok(Some((n(2), 5))));
+        // Large numbers which are not powers are rejected by their residues
+        assert_eq!(perfect_power(&((n(1) << 100_000) + 1), &ctx), Ok(None));
+        assert_eq!(perfect_power(&((n(1) << 100_000) * 3), &ctx), Ok(None));
+    }
+}
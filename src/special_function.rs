@@ -1,53 +1,776 @@
 use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
 use phf::phf_map;
-use std::{collections::HashMap, process::exit};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    convert::TryInto,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
 
-use crate::print_variable;
+use crate::{
+    bitwise,
+    bytes::ByteSyntax,
+    calculator::Output,
+    error::CalcError,
+    evaluator::{EvalOptions, Evaluation, Limits},
+    float::{self, Fields},
+    format::{format_value, format_variable},
+    function::UserFunction,
+    layout::{bit_ruler, Layout},
+    number_theory,
+    parser::{parse_arguments, parse_field_assignments, RES_VAR},
+    random::Random,
+    tokenizer::{tokenize, Token},
+    value::Value,
+};
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum DisplayMode {
     Binary,
+    #[default]
     Decimal,
     Hex,
+    /// Values below 2^128 as IPv4 or IPv6 addresses
+    Ip,
+    /// The bytes of the values as text, other than printable ASCII escaped
+    Ascii,
+    /// The bytes of the values as UTF-8 text
+    Utf8,
+    /// The bytes of the values as hex, also of the lists of bytes
+    Bytes(ByteSyntax),
 }
 
+#[derive(Debug, Default)]
 pub struct RuntimeVariables {
     pub display_mode: DisplayMode,
-    pub vars: HashMap<String, BigInt>,
+    /// Complex numbers are shown by their absolute value and angle
+    pub polar: bool,
+    pub vars: HashMap<String, Value>,
+    pub limits: Limits,
+    /// Word width in bits used by the rotate operators
+    pub width: Option<u32>,
+    /// Modulus of the modular mode, at least 2
+    pub modulus: Option<BigInt>,
+    /// Stops the running evaluation when set
+    pub interrupt: Arc<AtomicBool>,
+    /// Bit field layouts by name, used to decode and build register values
+    pub layouts: HashMap<String, Layout>,
+    /// Generator of the random functions, its seed is set by the `seed` command
+    pub rng: RefCell<Random>,
+    /// Functions defined in the session, e.g. with `f(x) = x**2`
+    pub functions: HashMap<String, UserFunction>,
 }
 
-pub static SPECIAL_FUNCTIONS: phf::Map<&'static str, fn(&mut RuntimeVariables)> = phf_map! {
+impl RuntimeVariables {
+    /// Settings of the evaluations in this session
+    pub fn eval_options(&self) -> EvalOptions<'_> {
+        EvalOptions {
+            limits: self.limits,
+            width: self.width,
+            modulus: self.modulus.as_ref(),
+            interrupt: Some(&self.interrupt),
+            rng: Some(&self.rng),
+            functions: Some(&self.functions),
+        }
+    }
+}
+
+/// Commands get the rest of the line after the command name as arguments
+pub type SpecialFunction = fn(&mut RuntimeVariables, &str) -> Result<Output, CalcError>;
+
+pub static SPECIAL_FUNCTIONS: phf::Map<&'static str, SpecialFunction> = phf_map! {
     "exit" => exit_function,
     "vars" => vars_print_function,
     "bin" => bin_display_mode,
     "dec" => dec_display_mode,
     "hex" => hex_display_mode,
+    "ip" => ip_display_mode,
+    "ascii" => ascii_display_mode,
+    "utf8" => utf8_display_mode,
+    "bytes" => bytes_display_mode,
+    "polar" => polar_function,
     "clear" => clear_function,
+    "limit" => limit_function,
+    "width" => width_function,
+    "factor" => factor_function,
+    "egcd" => egcd_function,
+    "mod" => modulus_function,
+    "bits" => bits_function,
+    "float" => float_function,
+    "layout" => layout_function,
+    "pack" => pack_function,
+    "seed" => seed_function,
 };
 
-fn exit_function(_settings: &mut RuntimeVariables) {
-    exit(0);
+/// Rejects the arguments of the commands which don't take any
+fn no_arguments(args: &str) -> Result<(), CalcError> {
+    if args.is_empty() {
+        Ok(())
+    } else {
+        Err(CalcError::InvalidCommandArgument(args.to_string()))
+    }
 }
 
-fn vars_print_function(runtime_vars: &mut RuntimeVariables) {
-    for var in runtime_vars.vars.keys() {
-        print_variable(runtime_vars, var);
-    }
+fn exit_function(_settings: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    no_arguments(args)?;
+    Ok(Output::Exit)
+}
+
+fn vars_print_function(
+    runtime_vars: &mut RuntimeVariables,
+    args: &str,
+) -> Result<Output, CalcError> {
+    no_arguments(args)?;
+    Ok(Output::Variables(
+        runtime_vars
+            .vars
+            .keys()
+            .map(|var| format_variable(runtime_vars, var))
+            .collect(),
+    ))
 }
 
-fn dec_display_mode(runtime_vars: &mut RuntimeVariables) {
+fn dec_display_mode(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    no_arguments(args)?;
     runtime_vars.display_mode = DisplayMode::Decimal;
+    Ok(Output::None)
 }
 
-fn hex_display_mode(runtime_vars: &mut RuntimeVariables) {
+fn hex_display_mode(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    no_arguments(args)?;
     runtime_vars.display_mode = DisplayMode::Hex;
+    Ok(Output::None)
 }
 
-fn bin_display_mode(runtime_vars: &mut RuntimeVariables) {
+fn bin_display_mode(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    no_arguments(args)?;
     runtime_vars.display_mode = DisplayMode::Binary;
+    Ok(Output::None)
+}
+
+fn ip_display_mode(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    no_arguments(args)?;
+    runtime_vars.display_mode = DisplayMode::Ip;
+    Ok(Output::None)
+}
+
+fn ascii_display_mode(
+    runtime_vars: &mut RuntimeVariables,
+    args: &str,
+) -> Result<Output, CalcError> {
+    no_arguments(args)?;
+    runtime_vars.display_mode = DisplayMode::Ascii;
+    Ok(Output::None)
+}
+
+fn utf8_display_mode(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    no_arguments(args)?;
+    runtime_vars.display_mode = DisplayMode::Utf8;
+    Ok(Output::None)
+}
+
+/// `bytes` shows the bytes of the results separated by spaces, `bytes c` and `bytes rust`
+/// as an array of the language
+fn bytes_display_mode(
+    runtime_vars: &mut RuntimeVariables,
+    args: &str,
+) -> Result<Output, CalcError> {
+    let syntax = match args {
+        "" => ByteSyntax::Plain,
+        "c" => ByteSyntax::C,
+        "rust" => ByteSyntax::Rust,
+        _ => return Err(CalcError::InvalidCommandArgument(args.to_string())),
+    };
+    runtime_vars.display_mode = DisplayMode::Bytes(syntax);
+    Ok(Output::None)
+}
+
+/// `polar` shows whether complex numbers are displayed in polar form, `polar <on|off>` changes it
+fn polar_function(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    runtime_vars.polar = match args {
+        "" => {
+            let polar = if runtime_vars.polar { "on" } else { "off" };
+            return Ok(Output::Text(format!("polar = {}", polar)));
+        }
+        "on" => true,
+        "off" => false,
+        _ => return Err(CalcError::InvalidCommandArgument(args.to_string())),
+    };
+    Ok(Output::None)
 }
 
-fn clear_function(runtime_vars: &mut RuntimeVariables) {
+fn clear_function(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    no_arguments(args)?;
     runtime_vars.vars.clear();
-    // https://stackoverflow.com/a/62101709/7611589
-    println!("\x1B[2J\x1B[1;1H");
+    runtime_vars.functions.clear();
+    Ok(Output::ClearScreen)
+}
+
+/// `limit` shows the evaluation limits, `limit <bits|shift|steps|time> <value|off>` changes one.
+/// The time limit is given in seconds.
+fn limit_function(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    let limits = &mut runtime_vars.limits;
+
+    if args.is_empty() {
+        let show = |limit: Option<String>| limit.unwrap_or_else(|| String::from("off"));
+        return Ok(Output::Text(format!(
+            "bits = {}\nshift = {}\nsteps = {}\ntime = {}",
+            show(limits.max_bits.map(|bits| bits.to_string())),
+            show(limits.max_shift.map(|shift| shift.to_string())),
+            show(limits.max_steps.map(|steps| steps.to_string())),
+            show(limits.timeout.map(|time| format!("{}s", time.as_secs()))),
+        )));
+    }
+
+    let invalid = || CalcError::InvalidCommandArgument(args.to_string());
+    let mut it = args.split_whitespace();
+    let (Some(limit), Some(val), None) = (it.next(), it.next(), it.next()) else {
+        return Err(invalid());
+    };
+    let val = match val {
+        "off" => None,
+        val => Some(val.parse::<u64>().map_err(|_| invalid())?),
+    };
+
+    // A limit of zero would reject every evaluation, only shifts can be limited to zero
+    if val == Some(0) && limit != "shift" {
+        return Err(invalid());
+    }
+    match limit {
+        "bits" => limits.max_bits = val,
+        "shift" => limits.max_shift = val,
+        "steps" => limits.max_steps = val,
+        "time" => limits.timeout = val.map(Duration::from_secs),
+        _ => return Err(invalid()),
+    }
+    Ok(Output::None)
+}
+
+/// `width` shows the word width of the rotate operators, `width <bits|off>` changes it
+fn width_function(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    runtime_vars.width = match args {
+        "" => {
+            return Ok(Output::Text(match runtime_vars.width {
+                Some(width) => format!("width = {}", width),
+                None => String::from("width = off"),
+            }))
+        }
+        "off" => None,
+        width => match width.parse::<u32>() {
+            Ok(width) if width > 0 => Some(width),
+            _ => return Err(CalcError::InvalidCommandArgument(args.to_string())),
+        },
+    };
+    Ok(Output::None)
+}
+
+/// Evaluates the comma separated expressions given to a command.
+/// Fails unless there are exactly `N` of them, and they must be integers.
+fn eval_arguments<const N: usize>(
+    command: &str,
+    variables: &HashMap<String, Value>,
+    options: EvalOptions,
+    args: &str,
+) -> Result<[BigInt; N], CalcError> {
+    eval_argument_tokens(command, variables, options, args, &tokenize(args)?)
+}
+
+/// Like `eval_arguments`, for commands that change the tokens before parsing them
+fn eval_argument_tokens<const N: usize>(
+    command: &str,
+    variables: &HashMap<String, Value>,
+    options: EvalOptions,
+    args: &str,
+    tokens: &[Token],
+) -> Result<[BigInt; N], CalcError> {
+    let exprs = parse_arguments(tokens)?;
+    if exprs.len() != N {
+        return Err(CalcError::InvalidCommandArgument(args.to_string()));
+    }
+
+    let ctx = Evaluation::new(variables, options);
+    let vals = exprs
+        .iter()
+        .map(|expr| ctx.eval(expr)?.into_owned().into_int(command))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(vals.try_into().unwrap_or_else(|_| unreachable!()))
+}
+
+/// `factor <expr>` prints the prime factorization of the value
+fn factor_function(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    let [n] = eval_arguments(
+        "factor",
+        &runtime_vars.vars,
+        runtime_vars.eval_options(),
+        args,
+    )?;
+    if n.is_zero() {
+        return Err(CalcError::InvalidArgument(
+            String::from("factor"),
+            String::from("0 has no prime factorization"),
+        ));
+    }
+
+    let ctx = Evaluation::new(&runtime_vars.vars, runtime_vars.eval_options());
+    let mode = &runtime_vars.display_mode;
+    let mut factors: Vec<String> = number_theory::factorize(&n, &ctx)?
+        .into_iter()
+        .map(|(p, exponent)| match exponent {
+            1 => format_value(&p, mode),
+            _ => format!("{}**{}", format_value(&p, mode), exponent),
+        })
+        .collect();
+    if n < BigInt::zero() {
+        factors.insert(0, String::from("-1"));
+    }
+    if factors.is_empty() {
+        factors.push(format_value(&BigInt::one(), mode));
+    }
+
+    Ok(Output::Text(format!(
+        "{} = {}",
+        format_value(&n, mode),
+        factors.join(" * ")
+    )))
+}
+
+/// `egcd <a>, <b>` prints the gcd with the coefficients of `a*x + b*y = gcd`
+fn egcd_function(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    let [a, b] = eval_arguments(
+        "egcd",
+        &runtime_vars.vars,
+        runtime_vars.eval_options(),
+        args,
+    )?;
+    let (gcd, x, y) = number_theory::extended_gcd(&a, &b);
+
+    let mode = &runtime_vars.display_mode;
+    Ok(Output::Text(format!(
+        "gcd = {}\nx = {}\ny = {}",
+        format_value(&gcd, mode),
+        format_value(&x, mode),
+        format_value(&y, mode)
+    )))
+}
+
+/// `mod` shows the modulus of the modular mode, `mod <expr|off>` changes it.
+/// While it is set every result is reduced and `/` multiplies by the modular inverse.
+fn modulus_function(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    match args {
+        "" => {
+            return Ok(Output::Text(match &runtime_vars.modulus {
+                Some(modulus) => format!(
+                    "mod = {}",
+                    format_value(modulus, &runtime_vars.display_mode)
+                ),
+                None => String::from("mod = off"),
+            }))
+        }
+        "off" => runtime_vars.modulus = None,
+        _ => {
+            // A modulus is written as `2^255 - 19`, an exclusive or makes no sense here
+            let tokens = tokenize(args)?
+                .into_iter()
+                .map(|token| match token {
+                    Token::BitwiseXor => Token::Power,
+                    token => token,
+                })
+                .collect::<Vec<_>>();
+            // The new modulus is not reduced by the current one
+            let options = EvalOptions {
+                modulus: None,
+                ..runtime_vars.eval_options()
+            };
+            let [modulus] =
+                eval_argument_tokens("mod", &runtime_vars.vars, options, args, &tokens)?;
+            if modulus < BigInt::from(2) {
+                return Err(CalcError::InvalidCommandArgument(args.to_string()));
+            }
+            runtime_vars.modulus = Some(modulus);
+        }
+    }
+    Ok(Output::None)
+}
+
+/// `bits <expr>` prints the bits of the value below their positions, `bits <expr> as <layout>`
+/// also the values of the fields of the layout. The bits are shown in the session width, or
+/// in whole bytes without one.
+fn bits_function(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    let (expr, layout) = match args.rsplit_once(char::is_whitespace) {
+        Some((rest, name)) if rest.trim_end().ends_with(" as") => {
+            let layout = runtime_vars
+                .layouts
+                .get(name)
+                .ok_or_else(|| CalcError::UnknownLayout(name.to_string()))?;
+            (&rest.trim_end()[..rest.trim_end().len() - 3], Some(layout))
+        }
+        _ => (args, None),
+    };
+
+    let [val] = eval_arguments(
+        "bits",
+        &runtime_vars.vars,
+        runtime_vars.eval_options(),
+        expr,
+    )?;
+    let width = match runtime_vars.width {
+        Some(width) => width,
+        None if val.is_negative() => return Err(CalcError::MissingWidth),
+        None => {
+            let bits = val
+                .bits()
+                .max(layout.map_or(0, |layout| u64::from(layout.width())))
+                .max(1);
+            bits.div_ceil(8)
+                .checked_mul(8)
+                .and_then(|bits| bits.try_into().ok())
+                .ok_or(CalcError::ResultTooLarge(u64::from(u32::MAX)))?
+        }
+    };
+
+    let ctx = Evaluation::new(&runtime_vars.vars, runtime_vars.eval_options());
+    ctx.check_bits(u64::from(width))?;
+    let unsigned = bitwise::to_width(&val, u64::from(width), "bits", &ctx)?;
+    let mut lines = vec![bit_ruler(&unsigned, width)];
+    if let Some(layout) = layout {
+        for (name, field) in layout.decode(&val, &ctx)? {
+            lines.push(format!(
+                "{} = {}",
+                name,
+                format_value(&field, &runtime_vars.display_mode)
+            ));
+        }
+    }
+    Ok(Output::Text(lines.join("\n")))
+}
+
+/// `float <f16|bf16|f32|f64> <expr>` prints the number of the bit pattern and its sign,
+/// exponent and mantissa fields
+fn float_function(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    let (name, expr) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let format =
+        float::format(name).ok_or_else(|| CalcError::InvalidCommandArgument(args.to_string()))?;
+    let [bits] = eval_arguments(
+        "float",
+        &runtime_vars.vars,
+        runtime_vars.eval_options(),
+        expr,
+    )?;
+    if !format.contains(&bits) {
+        return Err(CalcError::InvalidArgument(
+            String::from("float"),
+            format!("expected a {} bit pattern", format.width()),
+        ));
+    }
+
+    let fields = Fields::new(&bits, format);
+    let display = |n: BigInt| format_value(&n, &runtime_vars.display_mode);
+    // The power of two of the exponent field, subnormal numbers have the one of the smallest
+    // normal number
+    let power = match fields.exponent {
+        0 => format!(" (2**{})", 1 - format.bias()),
+        _ if fields.value(format).is_none() => String::new(),
+        exponent => format!(" (2**{})", exponent as i64 - format.bias()),
+    };
+    Ok(Output::Text(format!(
+        "{}\nsign = {}\nexponent = {}{}\nmantissa = {}",
+        float::format_float(&bits, format),
+        fields.negative as u8,
+        display(BigInt::from(fields.exponent)),
+        power,
+        display(fields.mantissa),
+    )))
+}
+
+/// `layout` lists the layouts, `layout <name>` shows one, `layout <name> = <fields>` defines
+/// one, e.g. `layout ctrl = en:0, mode:3..1, irq:7`, and `layout <name> off` removes it
+fn layout_function(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    let layouts = &mut runtime_vars.layouts;
+    if args.is_empty() {
+        let mut names: Vec<&String> = layouts.keys().collect();
+        names.sort();
+        return Ok(match names.is_empty() {
+            true => Output::None,
+            false => Output::Text(
+                names
+                    .into_iter()
+                    .map(|name| format!("{} = {}", name, layouts[name]))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        });
+    }
+
+    let end = args
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(args.len());
+    let (name, rest) = (&args[..end], args[end..].trim());
+    if !name.starts_with(char::is_alphabetic) {
+        return Err(CalcError::InvalidCommandArgument(args.to_string()));
+    }
+    let unknown = || CalcError::UnknownLayout(name.to_string());
+
+    match rest {
+        "" => {
+            let layout = layouts.get(name).ok_or_else(unknown)?;
+            return Ok(Output::Text(format!("{} = {}", name, layout)));
+        }
+        "off" => {
+            layouts.remove(name).ok_or_else(unknown)?;
+        }
+        _ => {
+            let Some(spec) = rest.strip_prefix('=') else {
+                return Err(CalcError::InvalidCommandArgument(args.to_string()));
+            };
+            let ctx = Evaluation::new(&runtime_vars.vars, runtime_vars.eval_options());
+            let layout = Layout::parse(spec, &ctx)?;
+            runtime_vars.layouts.insert(name.to_string(), layout);
+        }
+    }
+    Ok(Output::None)
+}
+
+/// `pack <layout> <field> = <expr>, ...` builds a value from the values of the fields and
+/// stores it in `$`. The fields which are not given are zero.
+fn pack_function(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    let (name, fields) = match args.split_once(char::is_whitespace) {
+        Some((name, fields)) => (name, fields.trim()),
+        None => (args, ""),
+    };
+    let layout = runtime_vars
+        .layouts
+        .get(name)
+        .ok_or_else(|| CalcError::UnknownLayout(name.to_string()))?;
+
+    let ctx = Evaluation::new(&runtime_vars.vars, runtime_vars.eval_options());
+    let vals = parse_field_assignments(&tokenize(fields)?)?
+        .into_iter()
+        .map(|(field, expr)| Ok((field, ctx.eval(&expr)?.into_owned().into_int("pack")?)))
+        .collect::<Result<Vec<_>, CalcError>>()?;
+    let val = layout.pack(&vals, &ctx)?;
+
+    let var = RES_VAR.to_string();
+    runtime_vars.vars.insert(var.clone(), Value::Int(val));
+    Ok(Output::Variables(vec![format_variable(runtime_vars, &var)]))
+}
+
+/// `seed` shows the seed of the random functions, `seed <expr>` restarts them with a new one
+fn seed_function(runtime_vars: &mut RuntimeVariables, args: &str) -> Result<Output, CalcError> {
+    if args.is_empty() {
+        return Ok(Output::Text(format!(
+            "seed = {}",
+            runtime_vars.rng.get_mut().seed()
+        )));
+    }
+
+    let [seed] = eval_arguments(
+        "seed",
+        &runtime_vars.vars,
+        runtime_vars.eval_options(),
+        args,
+    )?;
+    let seed = seed
+        .to_u64()
+        .ok_or_else(|| CalcError::InvalidCommandArgument(args.to_string()))?;
+    *runtime_vars.rng.get_mut() = Random::new(seed);
+    Ok(Output::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_function() {
+        let mut runtime_vars = RuntimeVariables::default();
+
+        limit_function(&mut runtime_vars, "bits 64").unwrap();
+        limit_function(&mut runtime_vars, "time off").unwrap();
+        assert_eq!(runtime_vars.limits.max_bits, Some(64));
+        assert_eq!(runtime_vars.limits.timeout, None);
+        assert_eq!(
+            limit_function(&mut runtime_vars, ""),
+            Ok(Output::Text(String::from(
+                "bits = 64\nshift = 4294967295\nsteps = 1000000\ntime = off"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_limit_function_invalid() {
+        let mut runtime_vars = RuntimeVariables::default();
+
+        for args in ["bits", "bits -1", "size 10", "bits 10 20"] {
+            assert_eq!(
+                limit_function(&mut runtime_vars, args),
+                Err(CalcError::InvalidCommandArgument(args.to_string()))
+            );
+        }
+        assert_eq!(runtime_vars.limits, Limits::default());
+    }
+
+    #[test]
+    fn test_width_function() {
+        let mut runtime_vars = RuntimeVariables::default();
+
+        assert_eq!(
+            width_function(&mut runtime_vars, ""),
+            Ok(Output::Text(String::from("width = off")))
+        );
+        width_function(&mut runtime_vars, "32").unwrap();
+        assert_eq!(runtime_vars.width, Some(32));
+        for args in ["0", "-8", "8 16"] {
+            assert_eq!(
+                width_function(&mut runtime_vars, args),
+                Err(CalcError::InvalidCommandArgument(args.to_string()))
+            );
+        }
+        width_function(&mut runtime_vars, "off").unwrap();
+        assert_eq!(runtime_vars.width, None);
+    }
+
+    #[test]
+    fn test_factor_function() {
+        let mut runtime_vars = RuntimeVariables::default();
+        runtime_vars
+            .vars
+            .insert(String::from("a"), Value::Int(BigInt::from(-360)));
+
+        assert_eq!(
+            factor_function(&mut runtime_vars, "a"),
+            Ok(Output::Text(String::from("-360 = -1 * 2**3 * 3**2 * 5")))
+        );
+        assert_eq!(
+            factor_function(&mut runtime_vars, "1"),
+            Ok(Output::Text(String::from("1 = 1")))
+        );
+        assert!(factor_function(&mut runtime_vars, "0").is_err());
+        assert_eq!(
+            factor_function(&mut runtime_vars, "1, 2"),
+            Err(CalcError::InvalidCommandArgument(String::from("1, 2")))
+        );
+    }
+
+    #[test]
+    fn test_egcd_function() {
+        let mut runtime_vars = RuntimeVariables::default();
+
+        assert_eq!(
+            egcd_function(&mut runtime_vars, "240, 46"),
+            Ok(Output::Text(String::from("gcd = 2\nx = -9\ny = 47")))
+        );
+        assert!(egcd_function(&mut runtime_vars, "240").is_err());
+    }
+
+    #[test]
+    fn test_bits_function() {
+        let mut runtime_vars = RuntimeVariables::default();
+
+        assert_eq!(
+            bits_function(&mut runtime_vars, "0x8B"),
+            Ok(Output::Text(String::from("7    3\n1000 1011")))
+        );
+        assert_eq!(
+            bits_function(&mut runtime_vars, "-1"),
+            Err(CalcError::MissingWidth)
+        );
+        runtime_vars.width = Some(12);
+        assert_eq!(
+            bits_function(&mut runtime_vars, "-1"),
+            Ok(Output::Text(String::from("11    7    3\n1111  1111 1111")))
+        );
+        // The ruler is as wide as the word
+        runtime_vars.width = Some(u32::MAX);
+        assert_eq!(
+            bits_function(&mut runtime_vars, "1"),
+            Err(CalcError::ResultTooLarge(1 << 24))
+        );
+    }
+
+    #[test]
+    fn test_float_function() {
+        let mut runtime_vars = RuntimeVariables::default();
+
+        assert_eq!(
+            float_function(&mut runtime_vars, "f32 0x40490FDB"),
+            Ok(Output::Text(String::from(
+                "3.1415927\nsign = 0\nexponent = 128 (2**1)\nmantissa = 4788187"
+            )))
+        );
+        runtime_vars.display_mode = DisplayMode::Hex;
+        assert_eq!(
+            float_function(&mut runtime_vars, "f16 0x8001"),
+            Ok(Output::Text(String::from(
+                "-6e-8\nsign = 1\nexponent = 0x0 (2**-14)\nmantissa = 0x1"
+            )))
+        );
+        assert_eq!(
+            float_function(&mut runtime_vars, "bf16 0xFF80"),
+            Ok(Output::Text(String::from(
+                "-inf\nsign = 1\nexponent = 0xFF\nmantissa = 0x0"
+            )))
+        );
+        assert!(float_function(&mut runtime_vars, "f16 0x10000").is_err());
+        assert!(float_function(&mut runtime_vars, "f8 1").is_err());
+        assert!(float_function(&mut runtime_vars, "f32").is_err());
+    }
+
+    #[test]
+    fn test_layout_function() {
+        let mut runtime_vars = RuntimeVariables::default();
+
+        assert_eq!(layout_function(&mut runtime_vars, ""), Ok(Output::None));
+        layout_function(&mut runtime_vars, "ctrl = en:0, mode:3..1, irq:7").unwrap();
+        assert_eq!(
+            layout_function(&mut runtime_vars, "ctrl"),
+            Ok(Output::Text(String::from("ctrl = en:0, mode:3..1, irq:7")))
+        );
+        runtime_vars.display_mode = DisplayMode::Hex;
+        assert_eq!(
+            bits_function(&mut runtime_vars, "0x80 | 0xB as ctrl"),
+            Ok(Output::Text(String::from(
+                "7    3\n1000 1011\nen = 0x1\nmode = 0x5\nirq = 0x1"
+            )))
+        );
+        assert_eq!(
+            pack_function(&mut runtime_vars, "ctrl mode = 5, irq = 1"),
+            Ok(Output::Variables(vec![(
+                String::from("$"),
+                String::from("0x8A")
+            )]))
+        );
+        assert_eq!(
+            bits_function(&mut runtime_vars, "1 as dma"),
+            Err(CalcError::UnknownLayout(String::from("dma")))
+        );
+
+        layout_function(&mut runtime_vars, "ctrl off").unwrap();
+        assert_eq!(
+            pack_function(&mut runtime_vars, "ctrl en = 1"),
+            Err(CalcError::UnknownLayout(String::from("ctrl")))
+        );
+        assert!(layout_function(&mut runtime_vars, "ctrl en:0").is_err());
+    }
+
+    #[test]
+    fn test_seed_function() {
+        let mut runtime_vars = RuntimeVariables::default();
+
+        seed_function(&mut runtime_vars, "6 * 7").unwrap();
+        assert_eq!(
+            seed_function(&mut runtime_vars, ""),
+            Ok(Output::Text(String::from("seed = 42")))
+        );
+        let first = runtime_vars.rng.get_mut().next_u64();
+        seed_function(&mut runtime_vars, "42").unwrap();
+        assert_eq!(runtime_vars.rng.get_mut().next_u64(), first);
+        for args in ["-1", "1 << 64"] {
+            assert_eq!(
+                seed_function(&mut runtime_vars, args),
+                Err(CalcError::InvalidCommandArgument(args.to_string()))
+            );
+        }
+    }
 }
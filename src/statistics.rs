@@ -0,0 +1,164 @@
+use num_bigint::BigInt;
+use num_traits::{One, Signed, Zero};
+
+use crate::{error::CalcError, evaluator::Evaluation};
+
+fn empty_list(function: &str) -> CalcError {
+    CalcError::InvalidArgument(function.to_string(), String::from("the list is empty"))
+}
+
+fn check_not_empty(function: &str, list: &[&BigInt]) -> Result<(), CalcError> {
+    match list.is_empty() {
+        true => Err(empty_list(function)),
+        false => Ok(()),
+    }
+}
+
+pub fn sum(list: &[&BigInt]) -> BigInt {
+    list.iter().copied().sum()
+}
+
+pub fn product(list: &[&BigInt], ctx: &Evaluation) -> Result<BigInt, CalcError> {
+    let mut res = BigInt::one();
+    for n in list {
+        ctx.step()?;
+        ctx.check_bits((res.bits() + n.bits()).saturating_sub(1))?;
+        res *= *n;
+    }
+    Ok(res)
+}
+
+pub fn min(list: &[&BigInt]) -> Result<BigInt, CalcError> {
+    let min = list.iter().copied().min();
+    min.cloned().ok_or_else(|| empty_list("min"))
+}
+
+pub fn max(list: &[&BigInt]) -> Result<BigInt, CalcError> {
+    let max = list.iter().copied().max();
+    max.cloned().ok_or_else(|| empty_list("max"))
+}
+
+/// Rounded toward zero like the division
+pub fn mean(list: &[&BigInt]) -> Result<BigInt, CalcError> {
+    check_not_empty("mean", list)?;
+    Ok(sum(list) / list.len())
+}
+
+/// The mean of the two middle values for an even length, rounded toward zero
+pub fn median(list: &[&BigInt]) -> Result<BigInt, CalcError> {
+    check_not_empty("median", list)?;
+    let sorted = sort(list);
+    let middle = sorted.len() / 2;
+    Ok(match sorted.len() % 2 {
+        1 => sorted[middle].clone(),
+        _ => (&sorted[middle - 1] + &sorted[middle]) / 2,
+    })
+}
+
+/// The most frequent value, the smallest one if there is a tie
+pub fn mode(list: &[&BigInt]) -> Result<BigInt, CalcError> {
+    check_not_empty("mode", list)?;
+    let sorted = sort(list);
+    let mut best = (&sorted[0], 0);
+    for run in sorted.chunk_by(|a, b| a == b) {
+        if run.len() > best.1 {
+            best = (&run[0], run.len());
+        }
+    }
+    Ok(best.0.clone())
+}
+
+/// Population standard deviation, rounded down
+pub fn stddev(list: &[&BigInt]) -> Result<BigInt, CalcError> {
+    check_not_empty("stddev", list)?;
+    // n² * variance = n * Σx² - (Σx)²
+    let n = BigInt::from(list.len());
+    let squares: BigInt = list.iter().map(|x| *x * *x).sum();
+    let scaled_variance = &n * squares - sum(list).pow(2);
+    Ok(scaled_variance.sqrt() / n)
+}
+
+pub fn sort(list: &[&BigInt]) -> Vec<BigInt> {
+    let mut sorted: Vec<BigInt> = list.iter().map(|n| (*n).clone()).collect();
+    sorted.sort();
+    sorted
+}
+
+/// `start`, `start + step`, ... while below `end`, or above it for a negative step
+pub fn range(
+    start: &BigInt,
+    end: &BigInt,
+    step: &BigInt,
+    ctx: &Evaluation,
+) -> Result<Vec<BigInt>, CalcError> {
+    if step.is_zero() {
+        return Err(CalcError::InvalidArgument(
+            String::from("range"),
+            String::from("the step must not be zero"),
+        ));
+    }
+
+    // Rounded up, negative if the range is empty
+    let count = (end - start + step - step.signum()) / step;
+    ctx.check_steps(&count)?;
+
+    let mut res = vec![];
+    let mut n = start.clone();
+    while (step.is_positive() && &n < end) || (step.is_negative() && &n > end) {
+        ctx.step()?;
+        let next = &n + step;
+        res.push(n);
+        n = next;
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::tests::empty_evaluation;
+
+    fn list(numbers: &[i64]) -> Vec<BigInt> {
+        numbers.iter().map(|&n| BigInt::from(n)).collect()
+    }
+
+    #[test]
+    fn test_statistics() {
+        let numbers = list(&[4, -1, 7, 4, 2, 7]);
+        let numbers: Vec<&BigInt> = numbers.iter().collect();
+
+        assert_eq!(sum(&numbers), BigInt::from(23));
+        assert_eq!(min(&numbers), Ok(BigInt::from(-1)));
+        assert_eq!(max(&numbers), Ok(BigInt::from(7)));
+        assert_eq!(mean(&numbers), Ok(BigInt::from(3)));
+        assert_eq!(median(&numbers), Ok(BigInt::from(4)));
+        assert_eq!(median(&numbers[..5]), Ok(BigInt::from(4)));
+        assert_eq!(mode(&numbers), Ok(BigInt::from(4)));
+        assert_eq!(sort(&numbers), list(&[-1, 2, 4, 4, 7, 7]));
+        assert!(min(&[]).is_err());
+        assert!(stddev(&[]).is_err());
+
+        let numbers = list(&[2, 4, 4, 4, 5, 5, 7, 9]);
+        let numbers: Vec<&BigInt> = numbers.iter().collect();
+        assert_eq!(stddev(&numbers), Ok(BigInt::from(2)));
+        let numbers = list(&[1, 2]);
+        let numbers: Vec<&BigInt> = numbers.iter().collect();
+        // 0.5 rounded down
+        assert_eq!(stddev(&numbers), Ok(BigInt::zero()));
+    }
+
+    #[test]
+    fn test_range() {
+        let ctx = empty_evaluation();
+        let n = |n: i64| BigInt::from(n);
+
+        assert_eq!(range(&n(0), &n(5), &n(2), &ctx), Ok(list(&[0, 2, 4])));
+        assert_eq!(range(&n(3), &n(0), &n(-1), &ctx), Ok(list(&[3, 2, 1])));
+        assert_eq!(range(&n(3), &n(0), &n(1), &ctx), Ok(vec![]));
+        assert!(range(&n(0), &n(5), &n(0), &ctx).is_err());
+        assert_eq!(
+            range(&n(0), &(n(1) << 64), &n(1), &ctx),
+            Err(CalcError::EvaluationTimeout)
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use num_bigint::{BigInt, BigUint, Sign};
+
+use crate::error::CalcError;
+
+/// Parses a text literal at the start of the string, like `'RIFF'` or `"a\n"`, into its bytes
+/// packed big-endian, or little-endian with an `le` suffix like `'RIFF'le`. Characters are
+/// encoded in UTF-8. Returns the number and the length of the literal in characters,
+/// or `None` if the string doesn't start with a quote.
+pub fn parse_literal(s: &str) -> Result<Option<(BigUint, usize)>, CalcError> {
+    let mut chars = s.chars();
+    let quote = match chars.next() {
+        Some(quote @ ('\'' | '"')) => quote,
+        _ => return Ok(None),
+    };
+    let invalid = |len: usize| CalcError::InvalidText(s.chars().take(len).collect());
+
+    let mut bytes = vec![];
+    let mut len = 1;
+    loop {
+        let c = chars.next().ok_or_else(|| invalid(len))?;
+        len += 1;
+        match c {
+            c if c == quote => break,
+            '\\' => {
+                let escaped = chars.next().ok_or_else(|| invalid(len))?;
+                len += 1;
+                let byte = match escaped {
+                    'n' => b'\n',
+                    'r' => b'\r',
+                    't' => b'\t',
+                    '0' => 0,
+                    '\\' | '\'' | '"' => escaped as u8,
+                    'x' => {
+                        let digits: String = chars.by_ref().take(2).collect();
+                        len += digits.chars().count();
+                        match digits.len() {
+                            2 => u8::from_str_radix(&digits, 16).map_err(|_| invalid(len))?,
+                            _ => return Err(invalid(len)),
+                        }
+                    }
+                    _ => return Err(invalid(len)),
+                };
+                bytes.push(byte);
+            }
+            c => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+
+    let little_endian = s.chars().skip(len).take(2).eq("le".chars());
+    if little_endian {
+        len += 2;
+    }
+    if s.chars().nth(len).is_some_and(char::is_alphanumeric) {
+        return Err(invalid(len + 1));
+    }
+    Ok(Some((pack(&bytes, little_endian), len)))
+}
+
+/// The bytes as one number, the first byte is the most significant one unless little-endian
+pub fn pack(bytes: &[u8], little_endian: bool) -> BigUint {
+    match little_endian {
+        true => BigUint::from_bytes_le(bytes),
+        false => BigUint::from_bytes_be(bytes),
+    }
+}
+
+/// The bytes of the value from the most significant one as a quoted text, e.g. `"RIFF"`.
+/// Bytes which are not printable ASCII are escaped, except UTF-8 characters if `utf8`.
+/// `None` for negative values.
+pub fn format_text(val: &BigInt, utf8: bool) -> Option<String> {
+    let (sign, bytes) = val.to_bytes_be();
+    let bytes = match sign {
+        Sign::Minus => return None,
+        Sign::NoSign => vec![],
+        Sign::Plus => bytes,
+    };
+
+    let mut text = String::from("\"");
+    let mut rest = &bytes[..];
+    while let Some(&byte) = rest.first() {
+        // The longest valid UTF-8 character at the start, at most 4 bytes
+        let character = match utf8 && !byte.is_ascii() {
+            true => (2..=rest.len().min(4)).find_map(|len| std::str::from_utf8(&rest[..len]).ok()),
+            false => None,
+        };
+        match character {
+            Some(character) => {
+                text.push_str(character);
+                rest = &rest[character.len()..];
+                continue;
+            }
+            None => text.push_str(&escape(byte)),
+        }
+        rest = &rest[1..];
+    }
+    text.push('"');
+    Some(text)
+}
+
+fn escape(byte: u8) -> String {
+    match byte {
+        b'\n' => String::from("\\n"),
+        b'\r' => String::from("\\r"),
+        b'\t' => String::from("\\t"),
+        0 => String::from("\\0"),
+        b'\\' | b'"' => format!("\\{}", byte as char),
+        b' '..=b'~' => (byte as char).to_string(),
+        _ => format!("\\x{:02X}", byte),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Option<(BigUint, usize)>, CalcError> {
+        parse_literal(s)
+    }
+
+    #[test]
+    fn test_parse_literal() {
+        assert_eq!(
+            parse("'RIFF' + 1"),
+            Ok(Some((BigUint::from(0x52494646u32), 6)))
+        );
+        assert_eq!(
+            parse("'RIFF'le"),
+            Ok(Some((BigUint::from(0x46464952u32), 8)))
+        );
+        assert_eq!(parse("'A'"), Ok(Some((BigUint::from(65u32), 3))));
+        assert_eq!(
+            parse(r#""a'\"\\\n\x7F""#),
+            Ok(Some((BigUint::from(0x6127225C0A7Fu64), 14)))
+        );
+        assert_eq!(parse("\"é\""), Ok(Some((BigUint::from(0xC3A9u32), 3))));
+        assert_eq!(parse("''"), Ok(Some((BigUint::from(0u32), 2))));
+        assert_eq!(parse("A"), Ok(None));
+        for (invalid, shown) in [
+            ("'abc", "'abc"),
+            ("'\\q' + 1", "'\\q"),
+            ("'\\x4' + 1", "'\\x4'"),
+            ("'ab'x", "'ab'x"),
+        ] {
+            assert_eq!(
+                parse(invalid),
+                Err(CalcError::InvalidText(shown.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_text() {
+        let format = |n: u64, utf8: bool| format_text(&BigInt::from(n), utf8).unwrap();
+        assert_eq!(format(0x52494646, false), "\"RIFF\"");
+        assert_eq!(format(0x61225C0A00, false), r#""a\"\\\n\0""#);
+        assert_eq!(format(0x41C3A9FF, false), r#""A\xC3\xA9\xFF""#);
+        assert_eq!(format(0x41C3A9FF, true), r#""Aé\xFF""#);
+        assert_eq!(format(0, true), "\"\"");
+        assert_eq!(format_text(&BigInt::from(-1), false), None);
+    }
+}
@@ -1,27 +1,58 @@
-use std::{iter::Peekable, str::FromStr};
+use std::iter::Peekable;
 
-use num_bigint::BigUint;
-use num_traits::Num;
+use num_bigint::{BigInt, BigUint};
+use num_rational::BigRational;
+use num_traits::{Num, Pow};
 
-use crate::{error::CalcError, parser::RES_VAR, special_function::SPECIAL_FUNCTIONS};
+use crate::{
+    datetime,
+    error::CalcError,
+    ip::{self, Network},
+    parser::RES_VAR,
+    special_function::SPECIAL_FUNCTIONS,
+    text, units,
+};
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum Token {
     Number(BigUint),
+    /// A number with the `i` suffix, e.g. `4i`
+    Imaginary(BigUint),
+    /// A number with a fraction, e.g. `3.14`
+    Decimal(BigRational),
+    /// An ISO-8601 date as nanoseconds since the Unix epoch, e.g. `2024-03-01T12:00:00Z`
+    DateTime(BigInt),
+    /// A network in CIDR notation, e.g. `192.168.1.0/24`. Addresses without a prefix are
+    /// numbers.
+    Network(Network),
     BitwiseAnd,
     BitwiseOr,
     BitwiseXor,
     BitshiftRight,
     BitshiftLeft,
+    RotateRight,
+    RotateLeft,
     Plus,
     Minus,
     Mult,
+    Power,
     Div,
     Modulo,
+    Factorial,
+    DoubleFactorial,
     LeftPar,
     RightPar,
+    LeftBracket,
+    RightBracket,
     Equals,
+    Comma,
+    Semicolon,
     Variable(String),
+    Function(String),
+    /// A unit after a number or in a conversion target, e.g. `KiB` in `4 KiB`
+    Unit(String),
+    /// The conversion operator `in`, or `to`
+    In,
     ResultVariable, // Special variable `$` to store the result of the last operation
 }
 
@@ -29,17 +60,56 @@ pub enum Token {
 pub fn tokenize(line: &str) -> Result<Vec<Token>, crate::error::CalcError> {
     let mut it = line.chars().enumerate().peekable();
     let mut tokens = vec![];
+    // Every name after `in` is a unit
+    let mut conversion = false;
 
     while let Some((index, c)) = it.next() {
+        if c.is_ascii_hexdigit() || c == ':' {
+            // No date, address or decimal of interest is longer than 64 characters
+            let rest: String = std::iter::once(c)
+                .chain(it.clone().map(|(_, c)| c))
+                .take(64)
+                .collect();
+            if let Some((token, len)) = literal_token(&rest)? {
+                for _ in 1..len {
+                    it.next();
+                }
+                tokens.push(token);
+                continue;
+            }
+        }
+
+        if c == '\'' || c == '"' {
+            let rest: String = line.chars().skip(index).collect();
+            if let Some((n, len)) = text::parse_literal(&rest)? {
+                for _ in 1..len {
+                    it.next();
+                }
+                tokens.push(Token::Number(n));
+                continue;
+            }
+        }
+
         let token = match c {
             '+' => Token::Plus,
             '-' => Token::Minus,
-            '*' => Token::Mult,
+            '*' => match it.next_if(|(_, c)| *c == '*') {
+                Some(_) => Token::Power,
+                None => Token::Mult,
+            },
             '/' => Token::Div,
             '%' => Token::Modulo,
+            '!' => match it.next_if(|(_, c)| *c == '!') {
+                Some(_) => Token::DoubleFactorial,
+                None => Token::Factorial,
+            },
             '(' => Token::LeftPar,
             ')' => Token::RightPar,
+            '[' => Token::LeftBracket,
+            ']' => Token::RightBracket,
             '=' => Token::Equals,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
             '&' => Token::BitwiseAnd,
             '|' => Token::BitwiseOr,
             '^' => Token::BitwiseXor,
@@ -47,13 +117,21 @@ pub fn tokenize(line: &str) -> Result<Vec<Token>, crate::error::CalcError> {
                 let Some((_, '>')) = it.next() else {
                     return Err(CalcError::InvalidToken(index));
                 };
-                Token::BitshiftRight
+                if it.next_if(|(_, c)| *c == '>').is_some() {
+                    Token::RotateRight
+                } else {
+                    Token::BitshiftRight
+                }
             }
             '<' => {
                 let Some((_, '<')) = it.next() else {
                     return Err(CalcError::InvalidToken(index));
                 };
-                Token::BitshiftLeft
+                if it.next_if(|(_, c)| *c == '<').is_some() {
+                    Token::RotateLeft
+                } else {
+                    Token::BitshiftLeft
+                }
             }
             '0' => {
                 // Consume a hex or binary number
@@ -63,15 +141,19 @@ pub fn tokenize(line: &str) -> Result<Vec<Token>, crate::error::CalcError> {
                     Some((_, x)) if !x.is_ascii_alphanumeric() => {
                         Token::Number(BigUint::from(0usize))
                     }
+                    Some((_, 'i')) => {
+                        it.next();
+                        Token::Imaginary(BigUint::from(0usize))
+                    }
                     Some((_, 'x')) | Some((_, 'b')) => {
                         let radix = match it.next() {
                             Some((_, 'x')) => 16,
                             Some((_, 'b')) => 2,
                             _ => panic!(), // This should not happen since above it is checked
                         };
-                        match BigUint::from_str_radix(&consume_alphanumeric(&mut it, None), radix) {
-                            Ok(n) => Token::Number(n),
-                            Err(_) => {
+                        match number_token(&consume_alphanumeric(&mut it, None), radix) {
+                            Some(token) => token,
+                            None => {
                                 return Err(CalcError::InvalidToken(index));
                             }
                         }
@@ -83,15 +165,52 @@ pub fn tokenize(line: &str) -> Result<Vec<Token>, crate::error::CalcError> {
             c if c.is_ascii_digit() => {
                 // Consume a regular number token (i.e. not binary or hex).
                 // Numbers cannot start with 0.
-                let Ok(n) = BigUint::from_str(&consume_alphanumeric(&mut it, Some(&c.to_string()))) else {
-                    return Err(CalcError::InvalidToken(index));
-                };
-                Token::Number(n)
+                let digits = consume_alphanumeric(&mut it, Some(&c.to_string()));
+                if let Some(token) = number_token(&digits, 10) {
+                    token
+                } else {
+                    // A unit directly after the number, e.g. `16MHz`
+                    let split = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+                    let (number, unit) = digits.split_at(split);
+                    if units::unit(unit).is_none() {
+                        return Err(CalcError::InvalidToken(index));
+                    }
+                    tokens.push(Token::Number(number.parse().unwrap_or_default()));
+                    Token::Unit(unit.to_string())
+                }
             }
             c if c.is_ascii_alphabetic() => {
                 // Consume a variable name.
-                // Must start with a letter but then can contain numbers
-                let var = consume_alphanumeric(&mut it, Some(&c.to_string()));
+                // Must start with a letter but then can contain numbers and underscores
+                let mut var = c.to_string();
+                while let Some((_, c)) = it.next_if(|(_, c)| c.is_alphanumeric() || *c == '_') {
+                    var.push(c);
+                }
+
+                // A name directly followed by a parenthesis is a function call
+                if let Some((_, '(')) = it.peek() {
+                    tokens.push(Token::Function(var));
+                    continue;
+                }
+
+                if var == "in" || var == "to" {
+                    conversion = true;
+                    tokens.push(Token::In);
+                    continue;
+                }
+                // A unit name is only a unit after a number or in a conversion target
+                let after_number =
+                    matches!(tokens.last(), Some(Token::Number(_) | Token::Decimal(_)));
+                if conversion || after_number {
+                    match units::unit(&var) {
+                        Some(_) => {
+                            tokens.push(Token::Unit(var));
+                            continue;
+                        }
+                        None if conversion => return Err(CalcError::UnknownUnit(var)),
+                        None => {}
+                    }
+                }
 
                 // Cannot use a special function name for a variable
                 if SPECIAL_FUNCTIONS.contains_key(&var) {
@@ -114,6 +233,56 @@ pub fn tokenize(line: &str) -> Result<Vec<Token>, crate::error::CalcError> {
     Ok(tokens)
 }
 
+/// A date or an IP address at the start of the string, and its length.
+/// They take precedence over the operators, `2024-12-25` is a date and not `1987`.
+fn literal_token(s: &str) -> Result<Option<(Token, usize)>, CalcError> {
+    if let Some((nanos, len)) = datetime::parse_literal(s)? {
+        return Ok(Some((Token::DateTime(nanos), len)));
+    }
+
+    let Some((address, width, len)) = ip::parse_address(s)? else {
+        return Ok(decimal_token(s));
+    };
+    // A prefix directly after the address makes it a network, `10.0.0.0/8`
+    Ok(Some(match ip::parse_prefix(&s[len..]) {
+        Some((prefix, prefix_len)) => {
+            let network = Network::new(address, prefix, width)
+                .map_err(|_| CalcError::InvalidAddress(s[..len + prefix_len].to_string()))?;
+            (Token::Network(network), len + prefix_len)
+        }
+        None => (Token::Number(address.to_biguint().unwrap_or_default()), len),
+    }))
+}
+
+/// A decimal number at the start of the string, digits with a point in between like `3.14`
+fn decimal_token(s: &str) -> Option<(Token, usize)> {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let integer_len = digits(s);
+    let fraction_len = digits(s.get(integer_len + 1..)?);
+    if integer_len == 0 || fraction_len == 0 || !s[integer_len..].starts_with('.') {
+        return None;
+    }
+    let fraction = &s[integer_len + 1..integer_len + 1 + fraction_len];
+    let numer: BigInt = format!("{}{}", &s[..integer_len], fraction).parse().ok()?;
+    let denom = Pow::pow(BigInt::from(10), fraction_len);
+    Some((
+        Token::Decimal(BigRational::new(numer, denom)),
+        integer_len + 1 + fraction_len,
+    ))
+}
+
+/// Parses the digits of a number in the given radix, an `i` suffix makes it imaginary
+fn number_token(digits: &str, radix: u32) -> Option<Token> {
+    match digits.strip_suffix('i') {
+        Some(digits) => BigUint::from_str_radix(digits, radix)
+            .ok()
+            .map(Token::Imaginary),
+        None => BigUint::from_str_radix(digits, radix)
+            .ok()
+            .map(Token::Number),
+    }
+}
+
 /// Consumes all the alphanumeric characters from the given iterator and
 /// returns as a string. Doesn't consume any succeeding non-alphanumeric character.
 /// The string is built with the given prefix if one was passed.
@@ -192,7 +361,7 @@ mod tests {
 
     #[test]
     fn test_tokenize_invalid_character() {
-        assert_eq!(tokenize("1+asd;*(12/234)"), Err(CalcError::InvalidToken(5)));
+        assert_eq!(tokenize("1+asd#*(12/234)"), Err(CalcError::InvalidToken(5)));
     }
 
     #[test]
@@ -203,6 +372,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tokenize_variable_with_underscore() {
+        assert_eq!(
+            tokenize("is_square(max_2)"),
+            Ok(vec![
+                Token::Function("is_square".to_string()),
+                Token::LeftPar,
+                Token::Variable("max_2".to_string()),
+                Token::RightPar,
+            ])
+        );
+        assert_eq!(tokenize("_a"), Err(CalcError::InvalidToken(0)));
+    }
+
     #[test]
     fn test_tokenize_res_variable() {
         assert_eq!(
@@ -292,4 +475,229 @@ mod tests {
             ])
         )
     }
+
+    #[test]
+    fn test_tokenize_rotate() {
+        assert_eq!(
+            tokenize("<<<>>>>>"),
+            Ok(vec![
+                Token::RotateLeft,
+                Token::RotateRight,
+                Token::BitshiftRight,
+            ])
+        )
+    }
+
+    #[test]
+    fn test_tokenize_power() {
+        assert_eq!(
+            tokenize("2**3*4"),
+            Ok(vec![
+                Token::Number(2usize.into()),
+                Token::Power,
+                Token::Number(3usize.into()),
+                Token::Mult,
+                Token::Number(4usize.into()),
+            ])
+        )
+    }
+
+    #[test]
+    fn test_tokenize_factorial() {
+        assert_eq!(
+            tokenize("3!!!"),
+            Ok(vec![
+                Token::Number(3usize.into()),
+                Token::DoubleFactorial,
+                Token::Factorial,
+            ])
+        )
+    }
+
+    #[test]
+    fn test_tokenize_function() {
+        // Only a name directly followed by a parenthesis is a function
+        assert_eq!(
+            tokenize("mod(a, 3) + b (1)"),
+            Ok(vec![
+                Token::Function("mod".to_string()),
+                Token::LeftPar,
+                Token::Variable("a".to_string()),
+                Token::Comma,
+                Token::Number(3usize.into()),
+                Token::RightPar,
+                Token::Plus,
+                Token::Variable("b".to_string()),
+                Token::LeftPar,
+                Token::Number(1usize.into()),
+                Token::RightPar,
+            ])
+        )
+    }
+
+    #[test]
+    fn test_tokenize_imaginary() {
+        assert_eq!(
+            tokenize("3+4i * 0i - 0x10i"),
+            Ok(vec![
+                Token::Number(3usize.into()),
+                Token::Plus,
+                Token::Imaginary(4usize.into()),
+                Token::Mult,
+                Token::Imaginary(0usize.into()),
+                Token::Minus,
+                Token::Imaginary(16usize.into()),
+            ])
+        );
+        assert_eq!(tokenize("4j"), Err(CalcError::InvalidToken(0)));
+        assert_eq!(tokenize("0xi"), Err(CalcError::InvalidToken(0)));
+    }
+
+    #[test]
+    fn test_tokenize_units() {
+        let unit = |name: &str| Token::Unit(name.to_string());
+        assert_eq!(
+            tokenize("1/16MHz in ns"),
+            Ok(vec![
+                Token::Number(1usize.into()),
+                Token::Div,
+                Token::Number(16usize.into()),
+                unit("MHz"),
+                Token::In,
+                unit("ns"),
+            ])
+        );
+        assert_eq!(
+            tokenize("4 KiB + s to B/s"),
+            Ok(vec![
+                Token::Number(4usize.into()),
+                unit("KiB"),
+                Token::Plus,
+                Token::Variable("s".to_string()),
+                Token::In,
+                unit("B"),
+                Token::Div,
+                unit("s"),
+            ])
+        );
+        assert_eq!(tokenize("16MHzz"), Err(CalcError::InvalidToken(0)));
+        assert_eq!(
+            tokenize("1 s in x"),
+            Err(CalcError::UnknownUnit("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_tokenize_datetime() {
+        assert_eq!(
+            tokenize("2024-03-01T12:00:00Z - 1970-01-01"),
+            Ok(vec![
+                Token::DateTime(BigInt::from(1709294400) * 1_000_000_000),
+                Token::Minus,
+                Token::DateTime(BigInt::from(0)),
+            ])
+        );
+        assert_eq!(
+            tokenize("2024 - 12 - 25"),
+            Ok(vec![
+                Token::Number(2024usize.into()),
+                Token::Minus,
+                Token::Number(12usize.into()),
+                Token::Minus,
+                Token::Number(25usize.into()),
+            ])
+        );
+        assert_eq!(
+            tokenize("1 + 2024-02-30"),
+            Err(CalcError::InvalidDateTime("2024-02-30".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_tokenize_ip() {
+        assert_eq!(
+            tokenize("10.0.0.1 + ::1 - 10.0.0.0/8 / 2"),
+            Ok(vec![
+                Token::Number(0x0A000001usize.into()),
+                Token::Plus,
+                Token::Number(1usize.into()),
+                Token::Minus,
+                Token::Network(Network::new(BigInt::from(0x0A000000), 8, 32).unwrap()),
+                Token::Div,
+                Token::Number(2usize.into()),
+            ])
+        );
+        assert_eq!(
+            tokenize("fe80::/10"),
+            Ok(vec![Token::Network(
+                Network::new(BigInt::from(0xfe80u128 << 112), 10, 128).unwrap()
+            )])
+        );
+        assert_eq!(
+            tokenize("10.0.0.0/33"),
+            Err(CalcError::InvalidAddress("10.0.0.0/33".to_string()))
+        );
+        assert_eq!(
+            tokenize("0::0/129"),
+            Err(CalcError::InvalidAddress("0::0/129".to_string()))
+        );
+        assert_eq!(
+            tokenize("face + 1"),
+            Ok(vec![
+                Token::Variable("face".to_string()),
+                Token::Plus,
+                Token::Number(1usize.into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_decimal() {
+        let decimal = |numer: i64, denom: i64| {
+            Token::Decimal(BigRational::new(BigInt::from(numer), BigInt::from(denom)))
+        };
+        assert_eq!(
+            tokenize("3.1415927 * 0.5h"),
+            Ok(vec![
+                decimal(31415927, 10000000),
+                Token::Mult,
+                decimal(1, 2),
+                Token::Unit("h".to_string()),
+            ])
+        );
+        assert_eq!(tokenize("1."), Err(CalcError::InvalidToken(1)));
+        assert_eq!(tokenize("1.2.3"), Err(CalcError::InvalidToken(3)));
+    }
+
+    #[test]
+    fn test_tokenize_text() {
+        assert_eq!(
+            tokenize("'RIFF' ^ \"ab\"le"),
+            Ok(vec![
+                Token::Number(0x52494646usize.into()),
+                Token::BitwiseXor,
+                Token::Number(0x6261usize.into()),
+            ])
+        );
+        assert_eq!(
+            tokenize("1 + 'a"),
+            Err(CalcError::InvalidText("'a".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_tokenize_matrix() {
+        assert_eq!(
+            tokenize("[1, 2; a]"),
+            Ok(vec![
+                Token::LeftBracket,
+                Token::Number(1usize.into()),
+                Token::Comma,
+                Token::Number(2usize.into()),
+                Token::Semicolon,
+                Token::Variable("a".to_string()),
+                Token::RightBracket,
+            ])
+        )
+    }
 }
@@ -0,0 +1,437 @@
+use std::{fmt, ops::Neg};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
+
+use crate::{
+    error::CalcError, evaluator::Evaluation, format, special_function::DisplayMode, value::Value,
+};
+
+/// Exponents of the base units bit, second and metre, e.g. `[1, -1, 0]` is a data rate
+pub type Dimension = [i32; 3];
+
+pub const DIMENSIONLESS: Dimension = [0, 0, 0];
+const DATA: Dimension = [1, 0, 0];
+pub const TIME: Dimension = [0, 1, 0];
+const FREQUENCY: Dimension = [0, -1, 0];
+const LENGTH: Dimension = [0, 0, 1];
+
+const BASE_UNITS: [&str; 3] = ["bit", "s", "m"];
+
+/// Units taking SI prefixes, data sizes also take IEC prefixes.
+/// The size in base units is given as a fraction.
+const PREFIXED_UNITS: &[(&str, i64, i64, Dimension)] = &[
+    ("bit", 1, 1, DATA),
+    ("B", 8, 1, DATA),
+    ("s", 1, 1, TIME),
+    ("Hz", 1, 1, FREQUENCY),
+    ("Bd", 1, 1, FREQUENCY),
+    ("m", 1, 1, LENGTH),
+];
+
+/// Units without prefixes
+const UNITS: &[(&str, i64, i64, Dimension)] = &[
+    ("bits", 1, 1, DATA),
+    ("byte", 8, 1, DATA),
+    ("bytes", 8, 1, DATA),
+    ("min", 60, 1, TIME),
+    ("h", 3600, 1, TIME),
+    ("d", 86400, 1, TIME),
+    ("baud", 1, 1, FREQUENCY),
+    ("inch", 254, 10000, LENGTH),
+    ("ft", 3048, 10000, LENGTH),
+    ("mi", 1609344, 1000, LENGTH),
+];
+
+/// Powers of 10
+const SI_PREFIXES: &[(&str, i32)] = &[
+    ("E", 18),
+    ("P", 15),
+    ("T", 12),
+    ("G", 9),
+    ("M", 6),
+    ("k", 3),
+    ("c", -2),
+    ("m", -3),
+    ("u", -6),
+    ("n", -9),
+    ("p", -12),
+    ("f", -15),
+];
+
+/// Powers of 2
+const IEC_PREFIXES: &[(&str, u32)] = &[
+    ("Ki", 10),
+    ("Mi", 20),
+    ("Gi", 30),
+    ("Ti", 40),
+    ("Pi", 50),
+    ("Ei", 60),
+];
+
+/// A unit a quantity is displayed in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unit {
+    pub name: String,
+    /// Size in base units
+    pub factor: BigRational,
+    pub dimension: Dimension,
+}
+
+/// Name of the unit of durations, which are shown in days, hours, minutes and seconds
+pub const DURATION: &str = "duration";
+
+impl Unit {
+    /// Seconds shown like `3d 4h`
+    pub fn duration() -> Unit {
+        Unit {
+            name: DURATION.to_string(),
+            factor: BigRational::one(),
+            dimension: TIME,
+        }
+    }
+
+    /// The product of the base units, e.g. `bit/s`
+    fn base(dimension: Dimension) -> Unit {
+        Unit {
+            name: base_name(dimension),
+            factor: BigRational::one(),
+            dimension,
+        }
+    }
+}
+
+/// Looks up a unit of the built-in table, e.g. `KiB`, `MHz` or `ns`
+pub fn unit(name: &str) -> Option<Unit> {
+    let fraction = |num: i64, den: i64| BigRational::new(num.into(), den.into());
+    let unit = |factor, dimension| {
+        Some(Unit {
+            name: name.to_string(),
+            factor,
+            dimension,
+        })
+    };
+
+    if let Some(&(_, num, den, dimension)) = UNITS.iter().find(|(unit, ..)| *unit == name) {
+        return unit(fraction(num, den), dimension);
+    }
+    PREFIXED_UNITS
+        .iter()
+        .find_map(|&(base, num, den, dimension)| {
+            let prefix = name.strip_suffix(base)?;
+            let scale = prefix_scale(prefix, dimension == DATA)?;
+            Some((scale * fraction(num, den), dimension))
+        })
+        .and_then(|(factor, dimension)| unit(factor, dimension))
+}
+
+/// Data sizes only take the prefixes for multiples, e.g. `kB` or `KiB`
+fn prefix_scale(prefix: &str, data: bool) -> Option<BigRational> {
+    if prefix.is_empty() {
+        return Some(BigRational::one());
+    }
+    if let Some(&(_, exponent)) = SI_PREFIXES.iter().find(|(p, _)| *p == prefix) {
+        if data && exponent < 0 {
+            return None;
+        }
+        return Some(BigRational::from_integer(BigInt::from(10)).pow(exponent));
+    }
+    let &(_, exponent) = IEC_PREFIXES.iter().find(|(p, _)| *p == prefix && data)?;
+    Some(BigRational::from_integer(BigInt::one() << exponent))
+}
+
+/// The error of exponents of a dimension which don't fit
+fn exponent_too_large() -> CalcError {
+    CalcError::InvalidArgument(
+        String::from("**"),
+        String::from("the exponent is too large for a unit"),
+    )
+}
+
+/// The unit of a conversion target like `KiB/s`, given as the unit names with their exponents
+pub fn compound_unit(units: &[(String, i32)]) -> Result<Unit, CalcError> {
+    let mut res = Unit {
+        name: String::new(),
+        factor: BigRational::one(),
+        dimension: DIMENSIONLESS,
+    };
+    for (name, exponent) in units {
+        let unit = unit(name).ok_or_else(|| CalcError::UnknownUnit(name.clone()))?;
+        if !res.name.is_empty() || *exponent < 0 {
+            res.name.push(if *exponent < 0 { '/' } else { '*' });
+        }
+        res.name.push_str(name);
+        res.factor *= unit.factor.pow(*exponent);
+        for (d, unit_d) in res.dimension.iter_mut().zip(unit.dimension) {
+            *d = unit_d
+                .checked_mul(*exponent)
+                .and_then(|product| d.checked_add(product))
+                .ok_or_else(exponent_too_large)?;
+        }
+    }
+    Ok(res)
+}
+
+/// The base units of the dimension, e.g. `bit/s`, frequencies are written as `Hz`
+fn base_name(dimension: Dimension) -> String {
+    if dimension == FREQUENCY {
+        return String::from("Hz");
+    }
+
+    let power = |unit: &str, exponent: i32| match exponent {
+        1 => unit.to_string(),
+        _ => format!("{}**{}", unit, exponent),
+    };
+    let mut numerator = vec![];
+    let mut denominator = vec![];
+    for (unit, &exponent) in BASE_UNITS.iter().zip(&dimension) {
+        if exponent > 0 {
+            numerator.push(power(unit, exponent));
+        } else if exponent < 0 {
+            denominator.push(power(unit, -exponent));
+        }
+    }
+
+    match (numerator.is_empty(), denominator.is_empty()) {
+        (_, true) => numerator.join("*"),
+        (true, false) => format!("1/{}", denominator.join("/")),
+        (false, false) => format!("{}/{}", numerator.join("*"), denominator.join("/")),
+    }
+}
+
+/// Name of the dimension in errors
+pub fn dimension_name(dimension: Dimension) -> String {
+    match dimension {
+        DIMENSIONLESS => String::from("a plain number"),
+        dimension => base_name(dimension),
+    }
+}
+
+/// A number with a unit, e.g. `4 KiB`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quantity {
+    /// Exact amount in base units
+    pub value: BigRational,
+    /// Unit to display the amount in, also gives the dimension
+    pub unit: Unit,
+}
+
+impl Quantity {
+    /// `amount` times the unit
+    pub fn new(amount: BigRational, unit: Unit) -> Quantity {
+        Quantity {
+            value: amount * &unit.factor,
+            unit,
+        }
+    }
+
+    /// A number without a unit
+    pub fn plain(n: BigRational) -> Quantity {
+        Quantity {
+            value: n,
+            unit: Unit::base(DIMENSIONLESS),
+        }
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        self.unit.dimension
+    }
+
+    /// The amount in the displayed unit
+    pub fn amount(&self) -> BigRational {
+        &self.value / &self.unit.factor
+    }
+
+    /// Bit length of the larger part of the fraction
+    pub fn bits(&self) -> u64 {
+        self.value.numer().bits().max(self.value.denom().bits())
+    }
+
+    /// Sum or difference, both must have the same dimension. The unit of `self` is kept.
+    pub fn add(&self, other: &Quantity, subtract: bool) -> Result<Quantity, CalcError> {
+        if self.dimension() != other.dimension() {
+            return Err(CalcError::IncompatibleUnits(
+                dimension_name(self.dimension()),
+                dimension_name(other.dimension()),
+            ));
+        }
+        let value = match subtract {
+            true => &self.value - &other.value,
+            false => &self.value + &other.value,
+        };
+        Ok(Quantity {
+            value,
+            unit: self.unit.clone(),
+        })
+    }
+
+    /// Product or quotient. Scaling keeps the unit, other products are in base units.
+    pub fn mul(&self, other: &Quantity, divide: bool) -> Result<Quantity, CalcError> {
+        let value = match divide {
+            true if other.value.is_zero() => {
+                return Err(CalcError::DivisionByZero(Value::Quantity(Box::new(
+                    self.clone(),
+                ))))
+            }
+            true => &self.value / &other.value,
+            false => &self.value * &other.value,
+        };
+        let unit = match (self.dimension(), other.dimension()) {
+            (_, DIMENSIONLESS) => self.unit.clone(),
+            (DIMENSIONLESS, _) if !divide => other.unit.clone(),
+            (mut lhs, rhs) => {
+                for (d, rhs_d) in lhs.iter_mut().zip(rhs) {
+                    *d = match divide {
+                        true => d.checked_sub(rhs_d),
+                        false => d.checked_add(rhs_d),
+                    }
+                    .ok_or_else(exponent_too_large)?;
+                }
+                Unit::base(lhs)
+            }
+        };
+        Ok(Quantity { value, unit })
+    }
+
+    pub fn pow(&self, exponent: &BigInt, ctx: &Evaluation) -> Result<Quantity, CalcError> {
+        if self.value.is_zero() && exponent.is_negative() {
+            return Err(CalcError::DivisionByZero(Value::Quantity(Box::new(
+                self.clone(),
+            ))));
+        }
+        let exponent = exponent.to_i32().ok_or_else(exponent_too_large)?;
+        let mut dimension = self.dimension();
+        for d in dimension.iter_mut() {
+            *d = d.checked_mul(exponent).ok_or_else(exponent_too_large)?;
+        }
+        ctx.check_bits(
+            self.bits()
+                .saturating_mul(u64::from(exponent.unsigned_abs())),
+        )?;
+
+        let unit = match exponent {
+            1 => self.unit.clone(),
+            _ => Unit::base(dimension),
+        };
+        Ok(Quantity {
+            value: Pow::pow(&self.value, exponent),
+            unit,
+        })
+    }
+
+    /// The same quantity displayed in another unit of the same dimension
+    pub fn convert(&self, unit: Unit) -> Result<Quantity, CalcError> {
+        if unit.dimension != self.dimension() {
+            return Err(CalcError::IncompatibleUnits(
+                dimension_name(self.dimension()),
+                unit.name,
+            ));
+        }
+        Ok(Quantity {
+            value: self.value.clone(),
+            unit,
+        })
+    }
+}
+
+impl Neg for Quantity {
+    type Output = Quantity;
+
+    fn neg(self) -> Quantity {
+        Quantity {
+            value: -self.value,
+            unit: self.unit,
+        }
+    }
+}
+
+/// The amount in the displayed unit followed by the unit, e.g. `62.5 ns`
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            format::format_quantity(self, &DisplayMode::Decimal)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::tests::empty_evaluation;
+
+    fn quantity(amount: i64, unit_name: &str) -> Quantity {
+        Quantity::new(BigInt::from(amount).into(), unit(unit_name).unwrap())
+    }
+
+    #[test]
+    fn test_unit_table() {
+        let factor = |name: &str| unit(name).map(|unit| unit.factor.to_string());
+
+        assert_eq!(factor("KiB"), Some(String::from("8192")));
+        assert_eq!(factor("kB"), Some(String::from("8000")));
+        assert_eq!(factor("Mbit"), Some(String::from("1000000")));
+        assert_eq!(factor("ns"), Some(String::from("1/1000000000")));
+        assert_eq!(factor("min"), Some(String::from("60")));
+        assert_eq!(factor("mm"), Some(String::from("1/1000")));
+        assert_eq!(factor("inch"), Some(String::from("127/5000")));
+        assert_eq!(unit("GHz").map(|unit| unit.dimension), Some(FREQUENCY));
+        assert_eq!(unit("mB"), None);
+        assert_eq!(unit("Kis"), None);
+        assert_eq!(unit("x"), None);
+    }
+
+    #[test]
+    fn test_quantity() {
+        let ctx = empty_evaluation();
+
+        let size = quantity(4, "KiB").add(&quantity(512, "B"), false).unwrap();
+        assert_eq!(size.to_string(), "4.5 KiB");
+        let rate = quantity(1, "MiB").mul(&quantity(1, "s"), true).unwrap();
+        assert_eq!(rate.to_string(), "8388608 bit/s");
+        let period = Quantity::plain(BigRational::one())
+            .mul(&quantity(16, "MHz"), true)
+            .unwrap();
+        assert_eq!(period.to_string(), "0.0000000625 s");
+        assert_eq!(
+            period.convert(unit("ns").unwrap()).unwrap().to_string(),
+            "62.5 ns"
+        );
+        let area = quantity(3, "m").pow(&BigInt::from(2), &ctx).unwrap();
+        assert_eq!(area.to_string(), "9 m**2");
+
+        assert_eq!(
+            quantity(1, "s").add(&quantity(1, "m"), false),
+            Err(CalcError::IncompatibleUnits(
+                String::from("s"),
+                String::from("m")
+            ))
+        );
+        assert!(quantity(1, "s").convert(unit("Hz").unwrap()).is_err());
+        assert_eq!(
+            compound_unit(&[(String::from("KiB"), 1), (String::from("s"), -1)])
+                .map(|unit| (unit.name, unit.dimension)),
+            Ok((String::from("KiB/s"), [1, -1, 0]))
+        );
+
+        // Dimensions whose exponents overflow are errors
+        let huge = Quantity {
+            value: BigRational::one(),
+            unit: Unit::base([0, i32::MAX, 0]),
+        };
+        assert_eq!(huge.mul(&huge, false), Err(exponent_too_large()));
+        assert_eq!(
+            Quantity::plain(BigRational::one())
+                .mul(&huge, true)
+                .unwrap()
+                .mul(&huge, true),
+            Err(exponent_too_large())
+        );
+        assert_eq!(
+            compound_unit(&[(String::from("s"), i32::MAX), (String::from("s"), 1)]),
+            Err(exponent_too_large())
+        );
+    }
+}
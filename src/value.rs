@@ -0,0 +1,171 @@
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_complex::Complex;
+use num_traits::{Signed, Zero};
+
+use crate::{
+    datetime,
+    error::CalcError,
+    ip::Network,
+    matrix::Matrix,
+    units::{Quantity, DIMENSIONLESS},
+};
+
+/// The result of an evaluation, stored in the variables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(BigInt),
+    /// Gaussian integer, the imaginary part is never zero
+    Complex(Complex<BigInt>),
+    Matrix(Matrix),
+    /// A number with a unit, e.g. `4 KiB`, boxed as it is much larger than the other values
+    Quantity(Box<Quantity>),
+    /// A point in time in UTC as nanoseconds since the Unix epoch, e.g. `2024-03-01T12:00:00Z`
+    DateTime(BigInt),
+    /// An IP network, e.g. `192.168.1.0/24`
+    Network(Network),
+    /// A function referenced by its name, e.g. the first argument of `map(f, list)`
+    Function(String),
+}
+
+impl Value {
+    /// A complex value with a zero imaginary part is an integer
+    pub fn complex(re: BigInt, im: BigInt) -> Value {
+        if im.is_zero() {
+            Value::Int(re)
+        } else {
+            Value::Complex(Complex::new(re, im))
+        }
+    }
+
+    /// The integer, operations which are only defined for integers fail with the name of the
+    /// operation otherwise
+    pub fn as_int(&self, operation: &str) -> Result<&BigInt, CalcError> {
+        match self {
+            Value::Int(n) => Ok(n),
+            Value::Complex(_) => Err(CalcError::ComplexOperand(operation.to_string())),
+            Value::Matrix(_) => Err(CalcError::MatrixOperand(operation.to_string())),
+            Value::Quantity(_) => Err(CalcError::QuantityOperand(operation.to_string())),
+            Value::DateTime(_) => Err(CalcError::DateTimeOperand(operation.to_string())),
+            Value::Network(_) => Err(CalcError::NetworkOperand(operation.to_string())),
+            Value::Function(_) => Err(CalcError::FunctionOperand(operation.to_string())),
+        }
+    }
+
+    pub fn into_int(self, operation: &str) -> Result<BigInt, CalcError> {
+        match self {
+            Value::Int(n) => Ok(n),
+            other => other.as_int(operation).cloned(),
+        }
+    }
+
+    /// The number as a complex number, fails for matrices and functions
+    pub fn to_complex(&self, operation: &str) -> Result<Complex<BigInt>, CalcError> {
+        match self {
+            Value::Complex(z) => Ok(z.clone()),
+            other => Ok(Complex::new(
+                other.as_int(operation)?.clone(),
+                BigInt::zero(),
+            )),
+        }
+    }
+
+    /// The number as a quantity, integers have no unit
+    pub fn to_quantity(&self, operation: &str) -> Result<Quantity, CalcError> {
+        match self {
+            Value::Quantity(q) => Ok((**q).clone()),
+            other => Ok(Quantity::plain(other.as_int(operation)?.clone().into())),
+        }
+    }
+
+    /// The matrix, functions which are only defined for matrices fail otherwise
+    pub fn as_matrix(&self, function: &str) -> Result<&Matrix, CalcError> {
+        match self {
+            Value::Matrix(m) => Ok(m),
+            _ => Err(CalcError::InvalidArgument(
+                function.to_string(),
+                String::from("expected a matrix"),
+            )),
+        }
+    }
+
+    /// Bit length of the largest component or element
+    pub fn bits(&self) -> u64 {
+        match self {
+            Value::Int(n) => n.bits(),
+            Value::Complex(z) => z.re.bits().max(z.im.bits()),
+            Value::Matrix(m) => m.bits(),
+            Value::Quantity(q) => q.bits(),
+            Value::DateTime(nanos) => nanos.bits(),
+            Value::Network(network) => network.address.bits(),
+            Value::Function(_) => 0,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        matches!(self, Value::Int(n) if n.is_zero())
+    }
+}
+
+impl From<BigInt> for Value {
+    fn from(n: BigInt) -> Self {
+        Value::Int(n)
+    }
+}
+
+impl From<Complex<BigInt>> for Value {
+    fn from(z: Complex<BigInt>) -> Self {
+        Value::complex(z.re, z.im)
+    }
+}
+
+/// A quantity without a unit is an integer if it is a whole number
+impl From<Quantity> for Value {
+    fn from(q: Quantity) -> Self {
+        if q.dimension() == DIMENSIONLESS && q.value.is_integer() {
+            Value::Int(q.value.to_integer())
+        } else {
+            Value::Quantity(Box::new(q))
+        }
+    }
+}
+
+/// Decimal, complex values as `3+4i`, matrices as `[1, 2; 3, 4]`, quantities as `62.5 ns`,
+/// dates in ISO-8601 and networks as `10.0.0.0/8`
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Complex(z) if z.re.is_zero() => write!(f, "{}i", z.im),
+            Value::Complex(z) => {
+                let sign = if z.im.is_negative() { "" } else { "+" };
+                write!(f, "{}{}{}i", z.re, sign, z.im)
+            }
+            Value::Matrix(m) => write!(f, "{}", m),
+            Value::Quantity(q) => write!(f, "{}", q),
+            Value::DateTime(nanos) => write!(f, "{}", datetime::format_datetime(nanos)),
+            Value::Network(network) => write!(f, "{}", network),
+            Value::Function(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value() {
+        let n = |n: i64| BigInt::from(n);
+
+        assert_eq!(Value::complex(n(3), n(0)), Value::Int(n(3)));
+        assert_eq!(Value::complex(n(3), n(-4)).to_string(), "3-4i");
+        assert_eq!(Value::complex(n(0), n(1)).to_string(), "1i");
+        assert_eq!(Value::complex(n(-300), n(4)).bits(), 9);
+        assert_eq!(
+            Value::complex(n(1), n(1)).as_int("&"),
+            Err(CalcError::ComplexOperand(String::from("&")))
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::{error::CalcError, value::Value};
+
+/// Where the evaluator resolves variables from and stores results to.
+/// Implement this to back the variables with other sources than a plain map.
+pub trait VariableStore {
+    /// Value of the variable if it is defined.
+    /// Stores keeping the values in memory should borrow them instead of cloning.
+    fn get(&self, var: &str) -> Option<Cow<'_, Value>>;
+
+    /// Stores the value of the variable. Can fail if the store does not accept assignments.
+    fn set(&mut self, var: &str, val: Value) -> Result<(), CalcError>;
+}
+
+impl VariableStore for HashMap<String, Value> {
+    fn get(&self, var: &str) -> Option<Cow<'_, Value>> {
+        HashMap::get(self, var).map(Cow::Borrowed)
+    }
+
+    fn set(&mut self, var: &str, val: Value) -> Result<(), CalcError> {
+        self.insert(var.to_string(), val);
+        Ok(())
+    }
+}
+
+/// Wraps another store and rejects all the assignments.
+/// Note that this includes saving the result to `$`, use it as the parent of a
+/// [`LayeredStore`] to keep the results in a separate scope.
+#[derive(Debug)]
+pub struct ReadOnlyStore<S>(pub S);
+
+impl<S: VariableStore> VariableStore for ReadOnlyStore<S> {
+    fn get(&self, var: &str) -> Option<Cow<'_, Value>> {
+        self.0.get(var)
+    }
+
+    fn set(&mut self, var: &str, _val: Value) -> Result<(), CalcError> {
+        Err(CalcError::ReadOnlyVariable(var.to_string()))
+    }
+}
+
+/// A scope on top of a parent store. Variables are looked up in the scope first,
+/// then in the parent. Assignments only change the scope, the parent is never modified.
+pub struct LayeredStore<'a> {
+    pub scope: HashMap<String, Value>,
+    parent: &'a dyn VariableStore,
+}
+
+impl<'a> LayeredStore<'a> {
+    pub fn new(parent: &'a dyn VariableStore) -> Self {
+        LayeredStore {
+            scope: HashMap::new(),
+            parent,
+        }
+    }
+}
+
+impl VariableStore for LayeredStore<'_> {
+    fn get(&self, var: &str) -> Option<Cow<'_, Value>> {
+        VariableStore::get(&self.scope, var).or_else(|| self.parent.get(var))
+    }
+
+    fn set(&mut self, var: &str, val: Value) -> Result<(), CalcError> {
+        self.scope.set(var, val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigInt;
+
+    use super::*;
+
+    /// The value if the store borrowed it, stores of maps must not clone their values
+    fn borrowed(val: Option<Cow<'_, Value>>) -> Option<&Value> {
+        match val {
+            Some(Cow::Borrowed(val)) => Some(val),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_read_only_store() {
+        let mut store = ReadOnlyStore(HashMap::from([(
+            String::from("a"),
+            Value::Int(BigInt::from(1)),
+        )]));
+
+        assert_eq!(borrowed(store.get("a")), Some(&Value::Int(BigInt::from(1))));
+        assert_eq!(
+            store.set("a", Value::Int(BigInt::from(2))),
+            Err(CalcError::ReadOnlyVariable(String::from("a")))
+        );
+        assert_eq!(borrowed(store.get("a")), Some(&Value::Int(BigInt::from(1))));
+    }
+
+    #[test]
+    fn test_layered_store() {
+        let parent = ReadOnlyStore(HashMap::from([
+            (String::from("a"), Value::Int(BigInt::from(1))),
+            (String::from("b"), Value::Int(BigInt::from(2))),
+        ]));
+        let mut store = LayeredStore::new(&parent);
+
+        store.set("a", Value::Int(BigInt::from(10))).unwrap();
+        assert_eq!(
+            borrowed(store.get("a")),
+            Some(&Value::Int(BigInt::from(10)))
+        );
+        assert_eq!(borrowed(store.get("b")), Some(&Value::Int(BigInt::from(2))));
+        assert_eq!(store.get("c"), None);
+        assert_eq!(
+            borrowed(parent.get("a")),
+            Some(&Value::Int(BigInt::from(1)))
+        );
+    }
+
+    #[test]
+    fn test_layered_store_nested() {
+        let root = HashMap::from([(String::from("a"), Value::Int(BigInt::from(1)))]);
+        let mut middle = LayeredStore::new(&root);
+        middle.set("b", Value::Int(BigInt::from(2))).unwrap();
+        let inner = LayeredStore::new(&middle);
+
+        assert_eq!(borrowed(inner.get("a")), Some(&Value::Int(BigInt::from(1))));
+        assert_eq!(borrowed(inner.get("b")), Some(&Value::Int(BigInt::from(2))));
+    }
+}